@@ -0,0 +1,178 @@
+use crate::text_file::{write_text_file, TextFormat};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Payload of the `autosave-done` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutosaveDone {
+    pub path: String,
+    /// Set if the write failed
+    pub error: Option<String>,
+}
+
+// Callback run with the outcome of a save
+type DoneCallback = Box<dyn FnOnce(AutosaveDone) + Send>;
+
+// The latest content waiting to be written to a path
+pub struct PendingSave {
+    content: String,
+    format: Option<TextFormat>,
+    due: Instant,
+    on_done: DoneCallback,
+}
+
+// Saves waiting for their debounce to pass; a path has an entry exactly
+// while a worker thread is waiting to write it
+pub type AutosaveStateHandle = Arc<Mutex<HashMap<PathBuf, PendingSave>>>;
+
+pub fn create_autosave_state() -> AutosaveStateHandle {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+/// Write `content` to `path` once `debounce` passes with no newer content for it
+///
+/// Each call replaces the save still pending for the same path and pushes
+/// back its deadline, so during rapid typing only the last content is
+/// written. One worker thread waits per path with a pending save. The file
+/// is written in `format`, or without one in the encoding and line ending
+/// it already has. `on_done` is called after the write with its outcome;
+/// the callbacks of replaced saves are dropped without being called.
+pub fn schedule_autosave(
+    state: &AutosaveStateHandle,
+    path: PathBuf,
+    content: String,
+    format: Option<TextFormat>,
+    debounce: Duration,
+    on_done: impl FnOnce(AutosaveDone) + Send + 'static,
+) {
+    let save = PendingSave {
+        content,
+        format,
+        due: Instant::now() + debounce,
+        on_done: Box::new(on_done),
+    };
+    let waiting = state.lock().unwrap_or_else(|e| e.into_inner()).insert(path.clone(), save).is_some();
+    if waiting {
+        return;
+    }
+
+    let state = Arc::clone(state);
+    thread::spawn(move || wait_and_save(&state, &path));
+}
+
+// Sleep until the path's pending save is due, following any pushed back
+// deadline, then write it
+fn wait_and_save(state: &AutosaveStateHandle, path: &Path) {
+    let save = loop {
+        let mut pending = state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(due) = pending.get(path).map(|save| save.due) else {
+            return;
+        };
+        let now = Instant::now();
+        if due <= now {
+            break pending.remove(path);
+        }
+        drop(pending);
+        thread::sleep(due - now);
+    };
+    let Some(save) = save else {
+        return;
+    };
+
+    let format = save.format.unwrap_or_else(|| TextFormat::of_file(path));
+    let result = write_text_file(path, &save.content, &format.encoding, format.bom, format.line_ending);
+    (save.on_done)(AutosaveDone {
+        path: path.to_string_lossy().to_string(),
+        error: result.err(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_only_last_content_in_window_is_written() {
+        let dir = std::env::temp_dir().join(format!("loom-autosave-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        let state = create_autosave_state();
+        let (sender, receiver) = mpsc::channel();
+
+        // A long debounce keeps all three calls inside one window
+        for content in ["a", "ab", "abc"] {
+            let sender = sender.clone();
+            schedule_autosave(&state, path.clone(), content.to_string(), None, Duration::from_millis(500), move |done| {
+                sender.send(done).unwrap();
+            });
+        }
+        drop(sender);
+
+        let done = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        // Every other callback was dropped unused, so the channel is closed
+        let extra = receiver.recv();
+        let written = fs::read_to_string(&path).unwrap();
+        let leftovers: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(done.path, path.to_string_lossy());
+        assert!(done.error.is_none());
+        assert!(extra.is_err());
+        assert_eq!(written, "abc");
+        assert_eq!(leftovers, ["note.md"]);
+        assert!(state.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_after_a_write_starts_a_new_worker() {
+        let dir = std::env::temp_dir().join(format!("loom-autosave-again-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        let state = create_autosave_state();
+        let (sender, receiver) = mpsc::channel();
+        let schedule = |content: &str| {
+            let sender = sender.clone();
+            schedule_autosave(&state, path.clone(), content.to_string(), None, Duration::ZERO, move |done| {
+                sender.send(done).unwrap();
+            });
+        };
+
+        schedule("first");
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        let first = fs::read_to_string(&path).unwrap();
+        schedule("second");
+        receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        let second = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, "first");
+        assert_eq!(second, "second");
+    }
+
+    #[test]
+    fn test_autosave_keeps_encoding_and_line_endings() {
+        let dir = std::env::temp_dir().join(format!("loom-autosave-encoding-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        fs::write(&path, b"\xFF\xFEo\x00l\x00d\x00\r\x00\n\x00").unwrap();
+        let state = create_autosave_state();
+        let (sender, receiver) = mpsc::channel();
+
+        schedule_autosave(&state, path.clone(), "a\nb".to_string(), None, Duration::ZERO, move |done| {
+            sender.send(done).unwrap();
+        });
+        let done = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        let written = fs::read(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(done.error.is_none());
+        assert_eq!(written, b"\xFF\xFEa\x00\r\x00\n\x00b\x00");
+    }
+}
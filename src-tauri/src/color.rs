@@ -0,0 +1,166 @@
+/**
+ * Color utilities for theme generation and checking
+ *
+ * Colors are handled as hex strings in themes; this module converts them
+ * to RGB/HSL for manipulation and computes WCAG contrast ratios.
+ */
+
+/// An sRGB color with 8-bit channels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+/// A color in HSL space (hue in degrees, saturation and lightness in 0-1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hsl {
+    pub h: f64,
+    pub s: f64,
+    pub l: f64,
+}
+
+/// Parse a `#rgb` or `#rrggbb` hex color (the `#` is optional)
+pub fn parse_hex(hex: &str) -> Result<Rgb, String> {
+    let digits = hex.trim().trim_start_matches('#');
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex color '{}'", hex));
+    }
+
+    let expanded: String = match digits.len() {
+        3 => digits.chars().flat_map(|c| [c, c]).collect(),
+        6 => digits.to_string(),
+        _ => return Err(format!("Invalid hex color '{}'", hex)),
+    };
+
+    let channel = |i: usize| {
+        u8::from_str_radix(&expanded[i..i + 2], 16)
+            .map_err(|_| format!("Invalid hex color '{}'", hex))
+    };
+
+    Ok(Rgb {
+        r: channel(0)?,
+        g: channel(2)?,
+        b: channel(4)?,
+    })
+}
+
+impl Rgb {
+    /// Format as a lowercase `#rrggbb` string
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    pub fn to_hsl(self) -> Hsl {
+        let r = self.r as f64 / 255.0;
+        let g = self.g as f64 / 255.0;
+        let b = self.b as f64 / 255.0;
+
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let l = (max + min) / 2.0;
+        let delta = max - min;
+
+        if delta == 0.0 {
+            return Hsl { h: 0.0, s: 0.0, l };
+        }
+
+        let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+        let h = if max == r {
+            60.0 * (((g - b) / delta).rem_euclid(6.0))
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+
+        Hsl { h, s, l }
+    }
+
+    /// WCAG relative luminance
+    pub fn relative_luminance(self) -> f64 {
+        let linear = |c: u8| {
+            let c = c as f64 / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        0.2126 * linear(self.r) + 0.7152 * linear(self.g) + 0.0722 * linear(self.b)
+    }
+}
+
+impl Hsl {
+    pub fn to_rgb(self) -> Rgb {
+        let h = self.h.rem_euclid(360.0);
+        let s = self.s.clamp(0.0, 1.0);
+        let l = self.l.clamp(0.0, 1.0);
+
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let x = c * (1.0 - ((h / 60.0).rem_euclid(2.0) - 1.0).abs());
+        let m = l - c / 2.0;
+
+        let (r, g, b) = match h as u32 {
+            0..=59 => (c, x, 0.0),
+            60..=119 => (x, c, 0.0),
+            120..=179 => (0.0, c, x),
+            180..=239 => (0.0, x, c),
+            240..=299 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        let channel = |v: f64| ((v + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+        Rgb {
+            r: channel(r),
+            g: channel(g),
+            b: channel(b),
+        }
+    }
+
+    /// Same color with the hue rotated and saturation/lightness replaced
+    pub fn with(self, hue_shift: f64, s: f64, l: f64) -> Hsl {
+        Hsl {
+            h: self.h + hue_shift,
+            s,
+            l,
+        }
+    }
+}
+
+/// WCAG contrast ratio between two colors (1.0 to 21.0)
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let la = a.relative_luminance();
+    let lb = b.relative_luminance();
+    let (lighter, darker) = if la > lb { (la, lb) } else { (lb, la) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(parse_hex("#007acc").unwrap(), Rgb { r: 0, g: 122, b: 204 });
+        assert_eq!(parse_hex("fff").unwrap(), Rgb { r: 255, g: 255, b: 255 });
+        assert!(parse_hex("#12345").is_err());
+        assert!(parse_hex("#zzzzzz").is_err());
+    }
+
+    #[test]
+    fn test_hsl_round_trip() {
+        let color = parse_hex("#569cd6").unwrap();
+        assert_eq!(color.to_hsl().to_rgb(), color);
+    }
+
+    #[test]
+    fn test_contrast_ratio() {
+        let black = parse_hex("#000000").unwrap();
+        let white = parse_hex("#ffffff").unwrap();
+        assert!((contrast_ratio(black, white) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio(white, white) - 1.0).abs() < 0.01);
+    }
+}
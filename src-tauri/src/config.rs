@@ -1,7 +1,17 @@
+use crate::color::{contrast_ratio, parse_hex, Hsl};
+use crate::file_ops::validate_file_name;
+use crate::front_matter::parse_front_matter;
+use crate::markdown::RenderOptions;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
 
 /// Theme configuration with all CSS variables
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -9,47 +19,283 @@ pub struct ThemeConfig {
     pub name: String,
     pub author: Option<String>,
     pub version: Option<String>,
+    /// Name of a base theme whose variables this theme overrides
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
     pub variables: HashMap<String, String>,
 }
 
+/// Theme variables every complete theme defines (the keys of the built-in themes)
+pub const REQUIRED_THEME_VARIABLES: &[&str] = &[
+    "bg-primary",
+    "bg-secondary",
+    "bg-tertiary",
+    "text-primary",
+    "text-secondary",
+    "border-color",
+    "accent-color",
+    "accent-hover",
+    "heading-color",
+    "h1-color",
+    "h2-color",
+    "h3-color",
+    "h4-color",
+    "h5-color",
+    "h6-color",
+    "code-bg",
+    "code-color",
+    "link-color",
+    "blockquote-border",
+    "blockquote-bg",
+    "table-border",
+    "table-header-bg",
+    "list-marker",
+    "hr-color",
+];
+
+/// Variables without which a theme is unusable, so importing it is refused
+const CRITICAL_THEME_VARIABLES: &[&str] = &["bg-primary", "text-primary", "accent-color"];
+
+// Characters that could end a declaration or the <style> element theme variables are written into
+const UNSAFE_CSS_CHARS: &[char] = &['<', '>', '{', '}', ';'];
+
+/// A loaded theme plus a warning when it had to fall back to a default
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThemeLoadResult {
+    pub theme: ThemeConfig,
+    pub warning: Option<String>,
+}
+
+/// Minimum WCAG contrast ratio for normal text (level AA)
+const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+/// Current config.json schema version; bump this and add a step to
+/// `migrate_config` whenever fields are added or renamed
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
+/// Foreground/background variable pairs checked for readable contrast
+const CONTRAST_PAIRS: &[(&str, &str)] = &[
+    ("text-primary", "bg-primary"),
+    ("text-secondary", "bg-primary"),
+    ("heading-color", "bg-primary"),
+    ("h1-color", "bg-primary"),
+    ("h2-color", "bg-primary"),
+    ("h3-color", "bg-primary"),
+    ("h4-color", "bg-primary"),
+    ("h5-color", "bg-primary"),
+    ("h6-color", "bg-primary"),
+    ("link-color", "bg-primary"),
+    ("code-color", "code-bg"),
+    ("text-primary", "blockquote-bg"),
+];
+
+/// A foreground/background pair whose contrast is too low to read comfortably
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContrastWarning {
+    pub foreground: String,
+    pub background: String,
+    pub ratio: f64,
+}
+
 /// Application settings stored in config.json
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AppConfig {
+    // Missing in files written before versioning, which are version 0
+    #[serde(default)]
+    pub schema_version: u32,
     pub current_theme: String,
     #[serde(default = "default_status_bar_visible")]
     pub status_bar_visible: bool,
-    #[serde(default)]
+    /// Key chord for each action id; actions missing here get their default
+    #[serde(default = "default_keybinds")]
     pub keybinds: HashMap<String, String>,
     #[serde(default = "default_true")]
     pub confirm_file_delete: bool,
     #[serde(default = "default_true")]
     pub confirm_folder_delete: bool,
+    #[serde(default = "default_true")]
+    pub use_trash: bool,
+    /// Render math blocks to MathML in the backend instead of with KaTeX in the webview
+    #[serde(default)]
+    pub server_side_math: bool,
+    /// Horizontal rule style in view mode: "solid", "dashed" or "dashes"
+    #[serde(default = "default_hr_style")]
+    pub hr_style: String,
+    /// Show HTML typed into paragraphs, sanitized, instead of escaping it
+    #[serde(default)]
+    pub allow_raw_html: bool,
+    /// In view mode, show a run of blank lines as a single break
+    #[serde(default)]
+    pub collapse_blank_lines: bool,
+    /// Unordered list bullets by nesting level, cycled; empty means "•"
+    #[serde(default)]
+    pub bullet_glyphs: Vec<String>,
+    /// Editor font as a CSS font-family list
+    #[serde(default = "default_font_family")]
+    pub font_family: String,
+    /// Editor font size in pixels (6-72)
+    #[serde(default = "default_font_size")]
+    pub font_size: u16,
+    /// Editor line height as a multiple of the font size (1.0-3.0)
+    #[serde(default = "default_line_height")]
+    pub line_height: f32,
+    /// Columns per tab stop (1-16)
+    #[serde(default = "default_tab_size")]
+    pub tab_size: u8,
+    #[serde(default = "default_true")]
+    pub word_wrap: bool,
     #[serde(default)]
     pub custom_settings: HashMap<String, serde_json::Value>,
+    /// Setting overrides keyed by file path relative to the folder
+    #[serde(default)]
+    pub per_file_settings: HashMap<String, serde_json::Value>,
+}
+
+impl AppConfig {
+    /// The settings that change how lines are rendered
+    pub fn render_options(&self) -> RenderOptions {
+        RenderOptions {
+            server_side_math: self.server_side_math,
+            hr_style: self.hr_style.clone(),
+            allow_raw_html: self.allow_raw_html,
+            collapse_blank_lines: self.collapse_blank_lines,
+            bullet_glyphs: self.bullet_glyphs.clone(),
+            tab_size: self.tab_size,
+        }
+    }
+}
+
+/// A single setting value along with where it came from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EffectiveSetting {
+    pub value: serde_json::Value,
+    /// One of "default", "config" or "front-matter"
+    pub source: String,
+}
+
+/// The merged settings actually in effect, keyed by setting name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EffectiveConfig {
+    pub settings: BTreeMap<String, EffectiveSetting>,
 }
 
 fn default_status_bar_visible() -> bool {
     true
 }
 
+fn default_hr_style() -> String {
+    "solid".to_string()
+}
+
 fn default_true() -> bool {
     true
 }
 
+fn default_font_family() -> String {
+    "-apple-system, BlinkMacSystemFont, \"Segoe UI\", Roboto, Oxygen, Ubuntu, Cantarell, sans-serif".to_string()
+}
+
+fn default_font_size() -> u16 {
+    16
+}
+
+fn default_line_height() -> f32 {
+    1.7
+}
+
+fn default_tab_size() -> u8 {
+    4
+}
+
+// Default chord for each keybind action, matching KEYBIND_ACTIONS in the frontend
+const DEFAULT_KEYBINDS: &[(&str, &str)] = &[
+    ("save-file", "Ctrl+S"),
+    ("open-file", "Ctrl+O"),
+    ("new-file", "Ctrl+N"),
+    ("open-folder", "Ctrl+K Ctrl+O"),
+    ("toggle-edit-mode", "Ctrl+E"),
+    ("select-all", "Ctrl+A"),
+    ("undo", "Ctrl+Z"),
+    ("redo", "Ctrl+Y"),
+    ("copy", "Ctrl+C"),
+    ("cut", "Ctrl+X"),
+    ("paste", "Ctrl+V"),
+    ("find", "Ctrl+F"),
+    ("replace", "Ctrl+H"),
+    ("bold", "Ctrl+B"),
+    ("italic", "Ctrl+I"),
+    ("strikethrough", "Ctrl+Shift+X"),
+    ("insert-link", "Ctrl+K"),
+    ("insert-code", "Ctrl+`"),
+    ("insert-code-block", "Ctrl+Shift+`"),
+    ("increase-heading", "Ctrl+Shift+]"),
+    ("decrease-heading", "Ctrl+Shift+["),
+    ("toggle-sidebar", "Ctrl+\\"),
+    ("toggle-status-bar", "Ctrl+Shift+B"),
+    ("zoom-in", "Ctrl+="),
+    ("zoom-out", "Ctrl+-"),
+    ("reset-zoom", "Ctrl+0"),
+    ("settings", "Ctrl+,"),
+    ("close-window", "Ctrl+W"),
+    ("minimize-window", "Ctrl+Shift+M"),
+    ("maximize-window", "F11"),
+];
+
+/// The built-in keybinds, keyed by action id
+pub fn default_keybinds() -> HashMap<String, String> {
+    DEFAULT_KEYBINDS
+        .iter()
+        .map(|(action, chord)| (action.to_string(), chord.to_string()))
+        .collect()
+}
+
+/// Apply user keybinds over the defaults, so actions added since the
+/// config was saved still get a binding. An empty chord leaves an action unbound.
+fn merge_keybinds(overrides: HashMap<String, String>) -> HashMap<String, String> {
+    let mut keybinds = default_keybinds();
+    keybinds.extend(overrides);
+    keybinds
+}
+
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            schema_version: CONFIG_SCHEMA_VERSION,
             current_theme: "dark".to_string(),
             status_bar_visible: true,
-            keybinds: HashMap::new(),
+            keybinds: default_keybinds(),
             confirm_file_delete: true,
             confirm_folder_delete: true,
+            use_trash: true,
+            server_side_math: false,
+            hr_style: default_hr_style(),
+            allow_raw_html: false,
+            collapse_blank_lines: false,
+            bullet_glyphs: Vec::new(),
+            font_family: default_font_family(),
+            font_size: default_font_size(),
+            line_height: default_line_height(),
+            tab_size: default_tab_size(),
+            word_wrap: true,
             custom_settings: HashMap::new(),
+            per_file_settings: HashMap::new(),
         }
     }
 }
 
+/// Directory for settings used when no folder is open: `loom` in the OS
+/// config directory (e.g. `~/.config/loom` on Linux)
+pub fn app_config_dir() -> Result<PathBuf, String> {
+    dirs::config_dir()
+        .map(|dir| dir.join("loom"))
+        .ok_or_else(|| "Could not find the app config directory".to_string())
+}
+
 /// Get the path to the .loom directory in the specified folder
+///
+/// With no folder, the app config directory is used instead, laid out like
+/// a .loom directory and created on first use.
 pub fn get_loom_dir(folder_path: Option<String>) -> Result<PathBuf, String> {
     match folder_path {
         Some(path) => {
@@ -62,16 +308,27 @@ pub fn get_loom_dir(folder_path: Option<String>) -> Result<PathBuf, String> {
             }
             Ok(folder.join(".loom"))
         }
-        None => Err("No folder path provided".to_string()),
+        None => prepare_app_config_dir(app_config_dir()?),
+    }
+}
+
+/// Set up `dir` like a .loom directory the first time it's used for settings
+fn prepare_app_config_dir(dir: PathBuf) -> Result<PathBuf, String> {
+    if !dir.join("config.json").exists() {
+        initialize_dir(&dir)?;
     }
+    Ok(dir)
 }
 
 /// Initialize the .loom directory structure in the specified folder
 pub fn initialize_loom_dir(folder_path: Option<String>) -> Result<(), String> {
-    let loom_dir = get_loom_dir(folder_path)?;
+    initialize_dir(&get_loom_dir(folder_path)?)
+}
 
+/// Create the .loom layout, default config and built-in themes in a directory
+fn initialize_dir(loom_dir: &Path) -> Result<(), String> {
     // Create main .loom directory
-    fs::create_dir_all(&loom_dir)
+    fs::create_dir_all(loom_dir)
         .map_err(|e| format!("Failed to create .loom directory: {}", e))?;
 
     // Create subdirectories
@@ -79,6 +336,7 @@ pub fn initialize_loom_dir(folder_path: Option<String>) -> Result<(), String> {
     let builtin_themes_dir = themes_dir.join("built-in");
     let custom_themes_dir = themes_dir.join("custom");
     let plugins_dir = loom_dir.join("plugins");
+    let templates_dir = loom_dir.join("templates");
 
     fs::create_dir_all(&builtin_themes_dir)
         .map_err(|e| format!("Failed to create themes/built-in directory: {}", e))?;
@@ -86,6 +344,8 @@ pub fn initialize_loom_dir(folder_path: Option<String>) -> Result<(), String> {
         .map_err(|e| format!("Failed to create themes/custom directory: {}", e))?;
     fs::create_dir_all(&plugins_dir)
         .map_err(|e| format!("Failed to create plugins directory: {}", e))?;
+    fs::create_dir_all(&templates_dir)
+        .map_err(|e| format!("Failed to create templates directory: {}", e))?;
 
     // Create default config.json if it doesn't exist
     let config_path = loom_dir.join("config.json");
@@ -129,7 +389,7 @@ fn create_builtin_themes(builtin_dir: &PathBuf) -> Result<(), String> {
 }
 
 /// Get default dark theme configuration
-fn get_default_dark_theme() -> ThemeConfig {
+pub fn get_default_dark_theme() -> ThemeConfig {
     let mut variables = HashMap::new();
 
     // Base colors
@@ -166,12 +426,13 @@ fn get_default_dark_theme() -> ThemeConfig {
         name: "Dark".to_string(),
         author: Some("Loom.md".to_string()),
         version: Some("1.0.0".to_string()),
+        extends: None,
         variables,
     }
 }
 
 /// Get default light theme configuration
-fn get_default_light_theme() -> ThemeConfig {
+pub fn get_default_light_theme() -> ThemeConfig {
     let mut variables = HashMap::new();
 
     // Base colors
@@ -208,6 +469,7 @@ fn get_default_light_theme() -> ThemeConfig {
         name: "Light".to_string(),
         author: Some("Loom.md".to_string()),
         version: Some("1.0.0".to_string()),
+        extends: None,
         variables,
     }
 }
@@ -221,29 +483,415 @@ pub fn load_app_config(folder_path: Option<String>) -> Result<AppConfig, String>
         return Ok(AppConfig::default());
     }
 
-    let content = fs::read_to_string(&config_path)
+    let error = match read_config_file(&config_path) {
+        Ok(config) => return Ok(config),
+        Err(e) => e,
+    };
+    eprintln!("{}", error);
+
+    // Try the backup written by the last successful save
+    let backup_path = loom_dir.join("config.json.bak");
+    if backup_path.exists() {
+        match read_config_file(&backup_path) {
+            Ok(config) => {
+                eprintln!("Recovered config from {}", backup_path.display());
+                return Ok(config);
+            }
+            Err(e) => eprintln!("Failed to recover config from backup: {}", e),
+        }
+    }
+
+    Ok(AppConfig::default())
+}
+
+/// Read and migrate a config file, writing the upgraded version back
+fn read_config_file(path: &Path) -> Result<AppConfig, String> {
+    let content = fs::read_to_string(path)
         .map_err(|e| format!("Failed to read config file: {}", e))?;
 
-    serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse config file: {}", e))
+    let value: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+
+    // Upgrade older config files and write the upgraded version back
+    let migrated = migrate_config(value.clone());
+    if migrated != value {
+        let json = serde_json::to_string_pretty(&migrated)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
+        fs::write(path, json)
+            .map_err(|e| format!("Failed to write config file: {}", e))?;
+    }
+
+    let mut config: AppConfig = serde_json::from_value(migrated)
+        .map_err(|e| format!("Failed to parse config file: {}", e))?;
+    config.keybinds = merge_keybinds(config.keybinds);
+    reset_invalid_appearance(&mut config);
+
+    Ok(config)
+}
+
+/// Upgrade a raw config.json value to the current schema version
+///
+/// A missing `schema_version` is treated as version 0. Each step only
+/// touches the keys it's responsible for so user values are preserved.
+pub fn migrate_config(mut value: serde_json::Value) -> serde_json::Value {
+    let Some(object) = value.as_object_mut() else {
+        return value;
+    };
+
+    let version = object
+        .get("schema_version")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0) as u32;
+
+    if version >= CONFIG_SCHEMA_VERSION {
+        return value;
+    }
+
+    // Version 0 -> 1: added use_trash
+    if version < 1 {
+        object
+            .entry("use_trash")
+            .or_insert(serde_json::Value::Bool(default_true()));
+    }
+
+    object.insert("schema_version".to_string(), CONFIG_SCHEMA_VERSION.into());
+    value
+}
+
+/// Key used for a file in `per_file_settings`: its path relative to the
+/// folder, with forward slashes so configs are portable across platforms
+fn file_settings_key(folder: &str, file_path: &str) -> String {
+    let path = Path::new(file_path);
+    let relative = path.strip_prefix(folder).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+/// Get the settings in effect for a file
+///
+/// Starts from the global config values and applies the file's overrides
+/// on top, so files without overrides just get the global settings.
+pub fn get_file_settings(folder_path: Option<String>, file_path: String) -> Result<HashMap<String, serde_json::Value>, String> {
+    let folder = folder_path.clone().unwrap_or_default();
+    let config = load_app_config(folder_path)?;
+    let key = file_settings_key(&folder, &file_path);
+
+    let mut settings: HashMap<String, serde_json::Value> = config_to_object(&config)?.into_iter().collect();
+    settings.remove("per_file_settings");
+
+    if let Some(serde_json::Value::Object(overrides)) = config.per_file_settings.get(&key) {
+        settings.extend(overrides.clone());
+    }
+
+    Ok(settings)
+}
+
+/// Replace a file's setting overrides; an empty map removes them
+pub fn set_file_settings(folder_path: Option<String>, file_path: String, settings: HashMap<String, serde_json::Value>) -> Result<(), String> {
+    let folder = folder_path.clone().unwrap_or_default();
+    let mut config = load_app_config(folder_path.clone())?;
+    let key = file_settings_key(&folder, &file_path);
+
+    if settings.is_empty() {
+        config.per_file_settings.remove(&key);
+    } else {
+        let overrides = settings.into_iter().collect();
+        config.per_file_settings.insert(key, serde_json::Value::Object(overrides));
+    }
+
+    save_app_config(folder_path, &config)
+}
+
+/// Render settings from the folder's config, or the defaults if it can't be loaded
+pub fn load_render_options(folder_path: Option<String>) -> RenderOptions {
+    match load_app_config(folder_path) {
+        Ok(config) => config.render_options(),
+        Err(e) => {
+            eprintln!("Failed to load render settings: {}", e);
+            RenderOptions::default()
+        }
+    }
+}
+
+// Bumped on every config save, so cached render settings can tell they are stale
+static CONFIG_SAVES: AtomicU64 = AtomicU64::new(0);
+
+/// Render settings as last loaded, with the folder and config save they came from
+pub struct CachedRenderOptions {
+    folder_path: Option<String>,
+    saves: u64,
+    options: RenderOptions,
+}
+
+// Render settings shared by the render commands, so a render doesn't re-read config.json
+pub type RenderOptionsHandle = Arc<Mutex<Option<CachedRenderOptions>>>;
+
+pub fn create_render_options_state() -> RenderOptionsHandle {
+    Arc::new(Mutex::new(None))
+}
+
+/// Render settings for a folder, loaded again only when the folder changes
+/// or the config has been saved since the last load
+pub fn cached_render_options(cache: &RenderOptionsHandle, folder_path: Option<String>) -> RenderOptions {
+    let saves = CONFIG_SAVES.load(Ordering::Acquire);
+    let mut cached = cache.lock().unwrap();
+    if let Some(hit) = cached.as_ref().filter(|c| c.folder_path == folder_path && c.saves == saves) {
+        return hit.options.clone();
+    }
+
+    let options = load_render_options(folder_path.clone());
+    *cached = Some(CachedRenderOptions { folder_path, saves, options: options.clone() });
+    options
+}
+
+/// Whether deleted files should go to the system trash
+///
+/// Falls back to the default (the trash) if the config can't be loaded.
+pub fn load_use_trash(folder_path: Option<String>) -> bool {
+    match load_app_config(folder_path) {
+        Ok(config) => config.use_trash,
+        Err(e) => {
+            eprintln!("Failed to load delete settings: {}", e);
+            AppConfig::default().use_trash
+        }
+    }
+}
+
+/// Save settings given as a partial config object on top of the saved config
+///
+/// The frontend only sends the settings it manages, so keys missing from
+/// `changes` keep their saved values, including per-file overrides.
+pub fn update_app_config(folder_path: Option<String>, changes: serde_json::Value) -> Result<(), String> {
+    let serde_json::Value::Object(changes) = changes else {
+        return Err("Config update must be an object".to_string());
+    };
+
+    let mut merged = config_to_object(&load_app_config(folder_path.clone())?)?;
+    merged.extend(changes);
+    let config: AppConfig = serde_json::from_value(serde_json::Value::Object(merged))
+        .map_err(|e| format!("Failed to parse config update: {}", e))?;
+
+    save_app_config(folder_path, &config)
+}
+
+/// A config as a JSON object keyed by setting name
+fn config_to_object(config: &AppConfig) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    match serde_json::to_value(config) {
+        Ok(serde_json::Value::Object(map)) => Ok(map),
+        Ok(_) => Err("Config did not serialize to an object".to_string()),
+        Err(e) => Err(format!("Failed to serialize config: {}", e)),
+    }
+}
+
+// Usable ranges of the editor appearance settings
+const FONT_SIZE_RANGE: RangeInclusive<u16> = 6..=72;
+const LINE_HEIGHT_RANGE: RangeInclusive<f32> = 1.0..=3.0;
+const TAB_SIZE_RANGE: RangeInclusive<u8> = 1..=16;
+
+/// Check that the editor appearance settings are within usable ranges
+fn validate_app_config(config: &AppConfig) -> Result<(), String> {
+    if config.font_family.trim().is_empty() {
+        return Err("Font family cannot be empty".to_string());
+    }
+    if !FONT_SIZE_RANGE.contains(&config.font_size) {
+        return Err(format!("Font size must be between 6 and 72, got {}", config.font_size));
+    }
+    if !LINE_HEIGHT_RANGE.contains(&config.line_height) {
+        return Err(format!("Line height must be between 1.0 and 3.0, got {}", config.line_height));
+    }
+    if !TAB_SIZE_RANGE.contains(&config.tab_size) {
+        return Err(format!("Tab size must be between 1 and 16, got {}", config.tab_size));
+    }
+    Ok(())
+}
+
+/// Put appearance settings outside their usable ranges back to the defaults
+///
+/// Applied when loading, so a hand-edited value that `save_app_config`
+/// would reject can't make every later settings change fail.
+fn reset_invalid_appearance(config: &mut AppConfig) {
+    let defaults = AppConfig::default();
+    if let Err(e) = validate_app_config(config) {
+        eprintln!("Invalid config value, using the default instead: {}", e);
+    }
+
+    if config.font_family.trim().is_empty() {
+        config.font_family = defaults.font_family;
+    }
+    if !FONT_SIZE_RANGE.contains(&config.font_size) {
+        config.font_size = defaults.font_size;
+    }
+    if !LINE_HEIGHT_RANGE.contains(&config.line_height) {
+        config.line_height = defaults.line_height;
+    }
+    if !TAB_SIZE_RANGE.contains(&config.tab_size) {
+        config.tab_size = defaults.tab_size;
+    }
 }
 
 /// Save application config to the specified folder
+///
+/// Out-of-range appearance settings are rejected and nothing is written.
 pub fn save_app_config(folder_path: Option<String>, config: &AppConfig) -> Result<(), String> {
+    validate_app_config(config)?;
     let loom_dir = get_loom_dir(folder_path)?;
     let config_path = loom_dir.join("config.json");
 
     let json = serde_json::to_string_pretty(config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
+    // Keep a copy of the previous config, unless it's corrupt and would
+    // replace a good backup
+    if let Ok(previous) = fs::read_to_string(&config_path) {
+        if serde_json::from_str::<serde_json::Value>(&previous).is_ok() {
+            fs::write(loom_dir.join("config.json.bak"), previous)
+                .map_err(|e| format!("Failed to back up config file: {}", e))?;
+        }
+    }
+
     fs::write(&config_path, json)
-        .map_err(|e| format!("Failed to write config file: {}", e))
+        .map_err(|e| format!("Failed to write config file: {}", e))?;
+    CONFIG_SAVES.fetch_add(1, Ordering::Release);
+    Ok(())
+}
+
+/// Two or more actions bound to the same chord
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeybindConflict {
+    pub chord: String,
+    /// Action ids in alphabetical order
+    pub actions: Vec<String>,
+}
+
+/// Canonical form of a chord, so "Shift+Ctrl+x" and "Ctrl+Shift+X" compare equal
+///
+/// Each space-separated step keeps its key last, with the modifiers before
+/// it sorted. A trailing `+` is the plus key itself, as in "Ctrl++".
+fn normalize_chord(chord: &str) -> String {
+    chord
+        .split_whitespace()
+        .map(|step| {
+            let step = step.to_lowercase();
+            let (modifiers, key) = match step.strip_suffix("++") {
+                Some(modifiers) => (modifiers.to_string(), "+".to_string()),
+                None => match step.rsplit_once('+') {
+                    Some((modifiers, key)) => (modifiers.to_string(), key.to_string()),
+                    None => (String::new(), step.clone()),
+                },
+            };
+            let mut parts: Vec<&str> = modifiers.split('+').filter(|m| !m.is_empty()).collect();
+            parts.sort_unstable();
+            parts.push(&key);
+            parts.join("+")
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Find chords bound to more than one action, ignoring unbound actions
+///
+/// Each conflict shows the chord as the first of its actions wrote it.
+pub fn validate_keybinds(keybinds: &HashMap<String, String>) -> Vec<KeybindConflict> {
+    let sorted: BTreeMap<&String, &String> = keybinds.iter().collect();
+    let mut by_chord: BTreeMap<String, (String, Vec<String>)> = BTreeMap::new();
+    for (action, chord) in sorted {
+        let normalized = normalize_chord(chord);
+        if normalized.is_empty() {
+            continue;
+        }
+        by_chord
+            .entry(normalized)
+            .or_insert_with(|| (chord.clone(), Vec::new()))
+            .1
+            .push(action.clone());
+    }
+
+    by_chord
+        .into_values()
+        .filter(|(_, actions)| actions.len() > 1)
+        .map(|(chord, actions)| KeybindConflict { chord, actions })
+        .collect()
+}
+
+/// Replace all keybinds with the defaults, returning them
+pub fn reset_keybinds(folder_path: Option<String>) -> Result<HashMap<String, String>, String> {
+    let mut config = load_app_config(folder_path.clone())?;
+    config.keybinds = default_keybinds();
+    save_app_config(folder_path, &config)?;
+    Ok(config.keybinds)
 }
 
 /// Load a theme by name from the specified folder
+///
+/// If the theme extends a base theme, the base is loaded first and this
+/// theme's variables are merged on top of it.
 pub fn load_theme(folder_path: Option<String>, theme_name: &str) -> Result<ThemeConfig, String> {
     let loom_dir = get_loom_dir(folder_path)?;
+    let mut visited = Vec::new();
+    let theme = load_theme_with_bases(&loom_dir, theme_name, &mut visited)?;
+
+    let missing = validate_theme(&theme);
+    if !missing.is_empty() {
+        eprintln!("Theme '{}' is missing variables: {}", theme_name, missing.join(", "));
+    }
+
+    Ok(theme)
+}
+
+/// Check a theme for missing required variables
+///
+/// Returns the required variable names the theme doesn't define.
+pub fn validate_theme(theme: &ThemeConfig) -> Vec<String> {
+    REQUIRED_THEME_VARIABLES
+        .iter()
+        .filter(|key| !theme.variables.contains_key(**key))
+        .map(|key| key.to_string())
+        .collect()
+}
+
+/// Whether a theme variable can be written into a stylesheet as it is
+pub fn is_safe_theme_variable(key: &str, value: &str) -> bool {
+    !key.contains(UNSAFE_CSS_CHARS) && !value.contains(UNSAFE_CSS_CHARS)
+}
+
+/// Reject a theme with variables that could break out of a stylesheet
+fn check_theme_variables(theme: &ThemeConfig) -> Result<(), String> {
+    let mut unsafe_keys: Vec<&str> = theme
+        .variables
+        .iter()
+        .filter(|(key, value)| !is_safe_theme_variable(key, value))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if unsafe_keys.is_empty() {
+        return Ok(());
+    }
+
+    unsafe_keys.sort();
+    Err(format!("Theme '{}' has variables containing <, >, {{, }} or ;: {}", theme.name, unsafe_keys.join(", ")))
+}
+
+/// Load the configured current theme, falling back to the default dark theme
+///
+/// A missing or corrupt theme file doesn't fail theme loading; the fallback
+/// is returned with a warning instead. The configured theme name is left
+/// untouched so fixing the file restores it.
+pub fn load_current_theme(folder_path: Option<String>) -> Result<ThemeLoadResult, String> {
+    let config = load_app_config(folder_path.clone())?;
+
+    match load_theme(folder_path, &config.current_theme) {
+        Ok(theme) => Ok(ThemeLoadResult { theme, warning: None }),
+        Err(e) => Ok(ThemeLoadResult {
+            theme: get_default_dark_theme(),
+            warning: Some(format!(
+                "Could not load theme '{}', using the default dark theme instead: {}",
+                config.current_theme, e
+            )),
+        }),
+    }
+}
 
+/// Load a theme by name without resolving its base theme
+fn load_theme_unresolved(loom_dir: &Path, theme_name: &str) -> Result<ThemeConfig, String> {
     // Try built-in themes first
     let builtin_path = loom_dir.join("themes").join("built-in").join(format!("{}.json", theme_name));
     if builtin_path.exists() {
@@ -256,11 +904,46 @@ pub fn load_theme(folder_path: Option<String>, theme_name: &str) -> Result<Theme
         return load_theme_from_path(&custom_path);
     }
 
-    Err(format!("Theme '{}' not found", theme_name))
+    // Built-in themes are always available as bases, even if their files are missing
+    match theme_name {
+        "dark" => Ok(get_default_dark_theme()),
+        "light" => Ok(get_default_light_theme()),
+        _ => Err(format!("Theme '{}' not found", theme_name)),
+    }
+}
+
+/// Load a theme and merge it over its chain of base themes
+///
+/// `visited` tracks the themes already on the chain so cycles are reported
+/// instead of recursing forever.
+fn load_theme_with_bases(loom_dir: &Path, theme_name: &str, visited: &mut Vec<String>) -> Result<ThemeConfig, String> {
+    if visited.iter().any(|name| name == theme_name) {
+        return Err(format!(
+            "Theme inheritance cycle: {} -> {}",
+            visited.join(" -> "),
+            theme_name
+        ));
+    }
+    visited.push(theme_name.to_string());
+
+    let theme = load_theme_unresolved(loom_dir, theme_name)?;
+
+    match theme.extends {
+        Some(ref base_name) => {
+            let base = load_theme_with_bases(loom_dir, base_name, visited)?;
+
+            // Child variables override the base
+            let mut variables = base.variables;
+            variables.extend(theme.variables.clone());
+
+            Ok(ThemeConfig { variables, ..theme })
+        }
+        None => Ok(theme),
+    }
 }
 
 /// Load theme from a file path
-fn load_theme_from_path(path: &PathBuf) -> Result<ThemeConfig, String> {
+fn load_theme_from_path(path: &Path) -> Result<ThemeConfig, String> {
     let content = fs::read_to_string(path)
         .map_err(|e| format!("Failed to read theme file: {}", e))?;
 
@@ -315,23 +998,120 @@ pub fn list_themes(folder_path: Option<String>) -> Result<Vec<String>, String> {
 /// Import a theme from an external path to the custom themes folder
 pub fn import_theme(folder_path: Option<String>, source_path: String) -> Result<String, String> {
     let loom_dir = get_loom_dir(folder_path)?;
-    let source = PathBuf::from(&source_path);
+    import_theme_file(&loom_dir, &PathBuf::from(&source_path), true)
+}
 
+// Check a theme file and copy it into the custom themes folder, named after
+// the theme. An existing custom theme of that name is only replaced if
+// `replace` is set.
+fn import_theme_file(loom_dir: &Path, source: &Path, replace: bool) -> Result<String, String> {
     if !source.exists() {
         return Err("Source theme file does not exist".to_string());
     }
 
     // Load and validate the theme
-    let theme = load_theme_from_path(&source)?;
+    let theme = load_theme_from_path(source)?;
+    let file_name = theme.name.to_lowercase();
+    validate_file_name(&file_name).map_err(|e| format!("Invalid theme name '{}': {}", theme.name, e))?;
+    check_theme_variables(&theme)?;
+
+    // Check the variables the theme ends up with once its base is applied
+    let mut resolved = theme.clone();
+    if let Some(ref base_name) = theme.extends {
+        let base = load_theme_with_bases(loom_dir, base_name, &mut Vec::new())?;
+        resolved.variables = base.variables;
+        resolved.variables.extend(theme.variables.clone());
+    }
+
+    let missing = validate_theme(&resolved);
+    let missing_critical: Vec<&str> = CRITICAL_THEME_VARIABLES
+        .iter()
+        .copied()
+        .filter(|key| missing.iter().any(|m| m == key))
+        .collect();
+    if !missing_critical.is_empty() {
+        return Err(format!("Theme is missing required variables: {}", missing_critical.join(", ")));
+    }
+    if !missing.is_empty() {
+        eprintln!("Imported theme '{}' is missing variables: {}", theme.name, missing.join(", "));
+    }
 
     // Copy to custom themes folder
     let custom_dir = loom_dir.join("themes").join("custom");
-    let dest_path = custom_dir.join(format!("{}.json", theme.name.to_lowercase()));
+    let dest_path = custom_dir.join(format!("{}.json", file_name));
+    if !replace && dest_path.exists() {
+        return Err(format!("A theme named '{}' already exists", file_name));
+    }
 
-    fs::copy(&source, &dest_path)
+    fs::copy(source, &dest_path)
         .map_err(|e| format!("Failed to import theme: {}", e))?;
 
-    Ok(theme.name.to_lowercase())
+    Ok(file_name)
+}
+
+/// Delete a custom theme
+///
+/// Built-in themes can't be deleted. If the deleted theme is the current
+/// theme, the config is reset to the dark theme.
+pub fn delete_theme(folder_path: Option<String>, theme_name: String) -> Result<(), String> {
+    validate_file_name(&theme_name)?;
+    let loom_dir = get_loom_dir(folder_path.clone())?;
+    let themes_dir = loom_dir.join("themes");
+
+    if themes_dir.join("built-in").join(format!("{}.json", theme_name)).exists()
+        || theme_name == "dark"
+        || theme_name == "light"
+    {
+        return Err("Built-in themes cannot be deleted".to_string());
+    }
+
+    let custom_path = themes_dir.join("custom").join(format!("{}.json", theme_name));
+    if !custom_path.exists() {
+        return Err(format!("Theme '{}' not found", theme_name));
+    }
+
+    fs::remove_file(&custom_path)
+        .map_err(|e| format!("Failed to delete theme: {}", e))?;
+
+    let mut config = load_app_config(folder_path.clone())?;
+    if config.current_theme == theme_name {
+        config.current_theme = "dark".to_string();
+        save_app_config(folder_path, &config)?;
+    }
+
+    Ok(())
+}
+
+/// Copy a theme under a new name into the custom themes folder
+///
+/// Returns the new theme's file name (the lowercased new name).
+pub fn duplicate_theme(folder_path: Option<String>, source_name: String, new_name: String) -> Result<String, String> {
+    let loom_dir = get_loom_dir(folder_path.clone())?;
+    let file_name = new_name.to_lowercase();
+
+    if file_name.is_empty() || file_name.contains(['/', '\\']) {
+        return Err("Invalid theme name".to_string());
+    }
+
+    let themes_dir = loom_dir.join("themes");
+    let builtin_path = themes_dir.join("built-in").join(format!("{}.json", file_name));
+    let dest_path = themes_dir.join("custom").join(format!("{}.json", file_name));
+    if builtin_path.exists() || dest_path.exists() {
+        return Err(format!("A theme named '{}' already exists", file_name));
+    }
+
+    let mut theme = load_theme(folder_path, &source_name)?;
+    theme.name = new_name;
+
+    let json = serde_json::to_string_pretty(&theme)
+        .map_err(|e| format!("Failed to serialize theme: {}", e))?;
+
+    fs::create_dir_all(themes_dir.join("custom"))
+        .map_err(|e| format!("Failed to create themes/custom directory: {}", e))?;
+    fs::write(&dest_path, json)
+        .map_err(|e| format!("Failed to write theme file: {}", e))?;
+
+    Ok(file_name)
 }
 
 /// Export a theme to an external path
@@ -345,12 +1125,957 @@ pub fn export_theme(folder_path: Option<String>, theme_name: String, dest_path:
         .map_err(|e| format!("Failed to write theme file: {}", e))
 }
 
-/// Get the default dark theme (for when no folder is open)
-pub fn get_default_dark_theme_config() -> ThemeConfig {
-    get_default_dark_theme()
+/// Export every theme (built-in and custom) into a single zip archive
+///
+/// Themes are stored under `built-in/` and `custom/` inside the archive,
+/// mirroring the themes folder layout.
+pub fn export_all_themes(folder_path: Option<String>, dest_path: String) -> Result<(), String> {
+    let loom_dir = get_loom_dir(folder_path)?;
+    let themes_dir = loom_dir.join("themes");
+
+    let file = fs::File::create(&dest_path)
+        .map_err(|e| format!("Failed to create archive: {}", e))?;
+    let mut archive = ZipWriter::new(file);
+    let options = SimpleFileOptions::default();
+
+    for subfolder in ["built-in", "custom"] {
+        let dir = themes_dir.join(subfolder);
+        if !dir.exists() {
+            continue;
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to read {} themes directory: {}", subfolder, e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let Some(file_name) = path.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let content = fs::read(&path)
+                .map_err(|e| format!("Failed to read theme file: {}", e))?;
+
+            archive
+                .start_file(format!("{}/{}", subfolder, file_name), options)
+                .map_err(|e| format!("Failed to write archive: {}", e))?;
+            archive
+                .write_all(&content)
+                .map_err(|e| format!("Failed to write archive: {}", e))?;
+        }
+    }
+
+    archive
+        .finish()
+        .map_err(|e| format!("Failed to write archive: {}", e))?;
+
+    Ok(())
+}
+
+/// Import every theme from an archive created by `export_all_themes`
+///
+/// All themes are written to the custom themes folder, except built-ins
+/// that already exist in this folder. Returns the imported theme names.
+pub fn import_themes_archive(folder_path: Option<String>, archive_path: String) -> Result<Vec<String>, String> {
+    let loom_dir = get_loom_dir(folder_path)?;
+    let themes_dir = loom_dir.join("themes");
+    let custom_dir = themes_dir.join("custom");
+
+    let file = fs::File::open(&archive_path)
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+    let mut archive = ZipArchive::new(file)
+        .map_err(|e| format!("Failed to read archive: {}", e))?;
+
+    fs::create_dir_all(&custom_dir)
+        .map_err(|e| format!("Failed to create themes/custom directory: {}", e))?;
+
+    let mut imported = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read archive: {}", e))?;
+
+        // Only the file name is used, so entries can't escape the themes folder
+        let Some(entry_path) = entry.enclosed_name() else {
+            continue;
+        };
+        if entry_path.extension().and_then(|s| s.to_str()) != Some("json") {
+            continue;
+        }
+        let Some(name) = entry_path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+
+        if themes_dir.join("built-in").join(format!("{}.json", name)).exists() {
+            continue;
+        }
+
+        let mut content = String::new();
+        entry
+            .read_to_string(&mut content)
+            .map_err(|e| format!("Failed to read theme '{}' from archive: {}", name, e))?;
+
+        // Make sure it's a valid theme before writing it
+        let theme: ThemeConfig = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse theme '{}': {}", name, e))?;
+        check_theme_variables(&theme)?;
+
+        fs::write(custom_dir.join(format!("{}.json", name)), content)
+            .map_err(|e| format!("Failed to write theme file: {}", e))?;
+
+        imported.push(name);
+    }
+
+    Ok(imported)
+}
+
+/// Copy every valid theme in `source_dir` into the folder's custom themes
+///
+/// Lets a theme collection be carried over to a new workspace. Each `.json`
+/// file is checked as by `import_theme`, and invalid ones are skipped with
+/// a warning, as are themes that would replace an existing custom theme.
+/// Returns the seeded theme names, in file name order.
+pub fn seed_themes_from(folder_path: Option<String>, source_dir: String) -> Result<Vec<String>, String> {
+    let loom_dir = get_loom_dir(folder_path)?;
+    let custom_dir = loom_dir.join("themes").join("custom");
+    fs::create_dir_all(&custom_dir)
+        .map_err(|e| format!("Failed to create themes/custom directory: {}", e))?;
+
+    let entries = fs::read_dir(&source_dir)
+        .map_err(|e| format!("Failed to read themes directory: {}", e))?;
+    let mut sources: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("json"))
+        .collect();
+    sources.sort();
+
+    let mut seeded = Vec::new();
+    for source in sources {
+        match import_theme_file(&loom_dir, &source, false) {
+            Ok(name) => seeded.push(name),
+            Err(e) => eprintln!("Skipping theme {}: {}", source.display(), e),
+        }
+    }
+
+    Ok(seeded)
+}
+
+/// Generate a complete theme from a single accent color
+///
+/// Backgrounds, text and borders are near-neutral tints of the accent hue,
+/// while headings, links and code use hue rotations of it, following the
+/// structure of the default dark and light palettes.
+pub fn generate_theme_from_accent(name: String, accent_hex: String, dark: bool) -> Result<ThemeConfig, String> {
+    let accent_rgb = parse_hex(&accent_hex)?;
+    let accent = accent_rgb.to_hsl();
+    let hex = |color: Hsl| color.to_rgb().to_hex();
+
+    // Keep generated colors vivid enough even for a greyish accent
+    let sat = accent.s.max(0.45);
+
+    // (background levels, text levels, border, hover shift, foreground lightness)
+    let (bg, text, border, hover, fg) = if dark {
+        ([0.12, 0.15, 0.18], [0.84, 0.52], 0.25, 0.1, 0.68)
+    } else {
+        ([1.0, 0.95, 0.91], [0.12, 0.43], 0.83, -0.1, 0.38)
+    };
+
+    let bg_primary = hex(accent.with(0.0, 0.08, bg[0]));
+    let bg_tertiary = hex(accent.with(0.0, 0.08, bg[2]));
+    let border_color = hex(accent.with(0.0, 0.08, border));
+    let text_secondary = hex(accent.with(0.0, 0.05, text[1]));
+    let h1_color = hex(accent.with(0.0, sat, fg));
+
+    let mut variables = HashMap::new();
+    let mut set = |key: &str, value: String| {
+        variables.insert(key.to_string(), value);
+    };
+
+    // Base colors
+    set("bg-primary", bg_primary.clone());
+    set("bg-secondary", hex(accent.with(0.0, 0.08, bg[1])));
+    set("bg-tertiary", bg_tertiary.clone());
+    set("text-primary", hex(accent.with(0.0, 0.05, text[0])));
+    set("text-secondary", text_secondary.clone());
+    set("border-color", border_color.clone());
+    set("accent-color", accent_rgb.to_hex());
+    set("accent-hover", hex(accent.with(0.0, accent.s, (accent.l + hover).clamp(0.0, 1.0))));
+
+    // Heading colors
+    set("heading-color", hex(accent.with(30.0, sat, fg)));
+    set("h1-color", h1_color.clone());
+    set("h2-color", hex(accent.with(30.0, sat, fg)));
+    set("h3-color", hex(accent.with(60.0, sat, fg)));
+    set("h4-color", hex(accent.with(-30.0, sat, fg)));
+    set("h5-color", hex(accent.with(90.0, sat, fg)));
+    set("h6-color", text_secondary);
+
+    // Syntax colors
+    set("code-bg", bg_primary.clone());
+    set("code-color", hex(accent.with(180.0, sat, fg)));
+    set("link-color", hex(accent.with(0.0, sat, fg)));
+    set("blockquote-border", accent_rgb.to_hex());
+    set("blockquote-bg", bg_primary);
+    set("table-border", border_color.clone());
+    set("table-header-bg", bg_tertiary);
+    set("list-marker", h1_color);
+    set("hr-color", border_color);
+
+    Ok(ThemeConfig {
+        name,
+        author: None,
+        version: Some("1.0.0".to_string()),
+        extends: None,
+        variables,
+    })
+}
+
+/// Check key foreground/background pairs of a theme against the WCAG AA ratio
+///
+/// Pairs with a missing or non-hex color are skipped.
+pub fn check_theme_contrast(theme: &ThemeConfig) -> Vec<ContrastWarning> {
+    CONTRAST_PAIRS
+        .iter()
+        .filter_map(|(fg_key, bg_key)| {
+            let fg = parse_hex(theme.variables.get(*fg_key)?).ok()?;
+            let bg = parse_hex(theme.variables.get(*bg_key)?).ok()?;
+            let ratio = contrast_ratio(fg, bg);
+
+            if ratio < MIN_CONTRAST_RATIO {
+                Some(ContrastWarning {
+                    foreground: fg_key.to_string(),
+                    background: bg_key.to_string(),
+                    ratio,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
 }
 
-/// Get the default light theme (for when no folder is open)
-pub fn get_default_light_theme_config() -> ThemeConfig {
-    get_default_light_theme()
+/// Compute the settings in effect after merging defaults, config.json and
+/// front matter overrides from the given document
+///
+/// Front matter keys only override settings that exist in `AppConfig`, and
+/// only when the value has the right type for that setting.
+pub fn compute_effective_config(folder_path: Option<String>, document_content: Option<String>) -> Result<EffectiveConfig, String> {
+    let mut merged = config_to_object(&AppConfig::default())?;
+    let mut sources: HashMap<String, &str> = merged.keys().map(|k| (k.clone(), "default")).collect();
+
+    // Values explicitly present in config.json
+    let config_path = get_loom_dir(folder_path.clone())?.join("config.json");
+    let loaded = config_to_object(&load_app_config(folder_path)?)?;
+
+    if config_path.exists() {
+        let content = fs::read_to_string(&config_path)
+            .map_err(|e| format!("Failed to read config file: {}", e))?;
+        let raw: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse config file: {}", e))?;
+
+        if let serde_json::Value::Object(raw) = raw {
+            for key in raw.keys() {
+                if let Some(value) = loaded.get(key) {
+                    merged.insert(key.clone(), value.clone());
+                    sources.insert(key.clone(), "config");
+                }
+            }
+        }
+    }
+
+    // Front matter overrides from the document
+    if let Some(front_matter) = document_content.as_deref().and_then(parse_front_matter) {
+        for (key, raw_value) in front_matter {
+            if !merged.contains_key(&key) {
+                continue;
+            }
+
+            // Front matter values are plain text, so try them as JSON first
+            // to pick up booleans and numbers
+            let value = serde_json::from_str(&raw_value)
+                .unwrap_or(serde_json::Value::String(raw_value));
+
+            let mut candidate = merged.clone();
+            candidate.insert(key.clone(), value.clone());
+            if serde_json::from_value::<AppConfig>(serde_json::Value::Object(candidate)).is_ok() {
+                merged.insert(key.clone(), value);
+                sources.insert(key, "front-matter");
+            }
+        }
+    }
+
+    let settings = merged
+        .into_iter()
+        .map(|(key, value)| {
+            let source = sources.get(&key).copied().unwrap_or("default").to_string();
+            (key, EffectiveSetting { value, source })
+        })
+        .collect();
+
+    Ok(EffectiveConfig { settings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{render_markdown_line, RenderRequest};
+
+    fn temp_folder(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("loom-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join(".loom")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_effective_config_sources() {
+        let dir = temp_folder("effective");
+        fs::write(
+            dir.join(".loom").join("config.json"),
+            r#"{ "current_theme": "light", "status_bar_visible": false }"#,
+        ).unwrap();
+        let folder = Some(dir.to_string_lossy().to_string());
+
+        let effective = compute_effective_config(folder.clone(), None).unwrap();
+        assert_eq!(effective.settings["current_theme"].value, "light");
+        assert_eq!(effective.settings["current_theme"].source, "config");
+        assert_eq!(effective.settings["confirm_file_delete"].source, "default");
+
+        let document = "---\ncurrent_theme: dark\nstatus_bar_visible: true\ntitle: Notes\n---\n# Notes".to_string();
+        let effective = compute_effective_config(folder, Some(document)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(effective.settings["current_theme"].value, "dark");
+        assert_eq!(effective.settings["current_theme"].source, "front-matter");
+        assert_eq!(effective.settings["status_bar_visible"].value, true);
+        assert_eq!(effective.settings["status_bar_visible"].source, "front-matter");
+        assert!(!effective.settings.contains_key("title"));
+    }
+
+    #[test]
+    fn test_effective_config_rejects_mistyped_front_matter() {
+        let dir = temp_folder("mistyped");
+        let document = "---\nconfirm_file_delete: sometimes\n---".to_string();
+        let effective = compute_effective_config(Some(dir.to_string_lossy().to_string()), Some(document)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(effective.settings["confirm_file_delete"].value, true);
+        assert_eq!(effective.settings["confirm_file_delete"].source, "default");
+    }
+
+    fn write_custom_theme(dir: &Path, file_name: &str, json: &str) {
+        let custom_dir = dir.join(".loom").join("themes").join("custom");
+        fs::create_dir_all(&custom_dir).unwrap();
+        fs::write(custom_dir.join(format!("{}.json", file_name)), json).unwrap();
+    }
+
+    #[test]
+    fn test_theme_extends_overrides_base() {
+        let dir = temp_folder("extends");
+        write_custom_theme(&dir, "ocean", r##"{
+            "name": "Ocean",
+            "author": null,
+            "version": null,
+            "extends": "dark",
+            "variables": { "accent-color": "#00aaff" }
+        }"##);
+
+        let theme = load_theme(Some(dir.to_string_lossy().to_string()), "ocean").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(theme.name, "Ocean");
+        assert_eq!(theme.variables["accent-color"], "#00aaff");
+        assert_eq!(theme.variables["bg-primary"], get_default_dark_theme().variables["bg-primary"]);
+    }
+
+    #[test]
+    fn test_theme_extends_missing_base() {
+        let dir = temp_folder("extends-missing");
+        write_custom_theme(&dir, "orphan", r#"{
+            "name": "Orphan",
+            "author": null,
+            "version": null,
+            "extends": "nonexistent",
+            "variables": {}
+        }"#);
+
+        let result = load_theme(Some(dir.to_string_lossy().to_string()), "orphan");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.unwrap_err().contains("nonexistent"));
+    }
+
+    #[test]
+    fn test_theme_extends_cycle() {
+        let dir = temp_folder("extends-cycle");
+        write_custom_theme(&dir, "a", r#"{ "name": "A", "author": null, "version": null, "extends": "b", "variables": {} }"#);
+        write_custom_theme(&dir, "b", r#"{ "name": "B", "author": null, "version": null, "extends": "a", "variables": {} }"#);
+
+        let result = load_theme(Some(dir.to_string_lossy().to_string()), "a");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.unwrap_err().contains("cycle"));
+    }
+
+    #[test]
+    fn test_validate_complete_theme() {
+        assert!(validate_theme(&get_default_dark_theme()).is_empty());
+        assert!(validate_theme(&get_default_light_theme()).is_empty());
+    }
+
+    #[test]
+    fn test_validate_theme_missing_key() {
+        let mut theme = get_default_dark_theme();
+        theme.variables.remove("bg-primary");
+
+        assert_eq!(validate_theme(&theme), vec!["bg-primary".to_string()]);
+    }
+
+    #[test]
+    fn test_import_rejects_theme_missing_critical_keys() {
+        let dir = temp_folder("import-invalid");
+        fs::create_dir_all(dir.join(".loom").join("themes").join("custom")).unwrap();
+        let source = dir.join("broken.json");
+        fs::write(&source, r#"{ "name": "Broken", "author": null, "version": null, "variables": {} }"#).unwrap();
+
+        let result = import_theme(Some(dir.to_string_lossy().to_string()), source.to_string_lossy().to_string());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.unwrap_err().contains("bg-primary"));
+    }
+
+    #[test]
+    fn test_import_rejects_theme_breaking_out_of_css() {
+        let dir = temp_folder("import-unsafe");
+        fs::create_dir_all(dir.join(".loom").join("themes").join("custom")).unwrap();
+        let source = dir.join("sneaky.json");
+        let mut theme = get_default_dark_theme();
+        theme.name = "Sneaky".to_string();
+        theme.variables.insert("accent-color".to_string(), "red</style><img src=x onerror=alert(1)>".to_string());
+        fs::write(&source, serde_json::to_string(&theme).unwrap()).unwrap();
+
+        let result = import_theme(Some(dir.to_string_lossy().to_string()), source.to_string_lossy().to_string());
+        let imported = dir.join(".loom").join("themes").join("custom").join("sneaky.json").exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.unwrap_err().contains("accent-color"));
+        assert!(!imported);
+    }
+
+    #[test]
+    fn test_app_config_dir_is_set_up_once() {
+        let dir = std::env::temp_dir().join(format!("loom-config-app-dir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let prepared = prepare_app_config_dir(dir.clone()).unwrap();
+        let initial = read_config_file(&dir.join("config.json")).unwrap();
+        let light = load_theme_with_bases(&dir, "light", &mut Vec::new()).unwrap();
+
+        // A saved config isn't replaced the next time the directory is used
+        let config = AppConfig { current_theme: "light".to_string(), ..AppConfig::default() };
+        fs::write(dir.join("config.json"), serde_json::to_string(&config).unwrap()).unwrap();
+        prepare_app_config_dir(dir.clone()).unwrap();
+        let reloaded = read_config_file(&dir.join("config.json")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(prepared, dir);
+        assert_eq!(initial.current_theme, "dark");
+        assert_eq!(light.name, "Light");
+        assert_eq!(reloaded.current_theme, "light");
+    }
+
+    #[test]
+    fn test_file_settings_key_without_folder() {
+        let dir = std::env::temp_dir().join("notes");
+        let notes = dir.join("today.md").to_string_lossy().to_string();
+
+        assert_eq!(file_settings_key("", &notes), notes.replace('\\', "/"));
+        assert_eq!(file_settings_key(&dir.to_string_lossy(), &notes), "today.md");
+    }
+
+    #[test]
+    fn test_corrupt_current_theme_falls_back() {
+        let dir = temp_folder("corrupt-theme");
+        let folder = Some(dir.to_string_lossy().to_string());
+        write_custom_theme(&dir, "broken", "{ not valid json");
+        let config = AppConfig {
+            current_theme: "broken".to_string(),
+            ..AppConfig::default()
+        };
+        save_app_config(folder.clone(), &config).unwrap();
+
+        let result = load_current_theme(folder.clone()).unwrap();
+        let current_theme = load_app_config(folder).unwrap().current_theme;
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.theme.name, "Dark");
+        assert!(result.warning.unwrap().contains("broken"));
+        assert_eq!(current_theme, "broken");
+    }
+
+    #[test]
+    fn test_delete_custom_theme() {
+        let dir = temp_folder("delete-theme");
+        let folder = Some(dir.to_string_lossy().to_string());
+        initialize_loom_dir(folder.clone()).unwrap();
+
+        let name = duplicate_theme(folder.clone(), "dark".to_string(), "Midnight".to_string()).unwrap();
+        assert_eq!(name, "midnight");
+        assert_eq!(load_theme(folder.clone(), "midnight").unwrap().name, "Midnight");
+
+        let mut config = load_app_config(folder.clone()).unwrap();
+        config.current_theme = "midnight".to_string();
+        save_app_config(folder.clone(), &config).unwrap();
+
+        delete_theme(folder.clone(), "midnight".to_string()).unwrap();
+        let themes = list_themes(folder.clone()).unwrap();
+        let current_theme = load_app_config(folder).unwrap().current_theme;
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!themes.contains(&"midnight".to_string()));
+        assert_eq!(current_theme, "dark");
+    }
+
+    #[test]
+    fn test_delete_builtin_theme_refused() {
+        let dir = temp_folder("delete-builtin");
+        let folder = Some(dir.to_string_lossy().to_string());
+        initialize_loom_dir(folder.clone()).unwrap();
+
+        let result = delete_theme(folder, "light".to_string());
+        let still_exists = dir.join(".loom").join("themes").join("built-in").join("light.json").exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.is_err());
+        assert!(still_exists);
+    }
+
+    #[test]
+    fn test_delete_theme_rejects_traversal() {
+        let dir = temp_folder("delete-traversal");
+        let folder = Some(dir.to_string_lossy().to_string());
+        initialize_loom_dir(folder.clone()).unwrap();
+        save_app_config(folder.clone(), &AppConfig::default()).unwrap();
+
+        let result = delete_theme(folder, "../../config".to_string());
+        let config_kept = dir.join(".loom").join("config.json").exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.is_err());
+        assert!(config_kept);
+    }
+
+    #[test]
+    fn test_generate_theme_from_accent() {
+        for dark in [true, false] {
+            let theme = generate_theme_from_accent("Generated".to_string(), "#E06C75".to_string(), dark).unwrap();
+            assert!(validate_theme(&theme).is_empty());
+            assert_eq!(theme.variables["accent-color"], "#e06c75");
+        }
+
+        assert!(generate_theme_from_accent("Bad".to_string(), "not-a-color".to_string(), true).is_err());
+    }
+
+    #[test]
+    fn test_contrast_check_flags_low_contrast() {
+        let mut theme = get_default_dark_theme();
+        theme.variables.insert("text-primary".to_string(), "#2a2a2a".to_string());
+
+        let warnings = check_theme_contrast(&theme);
+        let warning = warnings
+            .iter()
+            .find(|w| w.foreground == "text-primary" && w.background == "bg-primary")
+            .unwrap();
+        assert!(warning.ratio < MIN_CONTRAST_RATIO);
+    }
+
+    #[test]
+    fn test_contrast_check_passes_readable_theme() {
+        assert!(check_theme_contrast(&get_default_dark_theme()).is_empty());
+        assert!(check_theme_contrast(&get_default_light_theme()).is_empty());
+    }
+
+    #[test]
+    fn test_themes_archive_round_trip() {
+        let source = temp_folder("archive-source");
+        let target = temp_folder("archive-target");
+        let source_folder = Some(source.to_string_lossy().to_string());
+        let target_folder = Some(target.to_string_lossy().to_string());
+        initialize_loom_dir(source_folder.clone()).unwrap();
+        initialize_loom_dir(target_folder.clone()).unwrap();
+        write_custom_theme(&source, "ocean", r##"{
+            "name": "Ocean",
+            "author": null,
+            "version": null,
+            "extends": "dark",
+            "variables": { "accent-color": "#00aaff" }
+        }"##);
+
+        let archive_path = source.join("themes.zip");
+        export_all_themes(source_folder.clone(), archive_path.to_string_lossy().to_string()).unwrap();
+        let imported = import_themes_archive(target_folder.clone(), archive_path.to_string_lossy().to_string()).unwrap();
+
+        let mut source_themes = list_themes(source_folder).unwrap();
+        let mut target_themes = list_themes(target_folder.clone()).unwrap();
+        let ocean = load_theme(target_folder, "ocean").unwrap();
+        fs::remove_dir_all(&source).unwrap();
+        fs::remove_dir_all(&target).unwrap();
+
+        source_themes.sort();
+        target_themes.sort();
+        assert_eq!(imported, vec!["ocean".to_string()]);
+        assert_eq!(source_themes, target_themes);
+        assert_eq!(ocean.variables["accent-color"], "#00aaff");
+    }
+
+    #[test]
+    fn test_seed_themes_skips_invalid() {
+        let dir = temp_folder("seed");
+        let source = dir.join("collection");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(
+            source.join("ocean.json"),
+            r##"{ "name": "Ocean", "author": null, "version": null, "extends": "dark", "variables": { "accent-color": "#00aaff" } }"##,
+        )
+        .unwrap();
+        fs::write(
+            source.join("paper.json"),
+            r##"{ "name": "Paper", "author": null, "version": null, "extends": "light", "variables": {} }"##,
+        )
+        .unwrap();
+        fs::write(source.join("broken.json"), r#"{ "name": "Broken", "author": null, "version": null, "variables": {} }"#).unwrap();
+        fs::write(
+            source.join("escape.json"),
+            r##"{ "name": "../../config", "author": null, "version": null, "extends": "dark", "variables": {} }"##,
+        )
+        .unwrap();
+        fs::write(
+            source.join("sunset.json"),
+            r##"{ "name": "Sunset", "author": null, "version": null, "extends": "dark", "variables": { "accent-color": "#ff0000" } }"##,
+        )
+        .unwrap();
+        let folder = Some(dir.to_string_lossy().to_string());
+        save_app_config(folder.clone(), &AppConfig::default()).unwrap();
+        let custom_dir = dir.join(".loom").join("themes").join("custom");
+        fs::create_dir_all(&custom_dir).unwrap();
+        fs::write(
+            custom_dir.join("sunset.json"),
+            r##"{ "name": "Sunset", "author": null, "version": null, "extends": "dark", "variables": { "accent-color": "#ffaa00" } }"##,
+        )
+        .unwrap();
+
+        let seeded = seed_themes_from(folder.clone(), source.to_string_lossy().to_string()).unwrap();
+        let mut themes = list_themes(folder.clone()).unwrap();
+        let ocean = load_theme(folder.clone(), "ocean").unwrap();
+        let sunset = load_theme(folder.clone(), "sunset").unwrap();
+        let config = fs::read_to_string(dir.join(".loom").join("config.json")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        themes.sort();
+        assert_eq!(seeded, ["ocean", "paper"]);
+        assert_eq!(themes, ["ocean", "paper", "sunset"]);
+        assert_eq!(ocean.variables["accent-color"], "#00aaff");
+        assert_eq!(sunset.variables["accent-color"], "#ffaa00");
+        assert!(!config.contains("variables"));
+    }
+
+    #[test]
+    fn test_migrate_v0_config() {
+        let v0 = serde_json::json!({
+            "current_theme": "light",
+            "confirm_file_delete": false
+        });
+
+        let migrated = migrate_config(v0);
+        assert_eq!(migrated["schema_version"], CONFIG_SCHEMA_VERSION);
+        assert_eq!(migrated["use_trash"], true);
+
+        let config: AppConfig = serde_json::from_value(migrated).unwrap();
+        assert_eq!(config.current_theme, "light");
+        assert!(!config.confirm_file_delete);
+        assert!(config.confirm_folder_delete);
+        assert!(config.use_trash);
+    }
+
+    #[test]
+    fn test_old_config_gets_appearance_defaults() {
+        let config: AppConfig = serde_json::from_value(serde_json::json!({
+            "schema_version": 1,
+            "current_theme": "dark"
+        }))
+        .unwrap();
+
+        assert_eq!(config.font_family, default_font_family());
+        assert_eq!(config.font_size, 16);
+        assert_eq!(config.line_height, 1.7);
+        assert_eq!(config.tab_size, 4);
+        assert!(config.word_wrap);
+    }
+
+    #[test]
+    fn test_out_of_range_appearance_rejected() {
+        let dir = temp_folder("appearance");
+        let folder = Some(dir.to_string_lossy().to_string());
+
+        let too_small = AppConfig { font_size: 5, ..AppConfig::default() };
+        let too_tall = AppConfig { line_height: 4.0, ..AppConfig::default() };
+        let no_tabs = AppConfig { tab_size: 0, ..AppConfig::default() };
+        let results = [
+            save_app_config(folder.clone(), &too_small),
+            save_app_config(folder.clone(), &too_tall),
+            save_app_config(folder.clone(), &no_tabs),
+        ];
+        let written = dir.join(".loom").join("config.json").exists();
+        let largest = save_app_config(folder, &AppConfig { font_size: 72, ..AppConfig::default() });
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results[0], Err("Font size must be between 6 and 72, got 5".to_string()));
+        assert_eq!(results[1], Err("Line height must be between 1.0 and 3.0, got 4".to_string()));
+        assert_eq!(results[2], Err("Tab size must be between 1 and 16, got 0".to_string()));
+        assert!(!written);
+        assert!(largest.is_ok());
+    }
+
+    #[test]
+    fn test_out_of_range_value_on_disk_falls_back() {
+        let dir = temp_folder("appearance-on-disk");
+        let folder = Some(dir.to_string_lossy().to_string());
+        // A hand-edited config with one value out of range
+        let mut on_disk = config_to_object(&AppConfig::default()).unwrap();
+        on_disk.insert("font_size".to_string(), serde_json::json!(100));
+        on_disk.insert("line_height".to_string(), serde_json::json!(2.0));
+        fs::write(dir.join(".loom").join("config.json"), serde_json::Value::Object(on_disk).to_string()).unwrap();
+
+        let loaded = load_app_config(folder.clone()).unwrap();
+        let saved = reset_keybinds(folder.clone());
+        let config = load_app_config(folder).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.font_size, AppConfig::default().font_size);
+        assert_eq!(loaded.line_height, 2.0);
+        assert!(saved.is_ok());
+        assert_eq!(config.line_height, 2.0);
+    }
+
+    #[test]
+    fn test_load_writes_back_migrated_config() {
+        let dir = temp_folder("migrate");
+        let config_path = dir.join(".loom").join("config.json");
+        fs::write(&config_path, r#"{ "current_theme": "light" }"#).unwrap();
+
+        let config = load_app_config(Some(dir.to_string_lossy().to_string())).unwrap();
+        let written: serde_json::Value = serde_json::from_str(&fs::read_to_string(&config_path).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(config.schema_version, CONFIG_SCHEMA_VERSION);
+        assert_eq!(config.current_theme, "light");
+        assert_eq!(written["schema_version"], CONFIG_SCHEMA_VERSION);
+        assert_eq!(written["current_theme"], "light");
+    }
+
+    #[test]
+    fn test_keybind_conflicts() {
+        let mut keybinds = default_keybinds();
+        assert!(validate_keybinds(&keybinds).is_empty());
+
+        keybinds.insert("bold".to_string(), "shift+ctrl+x".to_string());
+        keybinds.insert("zoom-in".to_string(), "Ctrl++".to_string());
+        keybinds.insert("zoom-out".to_string(), "Ctrl++".to_string());
+        keybinds.insert("find".to_string(), String::new());
+        keybinds.insert("replace".to_string(), String::new());
+
+        assert_eq!(
+            validate_keybinds(&keybinds),
+            [
+                KeybindConflict {
+                    chord: "Ctrl++".to_string(),
+                    actions: vec!["zoom-in".to_string(), "zoom-out".to_string()],
+                },
+                KeybindConflict {
+                    chord: keybinds["bold"].clone(),
+                    actions: vec!["bold".to_string(), "strikethrough".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keybind_overrides_merge_over_defaults() {
+        let dir = temp_folder("keybinds");
+        let folder = Some(dir.to_string_lossy().to_string());
+        fs::write(
+            dir.join(".loom").join("config.json"),
+            r#"{ "schema_version": 1, "current_theme": "dark", "keybinds": { "bold": "Ctrl+Shift+B", "find": "" } }"#,
+        )
+        .unwrap();
+
+        let loaded = load_app_config(folder.clone()).unwrap();
+        let reset = reset_keybinds(folder.clone()).unwrap();
+        let reloaded = load_app_config(folder).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.keybinds["bold"], "Ctrl+Shift+B");
+        assert_eq!(loaded.keybinds["find"], "");
+        assert_eq!(loaded.keybinds["italic"], "Ctrl+I");
+        assert_eq!(loaded.keybinds.len(), DEFAULT_KEYBINDS.len());
+        assert_eq!(reset, default_keybinds());
+        assert_eq!(reloaded.keybinds["bold"], "Ctrl+B");
+    }
+
+    #[test]
+    fn test_save_backs_up_previous_config() {
+        let dir = temp_folder("config-backup");
+        let folder = Some(dir.to_string_lossy().to_string());
+
+        let mut config = AppConfig::default();
+        save_app_config(folder.clone(), &config).unwrap();
+        assert!(!dir.join(".loom").join("config.json.bak").exists());
+
+        config.current_theme = "light".to_string();
+        save_app_config(folder.clone(), &config).unwrap();
+        let backup = fs::read_to_string(dir.join(".loom").join("config.json.bak")).unwrap();
+        let current = load_app_config(folder).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let backup: AppConfig = serde_json::from_str(&backup).unwrap();
+        assert_eq!(backup.current_theme, "dark");
+        assert_eq!(current.current_theme, "light");
+    }
+
+    #[test]
+    fn test_corrupt_config_recovers_from_backup() {
+        let dir = temp_folder("config-recover");
+        let folder = Some(dir.to_string_lossy().to_string());
+
+        let config = AppConfig {
+            current_theme: "light".to_string(),
+            ..AppConfig::default()
+        };
+        save_app_config(folder.clone(), &config).unwrap();
+        save_app_config(folder.clone(), &config).unwrap();
+        fs::write(dir.join(".loom").join("config.json"), "{ corrupt").unwrap();
+
+        let recovered = load_app_config(folder.clone()).unwrap();
+
+        // Without a usable backup, defaults are used
+        fs::remove_file(dir.join(".loom").join("config.json.bak")).unwrap();
+        let fallback = load_app_config(folder).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(recovered.current_theme, "light");
+        assert_eq!(fallback.current_theme, "dark");
+    }
+
+    #[test]
+    fn test_file_settings_override_and_fallback() {
+        let dir = temp_folder("file-settings");
+        let folder = Some(dir.to_string_lossy().to_string());
+        let slides = dir.join("talks").join("slides.md").to_string_lossy().to_string();
+        let notes = dir.join("notes.md").to_string_lossy().to_string();
+
+        let overrides = HashMap::from([("current_theme".to_string(), serde_json::json!("light"))]);
+        set_file_settings(folder.clone(), slides.clone(), overrides).unwrap();
+
+        let slides_settings = get_file_settings(folder.clone(), slides.clone()).unwrap();
+        let notes_settings = get_file_settings(folder.clone(), notes).unwrap();
+        let config = load_app_config(folder.clone()).unwrap();
+
+        // Clearing the overrides falls back to the global value again
+        set_file_settings(folder.clone(), slides.clone(), HashMap::new()).unwrap();
+        let cleared = get_file_settings(folder, slides).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(slides_settings["current_theme"], "light");
+        assert_eq!(slides_settings["status_bar_visible"], true);
+        assert_eq!(notes_settings["current_theme"], "dark");
+        assert!(!notes_settings.contains_key("per_file_settings"));
+        assert!(config.per_file_settings.contains_key("talks/slides.md"));
+        assert_eq!(cleared["current_theme"], "dark");
+    }
+
+    #[test]
+    fn test_render_settings_come_from_config() {
+        let dir = temp_folder("render-options");
+        let folder = Some(dir.to_string_lossy().to_string());
+        let config = AppConfig {
+            hr_style: "dashed".to_string(),
+            bullet_glyphs: vec!["◦".to_string()],
+            allow_raw_html: true,
+            ..AppConfig::default()
+        };
+        save_app_config(folder.clone(), &config).unwrap();
+
+        // Requests as the frontend sends them, without any render settings
+        let render = |line: &str| {
+            let mut request: RenderRequest = serde_json::from_value(serde_json::json!({
+                "line": line,
+                "line_index": 0,
+                "all_lines": [line],
+                "is_editing": false,
+            }))
+            .unwrap();
+            request.apply_options(&load_render_options(folder.clone()));
+            render_markdown_line(request).html
+        };
+        let rule = render("---");
+        let item = render("- item");
+        let html = render("<b>kept</b><script>alert(1)</script>");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rule, "<hr class=\"hr hr-dashed\">");
+        assert!(item.contains("◦"));
+        assert!(html.contains("<b>kept</b>") && !html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_update_config_keeps_file_settings() {
+        let dir = temp_folder("update-config");
+        let folder = Some(dir.to_string_lossy().to_string());
+        let notes = dir.join("notes.md").to_string_lossy().to_string();
+
+        let config = AppConfig { use_trash: false, ..AppConfig::default() };
+        save_app_config(folder.clone(), &config).unwrap();
+        let overrides = HashMap::from([("word_wrap".to_string(), serde_json::json!(false))]);
+        set_file_settings(folder.clone(), notes.clone(), overrides).unwrap();
+
+        // Only the fields the settings panel manages, as the frontend sends them
+        let changes = serde_json::json!({ "current_theme": "light", "status_bar_visible": false, "custom_settings": {} });
+        update_app_config(folder.clone(), changes).unwrap();
+        let rejected = update_app_config(folder.clone(), serde_json::json!({ "font_size": "big" }));
+
+        let notes_settings = get_file_settings(folder.clone(), notes).unwrap();
+        let use_trash = load_use_trash(folder.clone());
+        let config = load_app_config(folder).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(notes_settings["word_wrap"], false);
+        assert_eq!(notes_settings["current_theme"], "light");
+        assert!(!config.status_bar_visible);
+        assert!(!config.use_trash && !use_trash);
+        assert!(rejected.is_err());
+    }
+
+    #[test]
+    fn test_cached_render_options_follow_saves_and_folder() {
+        let first_dir = temp_folder("render-options-a");
+        let second_dir = temp_folder("render-options-b");
+        let first = Some(first_dir.to_string_lossy().to_string());
+        let second = Some(second_dir.to_string_lossy().to_string());
+        save_app_config(first.clone(), &AppConfig { tab_size: 2, ..AppConfig::default() }).unwrap();
+        save_app_config(second.clone(), &AppConfig { tab_size: 8, ..AppConfig::default() }).unwrap();
+
+        let cache = create_render_options_state();
+        let loaded = cached_render_options(&cache, first.clone()).tab_size;
+        save_app_config(first.clone(), &AppConfig { tab_size: 3, ..AppConfig::default() }).unwrap();
+        let after_save = cached_render_options(&cache, first).tab_size;
+        let other_folder = cached_render_options(&cache, second).tab_size;
+        fs::remove_dir_all(&first_dir).unwrap();
+        fs::remove_dir_all(&second_dir).unwrap();
+
+        assert_eq!((loaded, after_save, other_folder), (2, 3, 8));
+    }
 }
@@ -0,0 +1,494 @@
+/**
+ * Word export
+ *
+ * Converts a document's markdown source to a .docx file. Headings map to
+ * Word's built-in heading styles, lists to Word numbering and pipe tables
+ * to tables. Math and syntax Word has no counterpart for is kept as text.
+ */
+
+use crate::links::is_remote_url;
+use crate::markdown::{
+    is_delimiter_row, list_depths, render_inline_markdown, split_row, DocumentBlockMap, RenderOptions, BLOCKQUOTE_RE,
+    HEADER_RE, HR_RE, LIST_RE,
+};
+use docx_rs::{
+    AbstractNumbering, AlignmentType, Docx, Hyperlink, HyperlinkType, IndentLevel, Level, LevelJc, LevelOverride,
+    LevelText, NumberFormat, Numbering, NumberingId, Paragraph, Pic, Run, RunFonts, SpecialIndentType, Start, Style,
+    StyleType, Table, TableCell, TableRow,
+};
+use image::ImageFormat;
+use scraper::{ElementRef, Html, Node};
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+const BULLET_LIST: usize = 1;
+const ORDERED_LIST: usize = 2;
+const CODE_FONT: &str = "Courier New";
+// Widest an embedded image is drawn, in pixels at 96 dpi (6 inches)
+const MAX_IMAGE_WIDTH: u32 = 576;
+const EMU_PER_PIXEL: u32 = 9525;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Format {
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    code: bool,
+}
+
+fn text_run(text: &str, format: Format) -> Run {
+    let mut run = Run::new().add_text(text);
+    if format.bold {
+        run = run.bold();
+    }
+    if format.italic {
+        run = run.italic();
+    }
+    if format.strike {
+        run = run.strike();
+    }
+    if format.code {
+        run = run.style("VerbatimChar");
+    }
+    run
+}
+
+/// Load a local image as a picture scaled to fit the page width
+///
+/// Images are converted to PNG, the only format docx-rs embeds as is.
+fn load_picture(path: &Path) -> Result<Pic, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read image: {}", e))?;
+    let img = image::load_from_memory(&data).map_err(|e| format!("Failed to decode image: {}", e))?;
+
+    let mut png = Cursor::new(Vec::new());
+    img.write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+
+    let (width, height) = (img.width().max(1), img.height().max(1));
+    let (shown_width, shown_height) = if width > MAX_IMAGE_WIDTH {
+        (MAX_IMAGE_WIDTH, (height as u64 * MAX_IMAGE_WIDTH as u64 / width as u64).max(1) as u32)
+    } else {
+        (width, height)
+    };
+    Ok(Pic::new_with_dimensions(png.into_inner(), width, height)
+        .size(shown_width * EMU_PER_PIXEL, shown_height * EMU_PER_PIXEL))
+}
+
+/// Runs for an image: the picture itself if it is local and readable,
+/// otherwise its alt text in brackets
+fn image_run(img: ElementRef, base_dir: &Path, format: Format) -> Run {
+    let src = img.value().attr("src").unwrap_or_default();
+    let src = html_escape::decode_html_entities(src).to_string();
+    let alt = img.value().attr("alt").filter(|alt| !alt.is_empty()).unwrap_or("image");
+
+    if !src.is_empty() && !is_remote_url(&src) {
+        let path = base_dir.join(&src);
+        match load_picture(&path) {
+            Ok(pic) => return Run::new().add_image(pic),
+            Err(e) => eprintln!("Failed to embed image {}: {}", path.display(), e),
+        }
+    }
+    text_run(&format!("[{}]", alt), Format { italic: true, ..format })
+}
+
+/// Runs for an element of rendered inline HTML, following strong/em/del/code nesting
+fn element_runs(element: ElementRef, format: Format, base_dir: &Path, runs: &mut Vec<Run>) {
+    let format = match element.value().name() {
+        "strong" => Format { bold: true, ..format },
+        "em" => Format { italic: true, ..format },
+        "del" => Format { strike: true, ..format },
+        "code" => Format { code: true, ..format },
+        "img" => {
+            runs.push(image_run(element, base_dir, format));
+            return;
+        }
+        _ => format,
+    };
+
+    for child in element.children() {
+        if let Some(child) = ElementRef::wrap(child) {
+            element_runs(child, format, base_dir, runs);
+        } else if let Node::Text(text) = child.value() {
+            runs.push(text_run(text, format));
+        }
+    }
+}
+
+/// Add the inline content of a markdown line to a paragraph
+///
+/// Top-level links become Word hyperlinks; links nested in emphasis keep
+/// their text only.
+fn add_inline(mut paragraph: Paragraph, text: &str, base_dir: &Path) -> Paragraph {
+    let fragment = Html::parse_fragment(&render_inline_markdown(text));
+
+    for child in fragment.root_element().children() {
+        let Some(element) = ElementRef::wrap(child) else {
+            if let Node::Text(text) = child.value() {
+                paragraph = paragraph.add_run(text_run(text, Format::default()));
+            }
+            continue;
+        };
+
+        let mut runs = Vec::new();
+        element_runs(element, Format::default(), base_dir, &mut runs);
+        let href = Some(element)
+            .filter(|element| element.value().name() == "a")
+            .and_then(|link| link.value().attr("href"));
+        if let Some(href) = href {
+            let mut hyperlink = match href.strip_prefix('#') {
+                Some(anchor) => Hyperlink::new(anchor, HyperlinkType::Anchor),
+                None => Hyperlink::new(href, HyperlinkType::External),
+            };
+            for run in runs {
+                hyperlink = hyperlink.add_run(run.style("Hyperlink"));
+            }
+            paragraph = paragraph.add_hyperlink(hyperlink);
+        } else {
+            for run in runs {
+                paragraph = paragraph.add_run(run);
+            }
+        }
+    }
+    paragraph
+}
+
+fn code_fonts() -> RunFonts {
+    RunFonts::new().ascii(CODE_FONT).hi_ansi(CODE_FONT).cs(CODE_FONT)
+}
+
+/// Styles the converted paragraphs and runs refer to
+///
+/// The heading style names are Word's built-in ones, so headings show up
+/// in the navigation pane and tables of contents.
+fn add_styles(mut docx: Docx) -> Docx {
+    // Heading sizes in half-points
+    for (i, size) in [32, 28, 24, 22, 22, 22].into_iter().enumerate() {
+        docx = docx.add_style(
+            Style::new(format!("Heading{}", i + 1), StyleType::Paragraph)
+                .name(format!("heading {}", i + 1))
+                .based_on("Normal")
+                .next("Normal")
+                .size(size)
+                .bold()
+                .outline_lvl(i),
+        );
+    }
+
+    docx.add_style(
+        Style::new("Quote", StyleType::Paragraph)
+            .name("Quote")
+            .based_on("Normal")
+            .italic()
+            .color("595959")
+            .indent(Some(720), None, None, None),
+    )
+    .add_style(
+        Style::new("SourceCode", StyleType::Paragraph)
+            .name("Source Code")
+            .based_on("Normal")
+            .fonts(code_fonts())
+            .size(20),
+    )
+    .add_style(Style::new("TableHeading", StyleType::Paragraph).name("Table Heading").based_on("Normal").bold())
+    .add_style(Style::new("VerbatimChar", StyleType::Character).name("Verbatim Char").fonts(code_fonts()))
+    .add_style(Style::new("Hyperlink", StyleType::Character).name("Hyperlink").color("0563C1").underline("single"))
+}
+
+/// Nine nesting levels of bullets or decimal numbers
+fn list_numbering(id: usize, ordered: bool) -> AbstractNumbering {
+    (0..9).fold(AbstractNumbering::new(id), |numbering, level| {
+        let (format, text) = if ordered {
+            ("decimal", format!("%{}.", level + 1))
+        } else {
+            ("bullet", ["•", "◦", "▪"][level % 3].to_string())
+        };
+        let indent = 360 * (level as i32 + 1);
+        numbering.add_level(
+            Level::new(level, Start::new(1), NumberFormat::new(format), LevelText::new(text), LevelJc::new("left"))
+                .indent(Some(indent), Some(SpecialIndentType::Hanging(360)), None, None),
+        )
+    })
+}
+
+/// The rows of the pipe table starting at `start`, delimiter row excluded,
+/// or None if no table starts there
+fn table_at(lines: &[String], map: &DocumentBlockMap, start: usize) -> Option<Vec<Vec<String>>> {
+    let is_row = |i: usize| {
+        let state = map.state(i);
+        !state.in_code && !state.in_math && lines[i].contains('|')
+    };
+    let has_delimiter = start + 1 < lines.len() && is_row(start + 1) && is_delimiter_row(&split_row(&lines[start + 1]));
+    if !is_row(start) || !has_delimiter {
+        return None;
+    }
+
+    let mut rows = vec![split_row(&lines[start])];
+    rows.extend((start + 2..lines.len()).take_while(|&i| is_row(i)).map(|i| split_row(&lines[i])));
+    Some(rows)
+}
+
+struct DocxWriter<'a> {
+    docx: Docx,
+    base_dir: &'a Path,
+    /// Consecutive plain lines, joined into one paragraph when it ends
+    paragraph: Vec<String>,
+    /// Numbering instance of the ordered list being written
+    ordered_list: Option<usize>,
+    next_numbering: usize,
+}
+
+impl<'a> DocxWriter<'a> {
+    fn new(base_dir: &'a Path) -> Self {
+        let docx = add_styles(Docx::new())
+            .add_abstract_numbering(list_numbering(BULLET_LIST, false))
+            .add_abstract_numbering(list_numbering(ORDERED_LIST, true))
+            .add_numbering(Numbering::new(BULLET_LIST, BULLET_LIST));
+
+        Self { docx, base_dir, paragraph: Vec::new(), ordered_list: None, next_numbering: ORDERED_LIST + 1 }
+    }
+
+    fn add(&mut self, paragraph: Paragraph) {
+        self.docx = std::mem::take(&mut self.docx).add_paragraph(paragraph);
+    }
+
+    fn flush_paragraph(&mut self) {
+        if !self.paragraph.is_empty() {
+            let text = self.paragraph.join(" ");
+            self.paragraph.clear();
+            self.add(add_inline(Paragraph::new(), &text, self.base_dir));
+        }
+    }
+
+    /// End any open paragraph and, unless `in_list`, any ordered list
+    fn end_blocks(&mut self, in_list: bool) {
+        self.flush_paragraph();
+        if !in_list {
+            self.ordered_list = None;
+        }
+    }
+
+    fn list_item(&mut self, depth: usize, number: Option<usize>, text: &str) {
+        self.end_blocks(true);
+        let level = depth.min(8);
+
+        let numbering = match number {
+            None => BULLET_LIST,
+            Some(_) if level > 0 => self.ordered_list.unwrap_or(BULLET_LIST),
+            Some(start) => match self.ordered_list {
+                Some(id) => id,
+                // A new list restarts at its first item's number
+                None => {
+                    let id = self.next_numbering;
+                    self.next_numbering += 1;
+                    self.docx = std::mem::take(&mut self.docx)
+                        .add_numbering(Numbering::new(id, ORDERED_LIST).add_override(LevelOverride::new(0).start(start)));
+                    self.ordered_list = Some(id);
+                    id
+                }
+            },
+        };
+
+        let paragraph = Paragraph::new().numbering(NumberingId::new(numbering), IndentLevel::new(level));
+        self.add(add_inline(paragraph, text, self.base_dir));
+    }
+
+    fn table(&mut self, rows: Vec<Vec<String>>) {
+        self.end_blocks(false);
+        let columns = rows[0].len();
+
+        let rows = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut cells)| {
+                cells.resize(columns, String::new());
+                let style = if i == 0 { "TableHeading" } else { "Normal" };
+                let cells = cells
+                    .iter()
+                    .map(|cell| add_inline(Paragraph::new().style(style), cell, self.base_dir))
+                    .map(|paragraph| TableCell::new().add_paragraph(paragraph))
+                    .collect();
+                TableRow::new(cells)
+            })
+            .collect();
+        self.docx = std::mem::take(&mut self.docx).add_table(Table::new(rows));
+    }
+
+    /// Convert one line outside any code or math block, given its list
+    /// nesting depth if it is a list item
+    fn line(&mut self, line: &str, list_depth: Option<usize>) {
+        if line.trim().is_empty() {
+            self.flush_paragraph();
+        } else if HR_RE.is_match(line) {
+            self.end_blocks(false);
+            self.add(Paragraph::new().align(AlignmentType::Center).add_run(Run::new().add_text("* * *")));
+        } else if let Some(cap) = HEADER_RE.captures(line) {
+            self.end_blocks(false);
+            let style = format!("Heading{}", cap[1].len());
+            self.add(add_inline(Paragraph::new().style(&style), &cap[2], self.base_dir));
+        } else if let Some(cap) = LIST_RE.captures(line) {
+            let number = cap.get(3).and_then(|n| n.as_str().parse().ok());
+            self.list_item(list_depth.unwrap_or(0), number, &cap[4]);
+        } else if let Some(cap) = BLOCKQUOTE_RE.captures(line) {
+            self.end_blocks(false);
+            self.add(add_inline(Paragraph::new().style("Quote"), &cap[1], self.base_dir));
+        } else {
+            // Text continues the open paragraph, so only an ordered list ends here
+            self.ordered_list = None;
+            self.paragraph.push(line.trim().to_string());
+        }
+    }
+
+    fn finish(mut self) -> Docx {
+        self.flush_paragraph();
+        self.docx
+    }
+}
+
+/// Convert a document to Word's format
+///
+/// Code block lines keep their text in a monospace style and math is
+/// written as its LaTeX source.
+fn build_docx(content: &str, base_dir: &Path) -> Docx {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let depths = list_depths(&lines, |i| map.state(i), RenderOptions::default().tab_size);
+    let mut writer = DocxWriter::new(base_dir);
+
+    let mut i = 0;
+    while i < lines.len() {
+        let state = map.state(i);
+        let line = &lines[i];
+
+        if state.code_start || state.code_end || state.math_start || state.math_end {
+            writer.end_blocks(false);
+        } else if state.in_code {
+            writer.add(Paragraph::new().style("SourceCode").add_run(Run::new().add_text(line)));
+        } else if state.in_math {
+            writer.add(Paragraph::new().align(AlignmentType::Center).add_run(Run::new().add_text(line).italic()));
+        } else if let Some(rows) = table_at(&lines, &map, i) {
+            // The header, the delimiter row and the body rows
+            i += rows.len() + 1;
+            writer.table(rows);
+            continue;
+        } else {
+            writer.line(line, depths[i]);
+        }
+        i += 1;
+    }
+
+    writer.finish()
+}
+
+/// Export a document to a Word (.docx) file
+///
+/// Local images are embedded, resolved against the document's folder (or
+/// the destination's folder if no document is given). Remote images and
+/// ones that can't be read are replaced by their alt text.
+#[tauri::command]
+pub fn export_document_docx(content: String, dest_path: String, document_path: Option<String>) -> Result<(), String> {
+    let dest = Path::new(&dest_path);
+    let base_dir = document_path
+        .as_deref()
+        .map(Path::new)
+        .unwrap_or(dest)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+
+    let file = fs::File::create(dest).map_err(|e| format!("Failed to create DOCX file: {}", e))?;
+    build_docx(&content, &base_dir)
+        .build()
+        .pack(file)
+        .map_err(|e| format!("Failed to write DOCX file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn document_xml(docx: &[u8]) -> String {
+        let mut archive = zip::ZipArchive::new(Cursor::new(docx)).unwrap();
+        let mut xml = String::new();
+        archive.by_name("word/document.xml").unwrap().read_to_string(&mut xml).unwrap();
+        xml
+    }
+
+    #[test]
+    fn test_export_writes_docx() {
+        let path = std::env::temp_dir().join(format!("loom-export-{}.docx", std::process::id()));
+        let result = export_document_docx(
+            "# Plan\n\n- first **bold** step\n- second step\n".to_string(),
+            path.to_string_lossy().to_string(),
+            None,
+        );
+        let docx = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let xml = document_xml(&docx);
+
+        assert!(result.is_ok());
+        assert!(xml.contains(r#"<w:pStyle w:val="Heading1" />"#));
+        assert_eq!(xml.matches(&format!(r#"<w:numId w:val="{}" />"#, BULLET_LIST)).count(), 2);
+        assert!(xml.contains("<w:b />"));
+        assert!(xml.contains(">bold</w:t>"));
+    }
+
+    #[test]
+    fn test_tables_and_ordered_lists() {
+        let content = "| Name | Qty |\n|:-----|----:|\n| Pens | 3 |\n| Ink |\n\n1. one\n2. two\n\nText\n\n3. three";
+        let mut docx = Cursor::new(Vec::new());
+        build_docx(content, Path::new(".")).build().pack(&mut docx).unwrap();
+        let xml = document_xml(docx.get_ref());
+
+        assert_eq!(xml.matches("<w:tr>").count(), 3);
+        assert_eq!(xml.matches("<w:tc>").count(), 6);
+        assert!(!xml.contains("-----"));
+        // Each ordered list gets its own numbering so it restarts
+        assert_eq!(xml.matches(r#"<w:numId w:val="3" />"#).count(), 2);
+        assert_eq!(xml.matches(r#"<w:numId w:val="4" />"#).count(), 1);
+    }
+
+    #[test]
+    fn test_list_levels_follow_nesting() {
+        let content = "- one\n    - four-space child\n        - grandchild\n- two\n  - two-space child";
+        let mut docx = Cursor::new(Vec::new());
+        build_docx(content, Path::new(".")).build().pack(&mut docx).unwrap();
+        let xml = document_xml(docx.get_ref());
+
+        let levels: Vec<&str> = xml
+            .match_indices(r#"<w:ilvl w:val=""#)
+            .map(|(i, m)| &xml[i + m.len()..i + m.len() + 1])
+            .collect();
+        assert_eq!(levels, ["0", "1", "2", "0", "1"]);
+    }
+
+    #[test]
+    fn test_consecutive_lines_join_into_one_paragraph() {
+        let mut docx = Cursor::new(Vec::new());
+        build_docx("First line\nsecond line\n\nNext paragraph", Path::new(".")).build().pack(&mut docx).unwrap();
+        let xml = document_xml(docx.get_ref());
+
+        assert_eq!(xml.matches("<w:p>").count() + xml.matches("<w:p ").count(), 2);
+        assert!(xml.contains("First line second line"));
+    }
+
+    #[test]
+    fn test_local_images_are_embedded() {
+        let dir = std::env::temp_dir().join(format!("loom-docx-images-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        image::RgbImage::new(4, 2).save(dir.join("dot.png")).unwrap();
+
+        let mut docx = Cursor::new(Vec::new());
+        build_docx("![dot](dot.png) and ![diagram](missing.png)", &dir).build().pack(&mut docx).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let archive = zip::ZipArchive::new(Cursor::new(docx.get_ref())).unwrap();
+        let media = archive
+            .file_names()
+            .filter(|name| name.starts_with("word/media/") && name.ends_with(".png"))
+            .count();
+
+        assert_eq!(media, 1);
+        assert!(document_xml(docx.get_ref()).contains("[diagram]"));
+    }
+}
@@ -0,0 +1,358 @@
+/**
+ * Document export
+ *
+ * Renders a whole document with the markdown renderer and wraps it in a
+ * standalone HTML page styled with the theme's variables.
+ */
+
+use crate::config::{is_safe_theme_variable, load_render_options, ThemeConfig};
+use crate::images::detect_image_extension;
+use crate::links::is_remote_url;
+use crate::markdown::{heading_slug, render_document, RenderOptions};
+use base64::{engine::general_purpose, Engine as _};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+static IMG_SRC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r#"<img src="([^"]*)""#).unwrap());
+
+const KATEX_VERSION: &str = "0.16.9";
+
+// Subset of the editor stylesheet needed to display rendered lines
+const EXPORT_CSS: &str = r#"
+body {
+  margin: 0;
+  background-color: var(--bg-primary);
+  color: var(--text-primary);
+  font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
+  line-height: 1.6;
+}
+main { max-width: 800px; margin: 0 auto; padding: 40px 20px; }
+.editor-line { min-height: 1.7em; margin-bottom: 0.2em; padding: 2px 0; }
+.heading { display: block; font-weight: 600; line-height: 1.3; margin: 0.5em 0; }
+.heading.h1 { font-size: 2.5em; color: var(--h1-color); border-bottom: 2px solid var(--border-color); padding-bottom: 0.3em; font-weight: 700; }
+.heading.h2 { font-size: 2em; color: var(--h2-color); border-bottom: 1px solid var(--border-color); padding-bottom: 0.3em; font-weight: 700; }
+.heading.h3 { font-size: 1.6em; color: var(--h3-color); }
+.heading.h4 { font-size: 1.3em; color: var(--h4-color); }
+.heading.h5 { font-size: 1.1em; color: var(--h5-color); }
+.heading.h6 { font-size: 1em; color: var(--h6-color); text-transform: uppercase; letter-spacing: 0.05em; }
+strong { font-weight: 700; color: var(--strong-color); }
+em { font-style: italic; color: var(--em-color); }
+del { text-decoration: line-through; color: var(--del-color); opacity: 0.7; }
+code { background-color: var(--code-bg); color: var(--code-color); padding: 2px 6px; border-radius: 3px; font-family: "Consolas", "Monaco", "Courier New", monospace; font-size: 0.9em; border: 1px solid var(--border-color); }
+.code-block-start, .code-block-end, .math-block-start, .math-block-end { display: none; }
+.code-block-line { display: block; padding: 0; border: none; border-radius: 0; line-height: 1.5; white-space: pre; overflow-x: auto; }
+.math-block { display: block; color: var(--h3-color); padding: 0.2em 0; }
+.markdown-image { max-width: 100%; height: auto; border-radius: 4px; margin: 8px 0; display: block; }
+a { color: var(--link-color); text-decoration: none; }
+.list-item { display: block; margin: 0.3em 0; }
+.list-marker { display: inline-block; min-width: 1.5em; margin-right: 0.25em; color: var(--accent-color); font-weight: 600; }
+.list-marker.ordered { color: var(--h3-color); }
+.blockquote { display: block; border-left: 4px solid var(--blockquote-border); padding-left: 1em; margin: 0.5em 0; color: var(--blockquote-color); font-style: italic; }
+.hr { display: block; text-align: center; color: var(--border-color); margin: 1.5em 0; }
+hr.hr { border: none; border-top: 1px solid var(--hr-color); }
+hr.hr-dashed { border-top-style: dashed; }
+"#;
+
+// Sample document shown in theme picker previews
+const PREVIEW_SAMPLE: &str = "# The quick brown fox\n\
+Some **bold** and *italic* text with [a link](https://example.com).\n\
+```rust\n\
+fn main() {}\n\
+```\n\
+> A quoted line";
+
+// Renders math blocks in display mode and inline $...$ elsewhere
+const MATH_SCRIPT: &str = r#"
+document.addEventListener("DOMContentLoaded", function () {
+  document.querySelectorAll(".math-block:not(.math-rendered)").forEach(function (el) {
+    katex.render(el.dataset.latex, el, { displayMode: true, throwOnError: false });
+  });
+  renderMathInElement(document.body, {
+    delimiters: [{ left: "$", right: "$", display: false }],
+    ignoredClasses: ["math-block", "code-block-line"],
+    throwOnError: false,
+  });
+});
+"#;
+
+/// Build the theme's variables as a CSS rule for `selector`
+///
+/// Variables that could break out of the rule or its `<style>` element are
+/// left out with a warning.
+fn theme_css(theme: &ThemeConfig, selector: &str) -> String {
+    let mut variables: Vec<_> = theme
+        .variables
+        .iter()
+        .filter(|(key, value)| {
+            let safe = is_safe_theme_variable(key, value);
+            if !safe {
+                eprintln!("Skipping unsafe variable '{}' in theme '{}'", key, theme.name);
+            }
+            safe
+        })
+        .collect();
+    variables.sort();
+
+    let declarations: String = variables
+        .iter()
+        .map(|(key, value)| format!("  --{}: {};\n", key, value))
+        .collect();
+
+    format!("{} {{\n{}}}\n", selector, declarations)
+}
+
+/// MIME type for an image file, from its contents or else its extension
+fn image_mime_type(path: &Path, data: &[u8]) -> &'static str {
+    let extension = detect_image_extension(data)
+        .map(str::to_string)
+        .or_else(|| path.extension().and_then(|s| s.to_str()).map(str::to_lowercase));
+
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("svg") => "image/svg+xml",
+        Some("bmp") => "image/bmp",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Restrict a stylesheet to the elements under `scope`
+///
+/// `body` rules apply to the scope element itself; `main` rules are page
+/// layout and are dropped.
+fn scoped_css(css: &str, scope: &str) -> String {
+    css.split('}')
+        .filter_map(|rule| rule.split_once('{'))
+        .filter(|(selectors, _)| selectors.trim() != "main")
+        .map(|(selectors, body)| {
+            let selectors: Vec<String> = selectors
+                .split(',')
+                .map(|selector| match selector.trim() {
+                    "body" => scope.to_string(),
+                    selector => format!("{} {}", scope, selector),
+                })
+                .collect();
+            format!("{} {{{}}}\n", selectors.join(", "), body)
+        })
+        .collect()
+}
+
+/// Render a sample document styled with a theme, for a theme picker card
+///
+/// The theme's variables and styles are scoped to the returned element, so
+/// previews of several themes can sit on one page without switching the
+/// live theme.
+#[tauri::command]
+pub fn render_theme_preview(theme: ThemeConfig) -> String {
+    let slug = heading_slug(&theme.name);
+    let scope = format!(".theme-preview-{}", if slug.is_empty() { "theme" } else { &slug });
+
+    format!(
+        "<div class=\"theme-preview {class}\">\n<style>\n{theme}{css}{scope} {{ padding: 12px 16px; }}\n</style>\n{body}</div>\n",
+        class = &scope[1..],
+        theme = theme_css(&theme, &scope),
+        css = scoped_css(EXPORT_CSS, &scope),
+        scope = scope,
+        body = render_document(PREVIEW_SAMPLE, &RenderOptions::default()),
+    )
+}
+
+/// Replace local image sources with base64 `data:` URIs
+///
+/// Relative paths are resolved against `base_dir`. Remote URLs and images
+/// that can't be read are left as they are.
+pub fn embed_images(html: &str, base_dir: &Path) -> String {
+    IMG_SRC_RE
+        .replace_all(html, |caps: &regex::Captures| {
+            let src = html_escape::decode_html_entities(&caps[1]).to_string();
+
+            // Leave URLs (http:, https:, data:, ...) alone
+            if is_remote_url(&src) {
+                return caps[0].to_string();
+            }
+
+            let path = base_dir.join(&src);
+            match fs::read(&path) {
+                Ok(data) => format!(
+                    "<img src=\"data:{};base64,{}\"",
+                    image_mime_type(&path, &data),
+                    general_purpose::STANDARD.encode(&data)
+                ),
+                Err(e) => {
+                    eprintln!("Failed to embed image {}: {}", path.display(), e);
+                    caps[0].to_string()
+                }
+            }
+        })
+        .to_string()
+}
+
+/// Render a document to a complete standalone HTML page
+///
+/// With `server_side_math` set in `options`, math blocks are rendered to
+/// MathML up front; anything left over is still picked up by KaTeX's
+/// auto-render.
+pub fn render_html_page(content: &str, theme: &ThemeConfig, title: &str, options: &RenderOptions) -> String {
+    let katex_base = format!("https://cdn.jsdelivr.net/npm/katex@{}/dist", KATEX_VERSION);
+
+    format!(
+        "<!DOCTYPE html>\n\
+        <html>\n\
+        <head>\n\
+        <meta charset=\"utf-8\">\n\
+        <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+        <title>{title}</title>\n\
+        <link rel=\"stylesheet\" href=\"{katex}/katex.min.css\">\n\
+        <script defer src=\"{katex}/katex.min.js\"></script>\n\
+        <script defer src=\"{katex}/contrib/auto-render.min.js\"></script>\n\
+        <script>{script}</script>\n\
+        <style>\n{theme}{css}</style>\n\
+        </head>\n\
+        <body>\n\
+        <main>\n{body}</main>\n\
+        </body>\n\
+        </html>\n",
+        title = html_escape::encode_text(title),
+        katex = katex_base,
+        script = MATH_SCRIPT,
+        theme = theme_css(theme, ":root"),
+        css = EXPORT_CSS,
+        body = render_document(content, options),
+    )
+}
+
+/// Export a rendered document to a standalone HTML file
+///
+/// With `embed_assets`, local images are inlined as data URIs so the file
+/// is fully self-contained. Relative image paths are resolved against the
+/// document's folder (or the destination's folder if no document is given).
+/// Rendering follows the folder's settings; `server_side_math`, if given,
+/// overrides the configured choice of exporting math blocks as MathML.
+#[tauri::command]
+pub fn export_document_html(
+    content: String,
+    theme: ThemeConfig,
+    dest_path: String,
+    embed_assets: Option<bool>,
+    document_path: Option<String>,
+    server_side_math: Option<bool>,
+    folder_path: Option<String>,
+) -> Result<(), String> {
+    let dest = Path::new(&dest_path);
+    let title = dest
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Document")
+        .to_string();
+
+    let mut options = load_render_options(folder_path);
+    if let Some(server_side_math) = server_side_math {
+        options.server_side_math = server_side_math;
+    }
+    let mut html = render_html_page(&content, &theme, &title, &options);
+
+    if embed_assets.unwrap_or(false) {
+        let base_dir = document_path
+            .as_deref()
+            .map(Path::new)
+            .unwrap_or(dest)
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        html = embed_images(&html, &base_dir);
+    }
+
+    fs::write(&dest_path, html)
+        .map_err(|e| format!("Failed to write HTML file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_dark_theme;
+
+    #[test]
+    fn test_export_document_html() {
+        let dir = std::env::temp_dir().join(format!("loom-export-html-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("notes.html");
+
+        let content = "# Title\n\nSome **bold** text\n\n## Section".to_string();
+        let theme = get_default_dark_theme();
+        let folder = Some(dir.to_string_lossy().to_string());
+        export_document_html(content, theme.clone(), dest.to_string_lossy().to_string(), None, None, None, folder).unwrap();
+        let html = fs::read_to_string(&dest).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>notes</title>"));
+        assert!(html.contains("<span class=\"heading h1\" id=\"title\">Title</span>"));
+        assert!(html.contains("<span class=\"heading h2\" id=\"section\">Section</span>"));
+        assert!(html.contains(&format!("--bg-primary: {};", theme.variables["bg-primary"])));
+        assert!(html.contains("katex.min.js"));
+    }
+
+    #[test]
+    fn test_render_theme_preview() {
+        let theme = get_default_dark_theme();
+        let html = render_theme_preview(theme.clone());
+
+        assert!(html.starts_with("<div class=\"theme-preview theme-preview-dark\">"));
+        assert!(html.contains("<span class=\"heading h1\" id=\"the-quick-brown-fox\">The quick brown fox</span>"));
+        assert!(html.contains(&format!("--bg-primary: {};", theme.variables["bg-primary"])));
+        assert!(html.contains(".theme-preview-dark .blockquote {"));
+        assert!(!html.contains(":root"));
+    }
+
+    #[test]
+    fn test_unsafe_theme_variables_are_left_out() {
+        let mut theme = get_default_dark_theme();
+        theme.variables.insert("accent-color".to_string(), "red</style><img src=x onerror=alert(1)>".to_string());
+        theme.variables.insert("x} body {color: red".to_string(), "blue".to_string());
+
+        let preview = render_theme_preview(theme.clone());
+        let page = render_html_page("text", &theme, "page", &RenderOptions::default());
+
+        for html in [preview, page] {
+            assert!(!html.contains("onerror"));
+            assert!(!html.contains("--accent-color:"));
+            assert!(!html.contains("color: red"));
+            assert!(html.contains(&format!("--bg-primary: {};", theme.variables["bg-primary"])));
+        }
+    }
+
+    #[test]
+    fn test_export_embeds_local_images() {
+        let dir = std::env::temp_dir().join(format!("loom-export-embed-{}", std::process::id()));
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        fs::write(dir.join("assets").join("local.png"), png).unwrap();
+        let document = dir.join("note.md");
+        let dest = dir.join("out").join("note.html");
+        fs::create_dir_all(dest.parent().unwrap()).unwrap();
+
+        let content = "![local](assets/local.png)\n![remote](https://example.com/remote.png)".to_string();
+        export_document_html(
+            content,
+            get_default_dark_theme(),
+            dest.to_string_lossy().to_string(),
+            Some(true),
+            Some(document.to_string_lossy().to_string()),
+            None,
+            Some(dir.to_string_lossy().to_string()),
+        )
+        .unwrap();
+        let html = fs::read_to_string(&dest).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let encoded = general_purpose::STANDARD.encode(png);
+        assert!(html.contains(&format!("<img src=\"data:image/png;base64,{}\"", encoded)));
+        assert!(html.contains("<img src=\"https://example.com/remote.png\""));
+        assert!(!html.contains("assets/local.png"));
+    }
+}
@@ -0,0 +1,768 @@
+use crate::text_file::read_text_file;
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+// File tree structures
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub children: Option<Vec<FileEntry>>,
+}
+
+// Read directory contents recursively
+pub fn read_directory(path: String, respect_gitignore: bool) -> Result<Vec<FileEntry>, String> {
+    let dir_path = PathBuf::from(&path);
+
+    if !dir_path.exists() {
+        return Err("Directory does not exist".to_string());
+    }
+
+    if !dir_path.is_dir() {
+        return Err("Path is not a directory".to_string());
+    }
+
+    read_dir_recursive(&dir_path, respect_gitignore)
+}
+
+fn read_dir_recursive(dir_path: &PathBuf, respect_gitignore: bool) -> Result<Vec<FileEntry>, String> {
+    let mut entries = Vec::new();
+
+    // The entries .gitignore files (here or in parent folders) don't exclude
+    let not_ignored: Option<HashSet<PathBuf>> = respect_gitignore.then(|| {
+        WalkBuilder::new(dir_path)
+            .max_depth(Some(1))
+            .hidden(false)
+            .git_global(false)
+            .require_git(false)
+            .build()
+            .filter_map(|e| e.ok())
+            .map(|entry| entry.into_path())
+            .collect()
+    });
+
+    let dir_entries = fs::read_dir(dir_path)
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    for entry in dir_entries {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        // Skip hidden files and directories (starting with .)
+        if name.starts_with('.') {
+            continue;
+        }
+
+        if not_ignored.as_ref().is_some_and(|allowed| !allowed.contains(&path)) {
+            continue;
+        }
+
+        let is_dir = path.is_dir();
+        let path_str = path.to_string_lossy().to_string();
+
+        let children = if is_dir {
+            // Don't recursively read children here - we'll do it on demand in the UI
+            Some(Vec::new())
+        } else {
+            None
+        };
+
+        entries.push(FileEntry {
+            name,
+            path: path_str,
+            is_dir,
+            children,
+        });
+    }
+
+    // Sort: directories first, then files, alphabetically within each group
+    entries.sort_by(|a, b| {
+        match (a.is_dir, b.is_dir) {
+            (true, false) => std::cmp::Ordering::Less,
+            (false, true) => std::cmp::Ordering::Greater,
+            _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        }
+    });
+
+    Ok(entries)
+}
+
+// Read file contents from a path, decoding non-UTF-8 files and dropping any BOM
+pub fn read_file_from_path(path: String) -> Result<String, String> {
+    read_text_file(Path::new(&path)).map(|decoded| decoded.content)
+}
+
+// Names Windows reserves for devices, with or without an extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+// Check that a file or folder name is a single path component, so joining it
+// onto a directory can't point somewhere else
+pub fn validate_file_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Name cannot be empty".to_string());
+    }
+
+    if name == "." || name == ".." {
+        return Err(format!("\"{}\" is not a valid name", name));
+    }
+
+    if name.contains(['/', '\\']) {
+        return Err("Name cannot contain path separators".to_string());
+    }
+
+    if name.chars().any(|c| c.is_control()) {
+        return Err("Name cannot contain control characters".to_string());
+    }
+
+    if cfg!(windows) {
+        if let Some(c) = name.chars().find(|c| matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*')) {
+            return Err(format!("Name cannot contain '{}'", c));
+        }
+
+        if name.ends_with(['.', ' ']) {
+            return Err("Name cannot end with a dot or a space".to_string());
+        }
+
+        let stem = name.split('.').next().unwrap_or(name).trim_end();
+        if WINDOWS_RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+            return Err(format!("\"{}\" is a reserved name", name));
+        }
+    }
+
+    Ok(())
+}
+
+// The last component of a path as typed, before any normalization
+fn leaf_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+// Create a new file, optionally pre-filled (e.g. from a template)
+pub fn create_file(path: String, content: Option<String>) -> Result<(), String> {
+    validate_file_name(leaf_name(&path))?;
+
+    let file_path = PathBuf::from(&path);
+
+    // Check if parent directory exists
+    if let Some(parent) = file_path.parent() {
+        if !parent.exists() {
+            return Err(format!("Parent directory does not exist: {:?}", parent));
+        }
+    }
+
+    // Check if file already exists
+    if file_path.exists() {
+        return Err("File already exists".to_string());
+    }
+
+    // Create the file
+    fs::write(&file_path, content.unwrap_or_default()).map_err(|e| format!("Failed to create file: {}", e))?;
+
+    // Verify the file was created
+    if !file_path.exists() {
+        return Err("File was not created successfully".to_string());
+    }
+
+    println!("File created successfully at: {:?}", file_path);
+    Ok(())
+}
+
+// Create a new folder
+pub fn create_folder(path: String) -> Result<(), String> {
+    validate_file_name(leaf_name(&path))?;
+
+    let dir_path = PathBuf::from(&path);
+
+    // Check if parent directory exists
+    if let Some(parent) = dir_path.parent() {
+        if !parent.exists() {
+            return Err("Parent directory does not exist".to_string());
+        }
+    }
+
+    // Check if folder already exists
+    if dir_path.exists() {
+        return Err("Folder already exists".to_string());
+    }
+
+    // Create the folder
+    fs::create_dir(&dir_path)
+        .map_err(|e| format!("Failed to create folder: {}", e))
+}
+
+// Delete a file, moving it to the system trash if asked
+pub fn delete_file(path: String, use_trash: bool) -> Result<(), String> {
+    let file_path = PathBuf::from(&path);
+
+    // Check if file exists
+    if !file_path.exists() {
+        return Err("File does not exist".to_string());
+    }
+
+    // Check if it's actually a file
+    if !file_path.is_file() {
+        return Err("Path is not a file".to_string());
+    }
+
+    // Delete the file
+    if use_trash {
+        trash::delete(&file_path)
+            .map_err(|e| format!("Failed to move file to trash: {}", e))?;
+    } else {
+        fs::remove_file(&file_path)
+            .map_err(|e| format!("Failed to delete file: {}", e))?;
+    }
+
+    println!("File deleted successfully: {:?}", file_path);
+    Ok(())
+}
+
+// Delete a folder (recursively), moving it to the system trash if asked
+pub fn delete_folder(path: String, use_trash: bool) -> Result<(), String> {
+    let dir_path = PathBuf::from(&path);
+
+    // Check if folder exists
+    if !dir_path.exists() {
+        return Err("Folder does not exist".to_string());
+    }
+
+    // Check if it's actually a directory
+    if !dir_path.is_dir() {
+        return Err("Path is not a folder".to_string());
+    }
+
+    // Delete the folder recursively
+    if use_trash {
+        trash::delete(&dir_path)
+            .map_err(|e| format!("Failed to move folder to trash: {}", e))?;
+    } else {
+        fs::remove_dir_all(&dir_path)
+            .map_err(|e| format!("Failed to delete folder: {}", e))?;
+    }
+
+    println!("Folder deleted successfully: {:?}", dir_path);
+    Ok(())
+}
+
+// Count contents of a folder (files and subfolders)
+pub fn count_folder_contents(path: String) -> Result<(usize, usize), String> {
+    let dir_path = PathBuf::from(&path);
+
+    if !dir_path.exists() {
+        return Err("Folder does not exist".to_string());
+    }
+
+    if !dir_path.is_dir() {
+        return Err("Path is not a folder".to_string());
+    }
+
+    let mut file_count = 0;
+    let mut folder_count = 0;
+
+    let entries = fs::read_dir(&dir_path)
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        // Skip hidden files and directories
+        if name.starts_with('.') {
+            continue;
+        }
+
+        if path.is_dir() {
+            folder_count += 1;
+        } else {
+            file_count += 1;
+        }
+    }
+
+    Ok((file_count, folder_count))
+}
+
+// Count all files and subfolders below a folder, for the delete confirmation
+pub fn count_folder_contents_recursive(path: String) -> Result<(usize, usize), String> {
+    let dir_path = PathBuf::from(&path);
+
+    if !dir_path.exists() {
+        return Err("Folder does not exist".to_string());
+    }
+
+    if !dir_path.is_dir() {
+        return Err("Path is not a folder".to_string());
+    }
+
+    let mut file_count = 0;
+    let mut folder_count = 0;
+
+    // Symlinks are counted but not followed, so link loops can't recurse forever
+    let entries = WalkDir::new(&dir_path)
+        .follow_links(false)
+        .min_depth(1)
+        .into_iter()
+        // Skip hidden files and directories, including .loom
+        .filter_entry(|entry| !entry.file_name().to_string_lossy().starts_with('.'));
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+
+        if entry.file_type().is_dir() {
+            folder_count += 1;
+        } else {
+            file_count += 1;
+        }
+    }
+
+    Ok((file_count, folder_count))
+}
+
+// Rename a file or folder
+pub fn rename_path(old_path: String, new_name: String) -> Result<String, String> {
+    validate_file_name(&new_name)?;
+
+    let old_path_buf = PathBuf::from(&old_path);
+
+    // Check if path exists
+    if !old_path_buf.exists() {
+        return Err("Path does not exist".to_string());
+    }
+
+    // Get parent directory
+    let parent = old_path_buf.parent()
+        .ok_or_else(|| "Cannot get parent directory".to_string())?;
+
+    // Create new path
+    let new_path_buf = parent.join(&new_name);
+
+    // Check if new path already exists
+    if new_path_buf.exists() {
+        return Err("A file or folder with that name already exists".to_string());
+    }
+
+    // Rename
+    fs::rename(&old_path_buf, &new_path_buf)
+        .map_err(|e| format!("Failed to rename: {}", e))?;
+
+    let new_path = new_path_buf.to_string_lossy().to_string();
+    println!("Renamed {:?} to {:?}", old_path, new_path);
+    Ok(new_path)
+}
+
+// Check that a folder isn't being copied or moved into itself or one of its subfolders
+fn ensure_not_into_own_subtree(source: &Path, dest_dir: &Path) -> Result<(), String> {
+    let source = source.canonicalize()
+        .map_err(|e| format!("Failed to resolve source path: {}", e))?;
+    let dest_dir = dest_dir.canonicalize()
+        .map_err(|e| format!("Failed to resolve destination path: {}", e))?;
+
+    if source.is_dir() && dest_dir.starts_with(&source) {
+        return Err("Cannot copy or move a folder into itself or one of its subfolders".to_string());
+    }
+
+    Ok(())
+}
+
+// Move a file or folder to a different directory
+pub fn move_path(source_path: String, dest_dir_path: String) -> Result<String, String> {
+    let source_path_buf = PathBuf::from(&source_path);
+    let dest_dir_buf = PathBuf::from(&dest_dir_path);
+
+    // Check if source exists
+    if !source_path_buf.exists() {
+        return Err("Source path does not exist".to_string());
+    }
+
+    // Check if destination directory exists
+    if !dest_dir_buf.exists() {
+        return Err("Destination directory does not exist".to_string());
+    }
+
+    // Check if destination is a directory
+    if !dest_dir_buf.is_dir() {
+        return Err("Destination must be a directory".to_string());
+    }
+
+    ensure_not_into_own_subtree(&source_path_buf, &dest_dir_buf)?;
+
+    // Get the file/folder name
+    let name = source_path_buf.file_name()
+        .ok_or_else(|| "Cannot get source name".to_string())?;
+
+    // Create new path in destination directory
+    let new_path_buf = dest_dir_buf.join(name);
+
+    // Check if destination already has a file/folder with the same name
+    if new_path_buf.exists() {
+        return Err("A file or folder with that name already exists in the destination".to_string());
+    }
+
+    // Move (rename) the file/folder
+    fs::rename(&source_path_buf, &new_path_buf)
+        .map_err(|e| format!("Failed to move: {}", e))?;
+
+    let new_path = new_path_buf.to_string_lossy().to_string();
+    println!("Moved {:?} to {:?}", source_path, new_path);
+    Ok(new_path)
+}
+
+// Copy a file or folder to a different directory
+pub fn copy_path(source_path: String, dest_dir_path: String) -> Result<String, String> {
+    let source_path_buf = PathBuf::from(&source_path);
+    let dest_dir_buf = PathBuf::from(&dest_dir_path);
+
+    // Check if source exists
+    if !source_path_buf.exists() {
+        return Err("Source path does not exist".to_string());
+    }
+
+    // Check if destination directory exists
+    if !dest_dir_buf.exists() {
+        return Err("Destination directory does not exist".to_string());
+    }
+
+    // Check if destination is a directory
+    if !dest_dir_buf.is_dir() {
+        return Err("Destination must be a directory".to_string());
+    }
+
+    ensure_not_into_own_subtree(&source_path_buf, &dest_dir_buf)?;
+
+    // Get the file/folder name
+    let name = source_path_buf.file_name()
+        .ok_or_else(|| "Cannot get source name".to_string())?;
+
+    // Create new path in destination directory
+    let new_path_buf = dest_dir_buf.join(name);
+
+    // Check if destination already has a file/folder with the same name
+    if new_path_buf.exists() {
+        return Err("A file or folder with that name already exists in the destination".to_string());
+    }
+
+    // Copy the file or folder
+    if source_path_buf.is_file() {
+        // Copy file
+        fs::copy(&source_path_buf, &new_path_buf)
+            .map_err(|e| format!("Failed to copy file: {}", e))?;
+    } else if source_path_buf.is_dir() {
+        // Copy directory recursively with depth limit
+        const MAX_DEPTH: usize = 100;
+        copy_dir_recursive(&source_path_buf, &new_path_buf, 0, MAX_DEPTH)?;
+    } else {
+        return Err("Source is neither a file nor a directory".to_string());
+    }
+
+    let new_path = new_path_buf.to_string_lossy().to_string();
+    println!("Copied {:?} to {:?}", source_path, new_path);
+    Ok(new_path)
+}
+
+// Helper function to copy directory recursively with depth limit
+fn copy_dir_recursive(src: &PathBuf, dest: &PathBuf, depth: usize, max_depth: usize) -> Result<(), String> {
+    // Check depth limit to prevent stack overflow
+    if depth >= max_depth {
+        return Err(format!("Directory depth exceeds maximum limit of {}", max_depth));
+    }
+
+    // Create destination directory
+    fs::create_dir(dest)
+        .map_err(|e| format!("Failed to create directory: {}", e))?;
+
+    // Read source directory
+    let entries = fs::read_dir(src)
+        .map_err(|e| format!("Failed to read directory: {}", e))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name_str = name.to_string_lossy();
+        let dest_path = dest.join(&name);
+
+        // Skip hidden files and directories (starting with .)
+        if name_str.starts_with('.') {
+            println!("Skipping hidden file/directory: {:?}", name_str);
+            continue;
+        }
+
+        // Follow symlinks but don't copy the symlink itself
+        let metadata = fs::metadata(&path)
+            .map_err(|e| format!("Failed to read metadata: {}", e))?;
+
+        if metadata.is_file() {
+            fs::copy(&path, &dest_path)
+                .map_err(|e| format!("Failed to copy file: {}", e))?;
+        } else if metadata.is_dir() {
+            // Recursively copy subdirectory with incremented depth
+            copy_dir_recursive(&path, &dest_path, depth + 1, max_depth)?;
+        }
+        // Skip other types (symlinks, devices, etc.)
+    }
+
+    Ok(())
+}
+
+/// One step of a `batch_file_operations` call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum FileOp {
+    Create { path: String, is_dir: bool, content: Option<String> },
+    Delete { path: String },
+    Move { source_path: String, dest_dir_path: String },
+    Copy { source_path: String, dest_dir_path: String },
+    Rename { old_path: String, new_name: String },
+}
+
+impl FileOp {
+    // The paths the operation reads or writes, for the workspace check
+    fn paths(&self) -> Vec<&str> {
+        match self {
+            FileOp::Create { path, .. } | FileOp::Delete { path } => vec![path],
+            FileOp::Move { source_path, dest_dir_path } | FileOp::Copy { source_path, dest_dir_path } => {
+                vec![source_path, dest_dir_path]
+            }
+            FileOp::Rename { old_path, .. } => vec![old_path],
+        }
+    }
+
+    // Run the operation, returning the path it created if any
+    fn run(&self, use_trash: bool) -> Result<Option<String>, String> {
+        match self.clone() {
+            FileOp::Create { path, is_dir: false, content } => create_file(path, content).map(|_| None),
+            FileOp::Create { path, is_dir: true, .. } => create_folder(path).map(|_| None),
+            FileOp::Delete { path } if Path::new(&path).is_dir() => delete_folder(path, use_trash).map(|_| None),
+            FileOp::Delete { path } => delete_file(path, use_trash).map(|_| None),
+            FileOp::Move { source_path, dest_dir_path } => move_path(source_path, dest_dir_path).map(Some),
+            FileOp::Copy { source_path, dest_dir_path } => copy_path(source_path, dest_dir_path).map(Some),
+            FileOp::Rename { old_path, new_name } => rename_path(old_path, new_name).map(Some),
+        }
+    }
+}
+
+/// Outcome of one operation in a batch
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOpResult {
+    pub success: bool,
+    /// Path of the moved, copied or renamed entry
+    pub new_path: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Run file operations in order, reporting each one's result
+///
+/// A failed operation doesn't stop the batch unless `stop_on_error` is set,
+/// in which case the remaining operations are reported as skipped. Deletes
+/// go to the system trash if `use_trash` is set. `check` vets every path an
+/// operation touches before it runs.
+pub fn batch_file_operations(
+    ops: Vec<FileOp>,
+    stop_on_error: bool,
+    use_trash: bool,
+    check: impl Fn(&str) -> Result<(), String>,
+) -> Vec<FileOpResult> {
+    let mut results = Vec::with_capacity(ops.len());
+    let mut stopped = false;
+
+    for op in &ops {
+        if stopped {
+            results.push(FileOpResult {
+                success: false,
+                new_path: None,
+                error: Some("Skipped after an earlier operation failed".to_string()),
+            });
+            continue;
+        }
+
+        let outcome = op.paths().into_iter().try_for_each(&check).and_then(|_| op.run(use_trash));
+        stopped = stop_on_error && outcome.is_err();
+        results.push(match outcome {
+            Ok(new_path) => FileOpResult { success: true, new_path, error: None },
+            Err(error) => FileOpResult { success: false, new_path: None, error: Some(error) },
+        });
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_folder_contents_recursive() {
+        let dir = std::env::temp_dir().join(format!("loom-count-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a").join("b")).unwrap();
+        fs::create_dir_all(dir.join("c")).unwrap();
+        fs::create_dir_all(dir.join(".loom").join("themes")).unwrap();
+        fs::write(dir.join("root.md"), "").unwrap();
+        fs::write(dir.join("a").join("one.md"), "").unwrap();
+        fs::write(dir.join("a").join("b").join("two.md"), "").unwrap();
+        fs::write(dir.join("a").join("b").join("three.png"), "").unwrap();
+        fs::write(dir.join("a").join(".hidden.md"), "").unwrap();
+        fs::write(dir.join(".loom").join("config.json"), "").unwrap();
+
+        let recursive = count_folder_contents_recursive(dir.to_string_lossy().to_string());
+        let shallow = count_folder_contents(dir.to_string_lossy().to_string());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(recursive, Ok((4, 3)));
+        assert_eq!(shallow, Ok((1, 2)));
+    }
+
+    #[test]
+    fn test_rename_rejects_invalid_names() {
+        let dir = std::env::temp_dir().join(format!("loom-rename-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("note.md");
+        fs::write(&file, "").unwrap();
+        let file = file.to_string_lossy().to_string();
+
+        let slash = rename_path(file.clone(), "sub/evil.md".to_string());
+        let parent = rename_path(file.clone(), "..".to_string());
+        let empty = rename_path(file.clone(), "".to_string());
+        let created = create_file(dir.join("..").to_string_lossy().to_string(), None);
+        let renamed = rename_path(file, "renamed.md".to_string());
+        let still_there = dir.join("renamed.md").exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(slash, Err("Name cannot contain path separators".to_string()));
+        assert_eq!(parent, Err("\"..\" is not a valid name".to_string()));
+        assert_eq!(empty, Err("Name cannot be empty".to_string()));
+        assert!(created.is_err());
+        assert!(renamed.is_ok());
+        assert!(still_there);
+    }
+
+    #[test]
+    fn test_copy_and_move_into_own_subtree_fail() {
+        let dir = std::env::temp_dir().join(format!("loom-nesting-{}", std::process::id()));
+        let source = dir.join("a");
+        fs::create_dir_all(source.join("b")).unwrap();
+        fs::write(source.join("note.md"), "").unwrap();
+        let source_str = source.to_string_lossy().to_string();
+
+        let copy_into_child = copy_path(source_str.clone(), source.join("b").to_string_lossy().to_string());
+        let move_into_child = move_path(source_str.clone(), source.join("b").to_string_lossy().to_string());
+        let move_into_self = move_path(source_str.clone(), source_str.clone());
+        let copied_nothing = !source.join("b").join("a").exists();
+        let copy_to_parent = copy_path(source_str, dir.to_string_lossy().to_string());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let expected = Err("Cannot copy or move a folder into itself or one of its subfolders".to_string());
+        assert_eq!(copy_into_child, expected);
+        assert_eq!(move_into_child, expected);
+        assert_eq!(move_into_self, expected);
+        assert!(copied_nothing);
+        // The folder's own parent is a valid destination; it only fails because the name is taken
+        assert_eq!(
+            copy_to_parent,
+            Err("A file or folder with that name already exists in the destination".to_string())
+        );
+    }
+
+    #[test]
+    fn test_batch_continues_after_failure() {
+        let dir = std::env::temp_dir().join(format!("loom-batch-{}", std::process::id()));
+        fs::create_dir_all(dir.join("archive")).unwrap();
+        fs::write(dir.join("a.md"), "").unwrap();
+        fs::write(dir.join("b.md"), "").unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+        let ops = vec![
+            FileOp::Create { path: path("new.md"), is_dir: false, content: None },
+            FileOp::Move { source_path: path("missing.md"), dest_dir_path: path("archive") },
+            FileOp::Move { source_path: path("a.md"), dest_dir_path: path("archive") },
+            FileOp::Rename { old_path: path("b.md"), new_name: "c.md".to_string() },
+            FileOp::Delete { path: path("archive") },
+        ];
+        let results = batch_file_operations(ops.clone(), false, false, |_| Ok(()));
+        let b_renamed = dir.join("c.md").exists();
+        let archive_deleted = !dir.join("archive").exists();
+
+        fs::write(dir.join("d.md"), "").unwrap();
+        let stopped = batch_file_operations(
+            vec![
+                FileOp::Delete { path: path("missing.md") },
+                FileOp::Delete { path: path("d.md") },
+            ],
+            true,
+            false,
+            |_| Ok(()),
+        );
+        let d_kept = dir.join("d.md").exists();
+        let rejected = batch_file_operations(ops[..1].to_vec(), false, false, |_| Err("Outside".to_string()));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let successes: Vec<bool> = results.iter().map(|r| r.success).collect();
+        assert_eq!(successes, [true, false, true, true, true]);
+        assert_eq!(results[1].error.as_deref(), Some("Source path does not exist"));
+        assert_eq!(results[3].new_path, Some(path("c.md")));
+        assert!(b_renamed && archive_deleted);
+
+        assert!(!stopped[0].success && !stopped[1].success);
+        assert_eq!(stopped[1].error.as_deref(), Some("Skipped after an earlier operation failed"));
+        assert!(d_kept);
+        assert_eq!(rejected[0].error.as_deref(), Some("Outside"));
+    }
+
+    #[test]
+    fn test_file_op_deserializes_from_tagged_json() {
+        let op: FileOp =
+            serde_json::from_str(r#"{"type": "move", "sourcePath": "a.md", "destDirPath": "notes"}"#).unwrap();
+        assert!(matches!(op, FileOp::Move { source_path, dest_dir_path } if source_path == "a.md" && dest_dir_path == "notes"));
+    }
+
+    #[test]
+    fn test_create_file_with_content() {
+        let dir = std::env::temp_dir().join(format!("loom-create-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("daily.md").to_string_lossy().to_string();
+
+        let created = create_file(path.clone(), Some("---\ntags: [daily]\n---\n".to_string()));
+        let content = fs::read_to_string(&path).unwrap();
+        let overwrite = create_file(path.clone(), Some("replaced".to_string()));
+        let content_after = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(created.is_ok());
+        assert_eq!(content, "---\ntags: [daily]\n---\n");
+        assert_eq!(overwrite, Err("File already exists".to_string()));
+        assert_eq!(content_after, content);
+    }
+
+    #[test]
+    fn test_read_directory_respects_gitignore() {
+        let dir = std::env::temp_dir().join(format!("loom-tree-gitignore-{}", std::process::id()));
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::write(dir.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(dir.join("readme.md"), "").unwrap();
+        fs::write(dir.join("debug.log"), "").unwrap();
+
+        let names = |respect_gitignore| {
+            read_directory(dir.to_string_lossy().to_string(), respect_gitignore)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.name)
+                .collect::<Vec<_>>()
+        };
+        let everything = names(false);
+        let respected = names(true);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(everything, ["notes", "target", "debug.log", "readme.md"]);
+        assert_eq!(respected, ["notes", "readme.md"]);
+    }
+}
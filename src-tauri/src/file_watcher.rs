@@ -1,6 +1,10 @@
-use notify::{Watcher, RecursiveMode, Event};
-use std::path::Path;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Watcher, RecursiveMode, Event, EventKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 use serde::{Serialize, Deserialize};
 
@@ -8,25 +12,468 @@ use serde::{Serialize, Deserialize};
 pub struct FileSystemEvent {
     pub event_type: String,
     pub path: String,
+    /// For paired renames, the path before the rename
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    /// For paired renames, the path after the rename (same as `path`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_path: Option<String>,
+}
+
+impl FileSystemEvent {
+    fn new(event_type: &str, path: &Path) -> Self {
+        Self {
+            event_type: event_type.to_string(),
+            path: path.to_string_lossy().to_string(),
+            old_path: None,
+            new_path: None,
+        }
+    }
+
+    fn renamed(old_path: &Path, new_path: &Path) -> Self {
+        let new_path = new_path.to_string_lossy().to_string();
+        Self {
+            event_type: "rename".to_string(),
+            path: new_path.clone(),
+            old_path: Some(old_path.to_string_lossy().to_string()),
+            new_path: Some(new_path),
+        }
+    }
+}
+
+/// Minimum time between two "modify" events for the same path
+const MODIFY_THROTTLE_WINDOW: Duration = Duration::from_millis(500);
+
+/// How long a rename-from waits for its matching rename-to before it is
+/// treated as a delete (the file was moved out of the watched tree)
+const RENAME_PAIR_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Map a notify event kind to the event type sent to the frontend
+///
+/// Returns None for kinds we don't forward (metadata changes, access, etc.)
+fn classify_event(kind: &EventKind) -> Option<&'static str> {
+    match kind {
+        EventKind::Create(_) => Some("create"),
+        EventKind::Remove(_) => Some("delete"),
+        EventKind::Modify(ModifyKind::Name(_)) => Some("rename"),
+        EventKind::Modify(ModifyKind::Data(_)) => Some("modify"),
+        _ => None,
+    }
+}
+
+/// Per-path rate limiter for content modification events
+struct ModifyThrottle {
+    window: Duration,
+    last_emitted: HashMap<PathBuf, Instant>,
+}
+
+impl ModifyThrottle {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last_emitted: HashMap::new(),
+        }
+    }
+
+    /// Returns true if an event for this path should be emitted at `now`
+    fn should_emit(&mut self, path: &Path, now: Instant) -> bool {
+        // Drop stale entries so the map doesn't grow without bound
+        let window = self.window;
+        self.last_emitted.retain(|_, last| now.duration_since(*last) < window);
+
+        if self.last_emitted.contains_key(path) {
+            return false;
+        }
+
+        self.last_emitted.insert(path.to_path_buf(), now);
+        true
+    }
+}
+
+/// Pairs rename-from/rename-to halves using the cookie notify attaches to them
+#[derive(Default)]
+struct RenameTracker {
+    /// Rename-from halves waiting for their rename-to, keyed by cookie
+    pending: HashMap<usize, (PathBuf, Instant)>,
+    /// Cookies already paired from separate halves, so the combined
+    /// rename event some backends send afterwards isn't reported twice
+    paired: Vec<usize>,
+}
+
+impl RenameTracker {
+    /// Process a rename event and return the events to report
+    fn handle(&mut self, mode: RenameMode, cookie: Option<usize>, paths: &[PathBuf], now: Instant) -> Vec<FileSystemEvent> {
+        let mut events = self.expire(now);
+
+        match (mode, cookie, paths) {
+            (RenameMode::From, Some(cookie), [from, ..]) => {
+                self.pending.insert(cookie, (from.clone(), now));
+            }
+            // Without a cookie the other half can never be matched
+            (RenameMode::From, None, [from, ..]) => {
+                events.push(FileSystemEvent::new("delete", from));
+            }
+            (RenameMode::To, cookie, [to, ..]) => {
+                match cookie.and_then(|c| self.pending.remove(&c).map(|(from, _)| (c, from))) {
+                    Some((cookie, from)) => {
+                        self.paired.push(cookie);
+                        events.push(FileSystemEvent::renamed(&from, to));
+                    }
+                    None => events.push(FileSystemEvent::new("create", to)),
+                }
+            }
+            (RenameMode::Both, cookie, [from, to, ..]) => {
+                if let Some(cookie) = cookie {
+                    self.pending.remove(&cookie);
+                    if let Some(index) = self.paired.iter().position(|c| *c == cookie) {
+                        self.paired.remove(index);
+                        return events;
+                    }
+                }
+                events.push(FileSystemEvent::renamed(from, to));
+            }
+            (_, _, [path, ..]) => {
+                events.push(FileSystemEvent::new("rename", path));
+            }
+            _ => {}
+        }
+
+        events
+    }
+
+    /// Report rename-from halves that waited too long as deletes
+    fn expire(&mut self, now: Instant) -> Vec<FileSystemEvent> {
+        let mut expired = Vec::new();
+
+        self.pending.retain(|_, (path, since)| {
+            if now.duration_since(*since) >= RENAME_PAIR_TIMEOUT {
+                expired.push(FileSystemEvent::new("delete", path));
+                false
+            } else {
+                true
+            }
+        });
+
+        // Only the most recent pairings can still see a combined event
+        if self.paired.len() > 64 {
+            self.paired.drain(..self.paired.len() - 64);
+        }
+
+        expired
+    }
+}
+
+/// Destination for watcher events
+///
+/// Implemented by `AppHandle` to forward events to the frontend; tests
+/// substitute a channel so events can be observed directly.
+pub trait WatchEventSink: Clone + Send + 'static {
+    fn send_event<S: Serialize + Clone>(&self, event: &str, payload: S);
+}
+
+impl WatchEventSink for AppHandle {
+    fn send_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Err(e) = self.emit(event, payload) {
+            eprintln!("Failed to emit {} event: {}", event, e);
+        }
+    }
+}
+
+/// Include/exclude glob filters applied to paths relative to the watched directory
+#[derive(Default)]
+pub struct WatchFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl WatchFilter {
+    /// Build a filter from optional include and exclude glob lists
+    pub fn new(include_globs: Option<Vec<String>>, exclude_globs: Option<Vec<String>>) -> Result<Self, String> {
+        Ok(Self {
+            include: build_glob_set(include_globs)?,
+            exclude: build_glob_set(exclude_globs)?,
+        })
+    }
+
+    /// Check whether events for a path (relative to the watched directory) should be emitted
+    ///
+    /// Excludes win over includes; with no include globs everything not
+    /// excluded is allowed.
+    pub fn allows(&self, relative_path: &Path) -> bool {
+        if let Some(ref exclude) = self.exclude {
+            if exclude.is_match(relative_path) {
+                return false;
+            }
+        }
+
+        match self.include {
+            Some(ref include) => include.is_match(relative_path),
+            None => true,
+        }
+    }
+}
+
+fn build_glob_set(globs: Option<Vec<String>>) -> Result<Option<GlobSet>, String> {
+    let globs = match globs {
+        Some(globs) if !globs.is_empty() => globs,
+        _ => return Ok(None),
+    };
+
+    let mut builder = GlobSetBuilder::new();
+    for glob in &globs {
+        let glob = Glob::new(glob).map_err(|e| format!("Invalid glob '{}': {}", glob, e))?;
+        builder.add(glob);
+    }
+
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| format!("Failed to build glob filter: {}", e))
+}
+
+/// Payload of the `file-watcher-error` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatcherErrorEvent {
+    /// The watched directory the error belongs to
+    pub path: String,
+    pub error: String,
+}
+
+/// Sends a single watcher's events to its sink
+#[derive(Clone)]
+struct EventEmitter<S: WatchEventSink> {
+    sink: S,
+    root: PathBuf,
+    filter: Arc<WatchFilter>,
+}
+
+impl<S: WatchEventSink> EventEmitter<S> {
+    /// Emit a file system event to the frontend, skipping hidden and filtered-out files
+    fn emit(&self, fs_event: FileSystemEvent) {
+        let path = Path::new(&fs_event.path);
+
+        // Skip hidden files/folders (starting with .)
+        if let Some(name) = path.file_name() {
+            if name.to_string_lossy().starts_with('.') {
+                return;
+            }
+        }
+
+        if !self.filter.allows(path.strip_prefix(&self.root).unwrap_or(path)) {
+            return;
+        }
+
+        self.sink.send_event("file-system-change", fs_event);
+    }
+
+    /// Tell the frontend that watching this directory failed
+    fn emit_error(&self, error: String) {
+        eprintln!("File watcher error for {:?}: {}", self.root, error);
+
+        self.sink.send_event("file-watcher-error", WatcherErrorEvent {
+            path: self.root.to_string_lossy().to_string(),
+            error,
+        });
+    }
+}
+
+/// Per-watcher state driven by the notify callback
+struct WatchHandler<S: WatchEventSink> {
+    emitter: EventEmitter<S>,
+    // Modify events arrive in bursts while a file is being written, so
+    // only forward one per path per throttle window
+    modify_throttle: ModifyThrottle,
+    // Shared with the timer threads that flush unmatched rename halves
+    renames: Arc<Mutex<RenameTracker>>,
+}
+
+impl<S: WatchEventSink> WatchHandler<S> {
+    fn new(sink: S, root: PathBuf, filter: WatchFilter) -> Self {
+        Self {
+            emitter: EventEmitter {
+                sink,
+                root,
+                filter: Arc::new(filter),
+            },
+            modify_throttle: ModifyThrottle::new(MODIFY_THROTTLE_WINDOW),
+            renames: Arc::new(Mutex::new(RenameTracker::default())),
+        }
+    }
+
+    fn handle(&mut self, res: Result<Event, notify::Error>) {
+        let event = match res {
+            Ok(event) => event,
+            Err(e) => {
+                self.emitter.emit_error(e.to_string());
+                return;
+            }
+        };
+
+        // The OS drops the watch when the watched directory itself goes away
+        if matches!(event.kind, EventKind::Remove(_)) && event.paths.iter().any(|p| p == &self.emitter.root) {
+            self.emitter.emit_error("Watched directory was removed".to_string());
+            return;
+        }
+
+        // Renames are reported in two halves that need pairing
+        if let EventKind::Modify(ModifyKind::Name(mode)) = event.kind {
+            self.handle_rename(mode, &event);
+            return;
+        }
+
+        // Filter out events we don't care about
+        let event_type = match classify_event(&event.kind) {
+            Some(event_type) => event_type,
+            None => return,
+        };
+
+        // Get the first path from the event
+        if let Some(path) = event.paths.first() {
+            if event_type == "modify" && !self.modify_throttle.should_emit(path, Instant::now()) {
+                return;
+            }
+
+            self.emitter.emit(FileSystemEvent::new(event_type, path));
+        }
+    }
+
+    fn handle_rename(&mut self, mode: RenameMode, event: &Event) {
+        let events = match self.renames.lock() {
+            Ok(mut tracker) => tracker.handle(mode, event.tracker(), &event.paths, Instant::now()),
+            Err(_) => return,
+        };
+        for fs_event in events {
+            self.emitter.emit(fs_event);
+        }
+
+        // Report the rename-from as a delete if its other half never shows up
+        if mode == RenameMode::From {
+            let renames = Arc::clone(&self.renames);
+            let emitter = self.emitter.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(RENAME_PAIR_TIMEOUT);
+                let expired = match renames.lock() {
+                    Ok(mut tracker) => tracker.expire(Instant::now()),
+                    Err(_) => return,
+                };
+                for fs_event in expired {
+                    emitter.emit(fs_event);
+                }
+            });
+        }
+    }
+}
+
+/// Payload of the `theme-changed` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeChangedEvent {
+    /// Theme file name without `.json`, as passed to `load_theme`
+    pub name: String,
+}
+
+/// The theme file whose contents an event changed, if any
+///
+/// Editors that save by renaming a temporary file over the original
+/// produce a rename-to rather than a modify, so those count too.
+fn changed_theme_file(event: &Event) -> Option<&Path> {
+    let changed = matches!(
+        event.kind,
+        EventKind::Create(_)
+            | EventKind::Modify(ModifyKind::Data(_))
+            | EventKind::Modify(ModifyKind::Any)
+            | EventKind::Modify(ModifyKind::Name(RenameMode::To | RenameMode::Both))
+    );
+    // The last path is the destination of a combined rename
+    let path = event.paths.last().filter(|_| changed)?;
+    (path.extension()? == "json").then_some(path.as_path())
+}
+
+/// Turns changes under `.loom/themes` into `theme-changed` events
+struct ThemeWatchHandler<S: WatchEventSink> {
+    sink: S,
+    modify_throttle: ModifyThrottle,
+}
+
+impl<S: WatchEventSink> ThemeWatchHandler<S> {
+    fn handle(&mut self, res: Result<Event, notify::Error>) {
+        let event = match res {
+            Ok(event) => event,
+            Err(e) => {
+                eprintln!("Theme watcher error: {}", e);
+                return;
+            }
+        };
+
+        let Some(path) = changed_theme_file(&event) else {
+            return;
+        };
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            return;
+        };
+        if self.modify_throttle.should_emit(path, Instant::now()) {
+            self.sink.send_event("theme-changed", ThemeChangedEvent { name: name.to_string() });
+        }
+    }
 }
 
 pub struct FileWatcherState {
-    watcher: Option<notify::RecommendedWatcher>,
+    /// Active watchers keyed by the watched directory path
+    watchers: HashMap<String, notify::RecommendedWatcher>,
+    /// Watcher on the open workspace's `.loom/themes`, if any
+    theme_watcher: Option<notify::RecommendedWatcher>,
 }
 
 impl FileWatcherState {
     pub fn new() -> Self {
-        Self { watcher: None }
+        Self { watchers: HashMap::new(), theme_watcher: None }
+    }
+
+    /// Watch a workspace's theme files, replacing the watch on any previous workspace
+    pub fn watch_themes<P: AsRef<Path>, S: WatchEventSink>(&mut self, folder_path: P, sink: S) -> Result<(), String> {
+        let themes_dir = folder_path.as_ref().join(".loom").join("themes");
+        if !themes_dir.is_dir() {
+            return Err("Themes directory does not exist".to_string());
+        }
+
+        let mut handler = ThemeWatchHandler {
+            sink,
+            modify_throttle: ModifyThrottle::new(MODIFY_THROTTLE_WINDOW),
+        };
+        let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+            handler.handle(res);
+        }).map_err(|e| format!("Failed to create theme watcher: {}", e))?;
+
+        watcher.watch(&themes_dir, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch themes directory: {}", e))?;
+
+        self.theme_watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// Stop watching theme files, e.g. when the workspace is closed
+    pub fn stop_watching_themes(&mut self) {
+        self.theme_watcher = None;
     }
 
-    pub fn start_watching<P: AsRef<Path>>(
+    /// Start watching a directory in addition to any already being watched
+    pub fn start_watching<P: AsRef<Path>, S: WatchEventSink>(
         &mut self,
         path: P,
-        app_handle: AppHandle,
+        include_globs: Option<Vec<String>>,
+        exclude_globs: Option<Vec<String>>,
+        sink: S,
     ) -> Result<(), String> {
-        // Stop any existing watcher first
-        self.stop_watching();
+        let filter = WatchFilter::new(include_globs, exclude_globs)?;
+        self.add_watch(path, filter, sink)
+    }
 
+    /// Add a watcher for a directory, replacing any existing watcher for the same path
+    pub fn add_watch<P: AsRef<Path>, S: WatchEventSink>(
+        &mut self,
+        path: P,
+        filter: WatchFilter,
+        sink: S,
+    ) -> Result<(), String> {
         let path = path.as_ref().to_path_buf();
 
         // Verify the path exists and is a directory
@@ -37,73 +484,46 @@ impl FileWatcherState {
             return Err("Path is not a directory".to_string());
         }
 
+        // Each watcher carries its own filter, relative to its own root
+        let mut handler = WatchHandler::new(sink, path.clone(), filter);
+
         // Create a new watcher
-        let watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
-            match res {
-                Ok(event) => {
-                    // Filter out events we don't care about
-                    match event.kind {
-                        notify::EventKind::Create(_) |
-                        notify::EventKind::Remove(_) |
-                        notify::EventKind::Modify(notify::event::ModifyKind::Name(_)) => {
-                            // Determine the event type
-                            let event_type = match event.kind {
-                                notify::EventKind::Create(_) => "create",
-                                notify::EventKind::Remove(_) => "delete",
-                                notify::EventKind::Modify(notify::event::ModifyKind::Name(_)) => "rename",
-                                _ => "modify",
-                            };
-
-                            // Get the first path from the event
-                            if let Some(path) = event.paths.first() {
-                                let path_str = path.to_string_lossy().to_string();
-
-                                // Skip hidden files/folders (starting with .)
-                                if let Some(name) = path.file_name() {
-                                    let name_str = name.to_string_lossy();
-                                    if name_str.starts_with('.') {
-                                        return;
-                                    }
-                                }
-
-                                let fs_event = FileSystemEvent {
-                                    event_type: event_type.to_string(),
-                                    path: path_str,
-                                };
-
-                                // Emit the event to the frontend
-                                if let Err(e) = app_handle.emit("file-system-change", fs_event) {
-                                    eprintln!("Failed to emit file system event: {}", e);
-                                }
-                            }
-                        }
-                        _ => {
-                            // Ignore other event types (metadata changes, content modifications, etc.)
-                        }
-                    }
-                }
-                Err(e) => eprintln!("File watcher error: {:?}", e),
-            }
+        let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+            handler.handle(res);
         }).map_err(|e| format!("Failed to create watcher: {}", e))?;
 
-        // Store the watcher before calling watch
-        self.watcher = Some(watcher);
+        watcher.watch(&path, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch directory: {}", e))?;
 
-        // Now watch the directory
-        if let Some(ref mut watcher) = self.watcher {
-            watcher.watch(&path, RecursiveMode::Recursive)
-                .map_err(|e| format!("Failed to watch directory: {}", e))?;
-        }
+        // Any previous watcher for this path is dropped (and stopped) here
+        self.watchers.insert(path.to_string_lossy().to_string(), watcher);
 
         println!("Started watching directory: {:?}", path);
         Ok(())
     }
 
+    /// Stop watching a single directory, leaving the others active
+    pub fn remove_watch<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let key = path.as_ref().to_string_lossy().to_string();
+
+        match self.watchers.remove(&key) {
+            Some(watcher) => {
+                // The watcher will automatically stop when dropped
+                drop(watcher);
+                println!("Stopped watching directory: {:?}", key);
+                Ok(())
+            }
+            None => Err("Directory is not being watched".to_string()),
+        }
+    }
+
+    /// Stop watching all directories, including the theme files
     pub fn stop_watching(&mut self) {
-        if let Some(watcher) = self.watcher.take() {
-            // The watcher will automatically stop when dropped
-            drop(watcher);
-            println!("Stopped watching directory");
+        self.theme_watcher = None;
+        if !self.watchers.is_empty() {
+            // The watchers will automatically stop when dropped
+            self.watchers.clear();
+            println!("Stopped watching all directories");
         }
     }
 }
@@ -114,3 +534,234 @@ pub type FileWatcherStateHandle = Arc<Mutex<FileWatcherState>>;
 pub fn create_watcher_state() -> FileWatcherStateHandle {
     Arc::new(Mutex::new(FileWatcherState::new()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, MetadataKind, RemoveKind, RenameMode};
+    use std::fs;
+    use std::sync::mpsc;
+
+    /// Test sink that forwards every event over a channel
+    #[derive(Clone)]
+    struct ChannelSink(mpsc::Sender<(String, serde_json::Value)>);
+
+    impl WatchEventSink for ChannelSink {
+        fn send_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+            let _ = self.0.send((event.to_string(), serde_json::to_value(payload).unwrap()));
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("loom-watch-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    /// Wait for an event whose path lies under `dir`
+    fn saw_event_under(rx: &mpsc::Receiver<(String, serde_json::Value)>, dir: &Path) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match rx.recv_timeout(remaining) {
+                Ok((_, payload)) => {
+                    if payload["path"].as_str().is_some_and(|p| Path::new(p).starts_with(dir)) {
+                        return true;
+                    }
+                }
+                Err(_) => return false,
+            }
+        }
+        false
+    }
+
+    #[test]
+    fn test_classify_content_change_as_modify() {
+        let kind = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        assert_eq!(classify_event(&kind), Some("modify"));
+    }
+
+    #[test]
+    fn test_classify_other_events() {
+        assert_eq!(classify_event(&EventKind::Create(CreateKind::File)), Some("create"));
+        assert_eq!(classify_event(&EventKind::Remove(RemoveKind::File)), Some("delete"));
+        assert_eq!(
+            classify_event(&EventKind::Modify(ModifyKind::Name(RenameMode::Any))),
+            Some("rename")
+        );
+        assert_eq!(
+            classify_event(&EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_modify_throttle_per_path() {
+        let mut throttle = ModifyThrottle::new(Duration::from_millis(500));
+        let start = Instant::now();
+        let a = Path::new("/notes/a.md");
+        let b = Path::new("/notes/b.md");
+
+        assert!(throttle.should_emit(a, start));
+        assert!(!throttle.should_emit(a, start + Duration::from_millis(100)));
+        assert!(throttle.should_emit(b, start + Duration::from_millis(100)));
+        assert!(throttle.should_emit(a, start + Duration::from_millis(600)));
+    }
+
+    #[test]
+    fn test_rename_pair_matched_by_cookie() {
+        let mut tracker = RenameTracker::default();
+        let now = Instant::now();
+        let old = PathBuf::from("/notes/old.md");
+        let new = PathBuf::from("/notes/new.md");
+
+        let events = tracker.handle(RenameMode::From, Some(7), std::slice::from_ref(&old), now);
+        assert!(events.is_empty());
+
+        let events = tracker.handle(RenameMode::To, Some(7), std::slice::from_ref(&new), now);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "rename");
+        assert_eq!(events[0].old_path.as_deref(), Some("/notes/old.md"));
+        assert_eq!(events[0].new_path.as_deref(), Some("/notes/new.md"));
+        assert_eq!(events[0].path, "/notes/new.md");
+
+        // The combined event for an already paired cookie is not reported again
+        let events = tracker.handle(RenameMode::Both, Some(7), &[old, new], now);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_rename_orphaned_halves() {
+        let mut tracker = RenameTracker::default();
+        let now = Instant::now();
+
+        // A rename-to with no matching rename-from is a file moved into the tree
+        let events = tracker.handle(RenameMode::To, Some(3), &[PathBuf::from("/notes/in.md")], now);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "create");
+        assert_eq!(events[0].path, "/notes/in.md");
+
+        // A rename-from that is never matched is a file moved out of the tree
+        let events = tracker.handle(RenameMode::From, Some(4), &[PathBuf::from("/notes/out.md")], now);
+        assert!(events.is_empty());
+        assert!(tracker.expire(now + Duration::from_millis(100)).is_empty());
+
+        let events = tracker.expire(now + RENAME_PAIR_TIMEOUT);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, "delete");
+        assert_eq!(events[0].path, "/notes/out.md");
+    }
+
+    #[test]
+    fn test_watch_multiple_directories() {
+        let first = temp_dir("first");
+        let second = temp_dir("second");
+        let (tx, rx) = mpsc::channel();
+        let mut state = FileWatcherState::new();
+
+        state.add_watch(&first, WatchFilter::default(), ChannelSink(tx.clone())).unwrap();
+        state.add_watch(&second, WatchFilter::default(), ChannelSink(tx)).unwrap();
+
+        fs::write(first.join("a.md"), "a").unwrap();
+        assert!(saw_event_under(&rx, &first));
+        fs::write(second.join("b.md"), "b").unwrap();
+        assert!(saw_event_under(&rx, &second));
+
+        // Removing one watch leaves the other active
+        state.remove_watch(&first).unwrap();
+        while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+
+        fs::write(first.join("c.md"), "c").unwrap();
+        fs::write(second.join("d.md"), "d").unwrap();
+        assert!(saw_event_under(&rx, &second));
+        while let Ok((_, payload)) = rx.recv_timeout(Duration::from_millis(200)) {
+            assert!(!Path::new(payload["path"].as_str().unwrap()).starts_with(&first));
+        }
+
+        assert!(state.remove_watch(&first).is_err());
+        state.stop_watching();
+        fs::remove_dir_all(&first).unwrap();
+        fs::remove_dir_all(&second).unwrap();
+    }
+
+    #[test]
+    fn test_theme_file_change_emits_theme_changed() {
+        let dir = temp_dir("themes");
+        let custom_dir = dir.join(".loom").join("themes").join("custom");
+        fs::create_dir_all(&custom_dir).unwrap();
+        fs::write(custom_dir.join("ocean.json"), "{}").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut state = FileWatcherState::new();
+
+        assert!(state.watch_themes(dir.join("missing"), ChannelSink(tx.clone())).is_err());
+        state.watch_themes(&dir, ChannelSink(tx)).unwrap();
+        fs::write(custom_dir.join("notes.txt"), "ignored").unwrap();
+        fs::write(custom_dir.join("ocean.json"), r#"{ "name": "Ocean" }"#).unwrap();
+        let received = rx.recv_timeout(Duration::from_secs(2));
+
+        state.stop_watching_themes();
+        while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+        fs::write(custom_dir.join("ocean.json"), "{}").unwrap();
+        let after_stop = rx.recv_timeout(Duration::from_millis(300));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let (event, payload) = received.unwrap();
+        assert_eq!(event, "theme-changed");
+        assert_eq!(payload["name"], "ocean");
+        assert!(after_stop.is_err());
+    }
+
+    #[test]
+    fn test_watch_filter_exclude_wins() {
+        let filter = WatchFilter::new(
+            Some(vec!["**/*.md".to_string()]),
+            Some(vec!["build/**".to_string()]),
+        ).unwrap();
+
+        assert!(filter.allows(Path::new("notes/today.md")));
+        assert!(!filter.allows(Path::new("build/out.md")));
+        assert!(!filter.allows(Path::new("notes/image.png")));
+
+        assert!(WatchFilter::default().allows(Path::new("anything/at/all.txt")));
+        assert!(WatchFilter::new(Some(vec!["[".to_string()]), None).is_err());
+    }
+
+    #[test]
+    fn test_excluded_path_suppressed() {
+        let dir = temp_dir("filtered");
+        fs::create_dir_all(dir.join("build")).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let mut state = FileWatcherState::new();
+
+        state.start_watching(&dir, None, Some(vec!["build/**".to_string()]), ChannelSink(tx)).unwrap();
+
+        fs::write(dir.join("build").join("out.md"), "out").unwrap();
+        assert!(!saw_event_under(&rx, &dir.join("build")));
+
+        fs::write(dir.join("kept.md"), "kept").unwrap();
+        assert!(saw_event_under(&rx, &dir));
+
+        state.stop_watching();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_injected_error_emits_watcher_error() {
+        let (tx, rx) = mpsc::channel();
+        let root = PathBuf::from("/notes");
+        let mut handler = WatchHandler::new(ChannelSink(tx), root.clone(), WatchFilter::default());
+
+        handler.handle(Err(notify::Error::generic("inotify queue overflow")));
+
+        let (event, payload) = rx.try_recv().unwrap();
+        assert_eq!(event, "file-watcher-error");
+        assert_eq!(payload["path"], "/notes");
+        assert!(payload["error"].as_str().unwrap().contains("inotify queue overflow"));
+
+        // Removal of the watched directory itself is reported as an error too
+        handler.handle(Ok(Event::new(EventKind::Remove(RemoveKind::Folder)).add_path(root)));
+        let (event, payload) = rx.try_recv().unwrap();
+        assert_eq!(event, "file-watcher-error");
+        assert_eq!(payload["path"], "/notes");
+    }
+}
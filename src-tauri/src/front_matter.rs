@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontMatterRow {
+    pub file_path: String,
+    pub fields: HashMap<String, Option<String>>,
+}
+
+/// Parse the `---` delimited front matter block at the top of a document
+///
+/// Only flat `key: value` pairs are supported. Surrounding quotes are
+/// stripped from values, and lines that aren't key/value pairs are ignored.
+/// Returns None if the document doesn't start with a front matter block.
+pub fn parse_front_matter(content: &str) -> Option<HashMap<String, String>> {
+    let mut lines = content.lines();
+
+    if lines.next()?.trim_end() != "---" {
+        return None;
+    }
+
+    let mut values = HashMap::new();
+
+    for line in lines {
+        if line.trim_end() == "---" {
+            return Some(values);
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim();
+            if key.is_empty() || key.starts_with('#') {
+                continue;
+            }
+
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(value);
+
+            values.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    // No closing delimiter - not front matter
+    None
+}
+
+/// Collect the requested front matter fields from every markdown file in a directory
+#[tauri::command]
+pub fn collect_front_matter(dir_path: String, fields: Vec<String>) -> Result<Vec<FrontMatterRow>, String> {
+    let path = Path::new(&dir_path);
+    if !path.exists() || !path.is_dir() {
+        return Err("Directory does not exist".to_string());
+    }
+
+    let mut rows = Vec::new();
+
+    for entry in WalkDir::new(path)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let entry_path = entry.path();
+
+        // Only look at .md files
+        if !entry_path.is_file() {
+            continue;
+        }
+
+        if entry_path.extension().and_then(|s| s.to_str()) != Some("md") {
+            continue;
+        }
+
+        let content = match fs::read_to_string(entry_path) {
+            Ok(c) => c,
+            Err(_) => continue, // Skip files we can't read
+        };
+
+        let front_matter = parse_front_matter(&content).unwrap_or_default();
+
+        let row_fields = fields
+            .iter()
+            .map(|field| (field.clone(), front_matter.get(field).cloned()))
+            .collect();
+
+        rows.push(FrontMatterRow {
+            file_path: entry_path.to_string_lossy().to_string(),
+            fields: row_fields,
+        });
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_front_matter() {
+        let content = "---\ntitle: \"Hello\"\ntags: rust\n---\n# Body";
+        let values = parse_front_matter(content).unwrap();
+        assert_eq!(values.get("title").map(String::as_str), Some("Hello"));
+        assert_eq!(values.get("tags").map(String::as_str), Some("rust"));
+
+        assert!(parse_front_matter("# No front matter").is_none());
+    }
+
+    #[test]
+    fn test_collect_front_matter() {
+        let dir = std::env::temp_dir().join(format!("loom-front-matter-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), "---\ntitle: First\nstatus: draft\n---\nBody").unwrap();
+        fs::write(dir.join("b.md"), "---\ntitle: Second\nauthor: Ann\n---\nBody").unwrap();
+        fs::write(dir.join("c.md"), "No front matter here").unwrap();
+
+        let fields = vec!["title".to_string(), "status".to_string()];
+        let rows = collect_front_matter(dir.to_string_lossy().to_string(), fields).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].file_path.ends_with("a.md"));
+        assert_eq!(rows[0].fields["title"], Some("First".to_string()));
+        assert_eq!(rows[0].fields["status"], Some("draft".to_string()));
+        assert_eq!(rows[1].fields["title"], Some("Second".to_string()));
+        assert_eq!(rows[1].fields["status"], None);
+        assert_eq!(rows[2].fields["title"], None);
+        assert_eq!(rows[2].fields["status"], None);
+    }
+}
@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+// Score for each matched character, plus bonuses for where it matched
+const MATCH_SCORE: i64 = 1;
+const CONSECUTIVE_BONUS: i64 = 5;
+const WORD_START_BONUS: i64 = 10;
+// Subtracted per unmatched character of the file name, so tighter names rank higher
+const LENGTH_PENALTY: i64 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub name: String,
+    pub score: i64,
+    /// Character indices in `name` that matched the query, for highlighting
+    pub positions: Vec<usize>,
+}
+
+/// Score `name` against `query` as a case-insensitive subsequence
+///
+/// Returns the score and the matched character positions, or `None` if the
+/// query's characters don't all appear in order. Characters are matched
+/// greedily, preferring the start of a word (after a separator or a
+/// lower-to-upper case change) when one is ahead.
+fn fuzzy_score(name: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+    let chars: Vec<char> = name.chars().collect();
+    let lower: Vec<char> = chars.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let is_word_start = |i: usize| {
+        i == 0 || matches!(chars[i - 1], ' ' | '-' | '_' | '.') || (chars[i - 1].is_lowercase() && chars[i].is_uppercase())
+    };
+
+    let mut positions = Vec::new();
+    let mut score = 0;
+    let mut next = 0;
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let candidates: Vec<usize> = (next..chars.len()).filter(|&i| lower[i] == q).collect();
+        let first = *candidates.first()?;
+        // Keep a consecutive run going, otherwise jump to a word start if there is one
+        let chosen = if positions.last().is_some_and(|&last| last + 1 == first) {
+            first
+        } else {
+            candidates.iter().copied().find(|&i| is_word_start(i)).unwrap_or(first)
+        };
+
+        score += MATCH_SCORE;
+        if positions.last().is_some_and(|&last| last + 1 == chosen) {
+            score += CONSECUTIVE_BONUS;
+        }
+        if is_word_start(chosen) {
+            score += WORD_START_BONUS;
+        }
+        positions.push(chosen);
+        next = chosen + 1;
+    }
+
+    score -= (chars.len() - positions.len()) as i64 * LENGTH_PENALTY;
+    Some((score, positions))
+}
+
+/// Find files whose names fuzzily match `query`, best matches first
+///
+/// Only markdown files are considered unless `all_files` is set; hidden
+/// files and folders (including `.loom`) are skipped. With an empty query
+/// the most recently modified files are returned instead.
+#[tauri::command]
+pub fn fuzzy_find_files(dir_path: String, query: String, limit: usize, all_files: Option<bool>) -> Vec<FuzzyMatch> {
+    let root = Path::new(&dir_path);
+    let all_files = all_files.unwrap_or(false);
+    let query = query.trim();
+
+    let files = WalkDir::new(root)
+        .follow_links(false)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            all_files
+                || entry
+                    .path()
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("md"))
+        });
+
+    if query.is_empty() {
+        let mut recent: Vec<(SystemTime, FuzzyMatch)> = files
+            .map(|entry| {
+                let modified = entry.metadata().ok().and_then(|m| m.modified().ok()).unwrap_or(SystemTime::UNIX_EPOCH);
+                let file = FuzzyMatch {
+                    path: entry.path().to_string_lossy().to_string(),
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    score: 0,
+                    positions: Vec::new(),
+                };
+                (modified, file)
+            })
+            .collect();
+        recent.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.path.cmp(&b.1.path)));
+        return recent.into_iter().take(limit).map(|(_, file)| file).collect();
+    }
+
+    let mut matches: Vec<FuzzyMatch> = files
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let (score, positions) = fuzzy_score(&name, query)?;
+            Some(FuzzyMatch {
+                path: entry.path().to_string_lossy().to_string(),
+                name,
+                score,
+                positions,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    matches.truncate(limit);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_fuzzy_score_positions() {
+        let (_, positions) = fuzzy_score("meeting-notes.md", "mn").unwrap();
+        assert_eq!(positions, vec![0, 8]);
+
+        let (_, positions) = fuzzy_score("ProjectPlan.md", "pp").unwrap();
+        assert_eq!(positions, vec![0, 7]);
+
+        assert!(fuzzy_score("notes.md", "xyz").is_none());
+        assert!(fuzzy_score("notes.md", "sn").is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_find_orders_by_score() {
+        let dir = std::env::temp_dir().join(format!("loom-fuzzy-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::create_dir_all(dir.join(".loom")).unwrap();
+        fs::write(dir.join("meeting-notes.md"), "").unwrap();
+        fs::write(dir.join("sub").join("mountain.md"), "").unwrap();
+        fs::write(dir.join("main.md"), "").unwrap();
+        fs::write(dir.join("image.png"), "").unwrap();
+        fs::write(dir.join(".loom").join("main.md"), "").unwrap();
+
+        let names = |matches: Vec<FuzzyMatch>| matches.into_iter().map(|m| m.name).collect::<Vec<_>>();
+        let root = dir.to_string_lossy().to_string();
+        let ranked = names(fuzzy_find_files(root.clone(), "main".to_string(), 10, None));
+        let limited = names(fuzzy_find_files(root.clone(), "mn".to_string(), 1, None));
+        let everything = names(fuzzy_find_files(root.clone(), "i".to_string(), 10, Some(true)));
+        let recent = fuzzy_find_files(root, String::new(), 10, None);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(ranked, ["main.md", "mountain.md"]);
+        assert_eq!(limited, ["meeting-notes.md"]);
+        assert!(everything.contains(&"image.png".to_string()));
+        assert_eq!(recent.len(), 3);
+        assert!(recent.iter().all(|m| m.positions.is_empty()));
+    }
+}
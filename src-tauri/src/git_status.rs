@@ -0,0 +1,220 @@
+use git2::{DiffFormat, DiffOptions, Repository, Status, StatusOptions};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitChange {
+    /// Changed in the index or working tree (including renames and type changes)
+    Modified,
+    /// New in the index
+    Added,
+    Deleted,
+    /// New in the working tree and not staged
+    Untracked,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitFileStatus {
+    /// Relative to the repository root, with `/` separators
+    pub path: String,
+    pub status: GitChange,
+}
+
+fn classify(status: Status) -> Option<GitChange> {
+    if status.is_index_new() {
+        Some(GitChange::Added)
+    } else if status.is_wt_new() {
+        Some(GitChange::Untracked)
+    } else if status.is_index_deleted() || status.is_wt_deleted() {
+        Some(GitChange::Deleted)
+    } else if status.intersects(
+        Status::INDEX_MODIFIED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE
+            | Status::WT_MODIFIED
+            | Status::WT_RENAMED
+            | Status::WT_TYPECHANGE,
+    ) {
+        Some(GitChange::Modified)
+    } else {
+        None
+    }
+}
+
+/// List the changed files of the git repository containing `dir_path`
+///
+/// Ignored files aren't listed. A folder outside any repository (or one
+/// git can't read) has no changes rather than an error.
+#[tauri::command]
+pub fn git_status(dir_path: String) -> Vec<GitFileStatus> {
+    let Ok(repo) = Repository::discover(&dir_path) else {
+        return Vec::new();
+    };
+
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+
+    let Ok(statuses) = repo.statuses(Some(&mut options)) else {
+        return Vec::new();
+    };
+
+    let mut files: Vec<GitFileStatus> = statuses
+        .iter()
+        .filter_map(|entry| {
+            let status = classify(entry.status())?;
+            let path = entry.path()?.to_string();
+            Some(GitFileStatus { path, status })
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    files
+}
+
+/// Unified diff of a file against HEAD, or "" if it hasn't changed
+///
+/// `file_path` may be absolute or relative to the repository root. Staged
+/// and unstaged changes are both included; an untracked file is diffed
+/// against an empty file.
+#[tauri::command]
+pub fn git_file_diff(dir_path: String, file_path: String) -> Result<String, String> {
+    let repo = Repository::discover(&dir_path)
+        .map_err(|e| format!("Not inside a git repository: {}", e.message()))?;
+    let workdir = repo.workdir()
+        .ok_or_else(|| "Repository has no working directory".to_string())?;
+
+    let file = Path::new(&file_path);
+    let relative = if file.is_absolute() {
+        // Canonicalize both sides so symlinked temp or home folders still line up
+        let workdir = workdir.canonicalize()
+            .map_err(|e| format!("Failed to resolve repository path: {}", e))?;
+        let file = file.parent()
+            .and_then(|parent| parent.canonicalize().ok())
+            .and_then(|parent| file.file_name().map(|name| parent.join(name)))
+            .ok_or_else(|| format!("Failed to resolve file path: {}", file_path))?;
+        file.strip_prefix(&workdir)
+            .map(Path::to_path_buf)
+            .map_err(|_| "File is not inside the repository".to_string())?
+    } else {
+        file.to_path_buf()
+    };
+
+    // An unborn HEAD (no commits yet) diffs against an empty tree
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree().map_err(|e| format!("Failed to read HEAD: {}", e.message()))?),
+        Err(_) => None,
+    };
+
+    let mut options = DiffOptions::new();
+    options
+        .pathspec(relative.to_string_lossy().replace('\\', "/"))
+        .disable_pathspec_match(true)
+        .include_untracked(true)
+        .show_untracked_content(true)
+        .recurse_untracked_dirs(true);
+
+    let diff = repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut options))
+        .map_err(|e| format!("Failed to diff file: {}", e.message()))?;
+
+    let mut patch = String::new();
+    diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin());
+        }
+        patch.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })
+    .map_err(|e| format!("Failed to format diff: {}", e.message()))?;
+
+    Ok(patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use std::fs;
+
+    #[test]
+    fn test_status_of_changed_files() {
+        let dir = std::env::temp_dir().join(format!("loom-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        let repo = Repository::init(&dir).unwrap();
+
+        fs::write(dir.join("notes").join("tracked.md"), "one").unwrap();
+        fs::write(dir.join("removed.md"), "gone soon").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("notes/tracked.md")).unwrap();
+        index.add_path(Path::new("removed.md")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[]).unwrap();
+
+        fs::write(dir.join("notes").join("tracked.md"), "two").unwrap();
+        fs::remove_file(dir.join("removed.md")).unwrap();
+        fs::write(dir.join("staged.md"), "new").unwrap();
+        index.add_path(Path::new("staged.md")).unwrap();
+        index.write().unwrap();
+        fs::write(dir.join("untracked.md"), "loose").unwrap();
+
+        let statuses = git_status(dir.join("notes").to_string_lossy().to_string());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let summary: Vec<(&str, GitChange)> = statuses.iter().map(|s| (s.path.as_str(), s.status.clone())).collect();
+        assert_eq!(
+            summary,
+            [
+                ("notes/tracked.md", GitChange::Modified),
+                ("removed.md", GitChange::Deleted),
+                ("staged.md", GitChange::Added),
+                ("untracked.md", GitChange::Untracked),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_non_repo_has_no_changes() {
+        assert!(git_status("/definitely/not/a/repo".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_file_diff_against_head() {
+        let dir = std::env::temp_dir().join(format!("loom-git-diff-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let repo = Repository::init(&dir).unwrap();
+
+        fs::write(dir.join("note.md"), "# Title\nold line\n").unwrap();
+        fs::write(dir.join("same.md"), "unchanged\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("note.md")).unwrap();
+        index.add_path(Path::new("same.md")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[]).unwrap();
+
+        fs::write(dir.join("note.md"), "# Title\nnew line\n").unwrap();
+        fs::write(dir.join("fresh.md"), "brand new\n").unwrap();
+
+        let root = dir.to_string_lossy().to_string();
+        let modified = git_file_diff(root.clone(), dir.join("note.md").to_string_lossy().to_string()).unwrap();
+        let untracked = git_file_diff(root.clone(), "fresh.md".to_string()).unwrap();
+        let unchanged = git_file_diff(root, "same.md".to_string()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(modified.contains("--- a/note.md\n+++ b/note.md\n"));
+        assert!(modified.contains(" # Title\n-old line\n+new line\n"));
+        assert!(!modified.contains("fresh.md"));
+        assert!(untracked.contains("+++ b/fresh.md\n"));
+        assert!(untracked.contains("+brand new\n"));
+        assert_eq!(unchanged, "");
+    }
+}
@@ -0,0 +1,325 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use std::io::Cursor;
+use std::path::Path;
+
+/// JPEG quality used when re-encoding without an explicit quality
+const DEFAULT_JPEG_QUALITY: u8 = 85;
+
+/// Optional constraints applied to an image before it's saved
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ImageOptions {
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    /// JPEG quality (1-100)
+    pub quality: Option<u8>,
+    /// Drop EXIF and other non-pixel metadata
+    pub strip_metadata: bool,
+}
+
+impl ImageOptions {
+    fn is_empty(&self) -> bool {
+        self.max_width.is_none() && self.max_height.is_none() && self.quality.is_none() && !self.strip_metadata
+    }
+}
+
+/// Pick a file extension for image data by looking at its magic bytes
+///
+/// Returns None if the format isn't recognized.
+pub fn detect_image_extension(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0x89, b'P', b'N', b'G']) {
+        Some("png")
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        Some("jpg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("webp")
+    } else {
+        None
+    }
+}
+
+/// Downscale and re-encode image data according to the given options
+///
+/// The image is scaled down (never up) to fit within the bounds while
+/// keeping its aspect ratio. JPEGs are re-encoded at the requested quality
+/// and PNGs are written back as PNG; other formats such as GIF and WebP
+/// can't be written back in the same form and are returned untouched.
+/// Re-encoding drops metadata, so any EXIF orientation is applied to the
+/// pixels first. When only metadata stripping is asked for, JPEGs have
+/// their EXIF segments removed without recompressing unless they need
+/// rotating. Data is returned untouched when no options are set.
+pub fn process_image(data: Vec<u8>, options: &ImageOptions) -> Result<Vec<u8>, String> {
+    if options.is_empty() {
+        return Ok(data);
+    }
+
+    let format = image::guess_format(&data)
+        .map_err(|e| format!("Failed to detect image format: {}", e))?;
+    if !matches!(format, ImageFormat::Jpeg | ImageFormat::Png) {
+        return Ok(data);
+    }
+
+    let mut decoder = ImageReader::with_format(Cursor::new(&data), format)
+        .into_decoder()
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    let orientation = decoder.orientation()
+        .map_err(|e| format!("Failed to read image orientation: {}", e))?;
+    let mut img = DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("Failed to decode image: {}", e))?;
+    img.apply_orientation(orientation);
+
+    let max_width = options.max_width.unwrap_or(u32::MAX);
+    let max_height = options.max_height.unwrap_or(u32::MAX);
+    let needs_resize = img.width() > max_width || img.height() > max_height;
+    let recompress = format == ImageFormat::Jpeg && options.quality.is_some();
+    // Stripping the EXIF of a rotated JPEG means baking in the rotation
+    let rotate = options.strip_metadata && orientation != Orientation::NoTransforms;
+
+    if !needs_resize && !recompress && !rotate {
+        return match (options.strip_metadata, format) {
+            (true, ImageFormat::Jpeg) => strip_jpeg_metadata(&data),
+            (true, _) => encode_image(&img, format, None),
+            (false, _) => Ok(data),
+        };
+    }
+
+    if needs_resize {
+        img = img.resize(max_width, max_height, FilterType::Lanczos3);
+    }
+
+    encode_image(&img, format, options.quality)
+}
+
+fn encode_image(img: &DynamicImage, format: ImageFormat, quality: Option<u8>) -> Result<Vec<u8>, String> {
+    let mut output = Cursor::new(Vec::new());
+
+    if format == ImageFormat::Jpeg {
+        let quality = quality.unwrap_or(DEFAULT_JPEG_QUALITY).clamp(1, 100);
+        let encoder = JpegEncoder::new_with_quality(&mut output, quality);
+        img.to_rgb8()
+            .write_with_encoder(encoder)
+            .map_err(|e| format!("Failed to encode image: {}", e))?;
+    } else {
+        img.write_to(&mut output, ImageFormat::Png)
+            .map_err(|e| format!("Failed to encode image: {}", e))?;
+    }
+
+    Ok(output.into_inner())
+}
+
+/// Remove APP1 segments (EXIF and XMP) from JPEG data
+fn strip_jpeg_metadata(data: &[u8]) -> Result<Vec<u8>, String> {
+    const SOI: u8 = 0xD8;
+    const SOS: u8 = 0xDA;
+    const APP1: u8 = 0xE1;
+    let invalid = || "Invalid JPEG data".to_string();
+
+    if !data.starts_with(&[0xFF, SOI]) {
+        return Err(invalid());
+    }
+
+    let mut output = vec![0xFF, SOI];
+    let mut pos = 2;
+
+    while pos < data.len() {
+        if data[pos] != 0xFF {
+            return Err(invalid());
+        }
+
+        // Skip fill bytes before the marker
+        let mut marker_pos = pos + 1;
+        while data.get(marker_pos) == Some(&0xFF) {
+            marker_pos += 1;
+        }
+        let marker = *data.get(marker_pos).ok_or_else(invalid)?;
+
+        // Markers without a length
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            output.extend_from_slice(&[0xFF, marker]);
+            pos = marker_pos + 1;
+            continue;
+        }
+
+        let length_bytes = data.get(marker_pos + 1..marker_pos + 3).ok_or_else(invalid)?;
+        let length = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        let segment_end = marker_pos + 1 + length;
+        if length < 2 || segment_end > data.len() {
+            return Err(invalid());
+        }
+
+        // Everything from the start of scan on is image data
+        if marker == SOS {
+            output.push(0xFF);
+            output.extend_from_slice(&data[marker_pos..]);
+            return Ok(output);
+        }
+
+        if marker != APP1 {
+            output.push(0xFF);
+            output.extend_from_slice(&data[marker_pos..segment_end]);
+        }
+        pos = segment_end;
+    }
+
+    Ok(output)
+}
+
+/// Path of a saved image relative to a document or folder, for embedding
+/// in markdown
+///
+/// If `base` is a file, the path is relative to its directory. Falls back
+/// to the absolute path when the image isn't under the base.
+pub fn relative_image_path(base: &Path, image_path: &Path) -> String {
+    let base_dir = if base.is_file() {
+        base.parent().unwrap_or(base)
+    } else {
+        base
+    };
+
+    match image_path.strip_prefix(base_dir) {
+        Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+        Err(_) => image_path.to_string_lossy().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::GenericImageView;
+
+    #[test]
+    fn test_detect_image_extension() {
+        assert_eq!(detect_image_extension(&[0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A]), Some("png"));
+        assert_eq!(detect_image_extension(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("jpg"));
+        assert_eq!(detect_image_extension(b"GIF89a\x01\x00"), Some("gif"));
+        assert_eq!(detect_image_extension(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some("webp"));
+        assert_eq!(detect_image_extension(b"RIFF\x24\x00\x00\x00WAVE"), None);
+        assert_eq!(detect_image_extension(b"not an image"), None);
+    }
+
+    #[test]
+    fn test_relative_image_path_nested() {
+        let dir = std::env::temp_dir().join(format!("loom-images-nested-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        let document = dir.join("note.md");
+        std::fs::write(&document, "# Note").unwrap();
+        let image = dir.join("assets").join("pasted.png");
+
+        let from_document = relative_image_path(&document, &image);
+        let from_folder = relative_image_path(&dir, &image);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(from_document, "assets/pasted.png");
+        assert_eq!(from_folder, "assets/pasted.png");
+    }
+
+    #[test]
+    fn test_relative_image_path_sibling_falls_back() {
+        let root = std::env::temp_dir().join(format!("loom-images-sibling-{}", std::process::id()));
+        let image = root.join("assets").join("pasted.png");
+
+        let result = relative_image_path(&root.join("docs"), &image);
+
+        assert_eq!(result, image.to_string_lossy());
+    }
+
+    fn encode_fixture(width: u32, height: u32, format: ImageFormat) -> Vec<u8> {
+        let img = DynamicImage::ImageRgb8(image::RgbImage::from_pixel(width, height, image::Rgb([200, 80, 40])));
+        let mut output = Cursor::new(Vec::new());
+        img.write_to(&mut output, format).unwrap();
+        output.into_inner()
+    }
+
+    #[test]
+    fn test_process_image_downscales_to_bounds() {
+        let options = ImageOptions {
+            max_width: Some(100),
+            max_height: Some(100),
+            quality: None,
+            strip_metadata: false,
+        };
+
+        let png = process_image(encode_fixture(400, 200, ImageFormat::Png), &options).unwrap();
+        let jpeg = process_image(encode_fixture(150, 300, ImageFormat::Jpeg), &options).unwrap();
+
+        assert_eq!(detect_image_extension(&png), Some("png"));
+        assert_eq!(image::load_from_memory(&png).unwrap().dimensions(), (100, 50));
+        assert_eq!(detect_image_extension(&jpeg), Some("jpg"));
+        assert_eq!(image::load_from_memory(&jpeg).unwrap().dimensions(), (50, 100));
+    }
+
+    #[test]
+    fn test_process_image_without_options_is_unchanged() {
+        let data = encode_fixture(400, 200, ImageFormat::Png);
+        let small = ImageOptions {
+            max_width: Some(1000),
+            ..ImageOptions::default()
+        };
+
+        assert_eq!(process_image(data.clone(), &ImageOptions::default()).unwrap(), data);
+        assert_eq!(process_image(data.clone(), &small).unwrap(), data);
+    }
+
+    // Insert an APP1 EXIF segment right after the SOI marker
+    fn with_exif(jpeg: &[u8], exif_payload: &[u8]) -> Vec<u8> {
+        let length = (exif_payload.len() + 2) as u16;
+        let mut output = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        output.extend_from_slice(&length.to_be_bytes());
+        output.extend_from_slice(exif_payload);
+        output.extend_from_slice(&jpeg[2..]);
+        output
+    }
+
+    #[test]
+    fn test_strip_metadata_removes_exif() {
+        let jpeg = encode_fixture(20, 10, ImageFormat::Jpeg);
+        let with_exif = with_exif(&jpeg, b"Exif\0\0MM\0*\0\0\0\x08\0\0");
+
+        let options = ImageOptions {
+            strip_metadata: true,
+            ..ImageOptions::default()
+        };
+        let stripped = process_image(with_exif, &options).unwrap();
+
+        assert!(!stripped.windows(4).any(|w| w == b"Exif"));
+        assert_eq!(image::load_from_memory(&stripped).unwrap().dimensions(), (20, 10));
+    }
+
+    #[test]
+    fn test_strip_metadata_applies_exif_orientation() {
+        let jpeg = encode_fixture(20, 10, ImageFormat::Jpeg);
+        // One IFD entry: Orientation (0x0112) = 6, rotate 90 degrees clockwise
+        let rotated = with_exif(
+            &jpeg,
+            b"Exif\0\0MM\0*\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0",
+        );
+
+        let options = ImageOptions {
+            strip_metadata: true,
+            ..ImageOptions::default()
+        };
+        let stripped = process_image(rotated, &options).unwrap();
+
+        assert!(!stripped.windows(4).any(|w| w == b"Exif"));
+        assert_eq!(detect_image_extension(&stripped), Some("jpg"));
+        assert_eq!(image::load_from_memory(&stripped).unwrap().dimensions(), (10, 20));
+    }
+
+    #[test]
+    fn test_process_image_leaves_gif_and_webp_alone() {
+        let gif = encode_fixture(400, 200, ImageFormat::Gif);
+        let webp = encode_fixture(400, 200, ImageFormat::WebP);
+        let options = ImageOptions {
+            max_width: Some(100),
+            strip_metadata: true,
+            ..ImageOptions::default()
+        };
+
+        assert_eq!(process_image(gif.clone(), &options).unwrap(), gif);
+        assert_eq!(process_image(webp.clone(), &options).unwrap(), webp);
+    }
+}
@@ -2,434 +2,460 @@ mod markdown;
 mod config;
 mod file_watcher;
 mod search;
-
-use markdown::{render_markdown_line, LineRenderResult, RenderRequest};
+mod front_matter;
+mod color;
+mod images;
+mod export;
+mod pdf_export;
+mod docx_export;
+mod links;
+mod fuzzy;
+mod file_ops;
+mod text_file;
+mod templates;
+mod snippets;
+mod session;
+mod plugins;
+mod git_status;
+mod workspace;
+mod autosave;
+
+use markdown::{render_markdown_line, render_markdown_line_with_highlights, render_markdown_lines,
+               create_render_cache, BlockContext, DocStats, FoldRegion, InlineFormat, LineRenderResult,
+               LintWarning, RenderCacheHandle, RenderRequest, TagCount, WindowRenderResult};
 use config::{ThemeConfig, AppConfig, initialize_loom_dir, load_app_config, save_app_config,
              load_theme, list_themes, import_theme, export_theme, get_loom_dir,
-             get_default_dark_theme_config, get_default_light_theme_config};
+             EffectiveConfig, compute_effective_config, ThemeLoadResult, load_current_theme,
+             ContrastWarning, KeybindConflict, RenderOptionsHandle, create_render_options_state};
 use file_watcher::{FileWatcherStateHandle, create_watcher_state};
-use search::{search_in_content, replace_in_content, search_in_directory};
+use search::{search_in_content, replace_in_content, preview_replace, search_in_directory,
+             search_in_directory_streaming, cancel_search, create_search_cancel_state, find_files_by_tag,
+             validate_search_regex, count_matches_in_directory};
+use front_matter::collect_front_matter;
+use export::{export_document_html, render_theme_preview};
+use pdf_export::export_document_pdf;
+use docx_export::export_document_docx;
+use links::{build_backlink_index, check_links, resolve_wikilink};
+use fuzzy::fuzzy_find_files;
+use git_status::{git_file_diff, git_status};
+use file_ops::{FileEntry, FileOp, FileOpResult};
+use text_file::{DecodedFile, LineEnding, TextFormat};
+use session::SessionState;
+use plugins::PluginManifest;
+use autosave::{AutosaveStateHandle, create_autosave_state};
+use workspace::{WorkspaceRootHandle, create_workspace_root_state, ensure_in_workspace, set_workspace_root};
+use images::{detect_image_extension, process_image, relative_image_path, ImageOptions};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{Emitter, State};
 use base64::{engine::general_purpose, Engine as _};
 
-// File tree structures
+// Result of saving a pasted image
 #[derive(Debug, Serialize, Deserialize)]
-struct FileEntry {
-    name: String,
+struct SavedImage {
     path: String,
-    is_dir: bool,
-    children: Option<Vec<FileEntry>>,
+    // Relative to the requested base, or the absolute path if it isn't under it
+    relative_path: Option<String>,
 }
 
-// Markdown rendering commands
+// Markdown rendering commands, with render settings taken from the folder's config
 #[tauri::command]
-fn render_markdown(request: RenderRequest) -> LineRenderResult {
+fn render_markdown(
+    folder_path: Option<String>,
+    mut request: RenderRequest,
+    render_options: State<RenderOptionsHandle>,
+) -> LineRenderResult {
+    request.apply_options(&config::cached_render_options(&render_options, folder_path));
     render_markdown_line(request)
 }
 
-// Batch rendering for multiple lines (parallelized for performance)
+// Render a line with search matches highlighted, as [start, end) character columns
 #[tauri::command]
-fn render_markdown_batch(requests: Vec<RenderRequest>) -> Vec<LineRenderResult> {
-    use rayon::prelude::*;
-
-    // Use parallel iterator for large batches (>50 lines)
-    if requests.len() > 50 {
-        requests.into_par_iter().map(render_markdown_line).collect()
-    } else {
-        // For small batches, sequential is faster (no thread overhead)
-        requests.into_iter().map(render_markdown_line).collect()
-    }
+fn render_markdown_highlighted(
+    folder_path: Option<String>,
+    mut request: RenderRequest,
+    highlights: Vec<(usize, usize)>,
+    render_options: State<RenderOptionsHandle>,
+) -> LineRenderResult {
+    request.apply_options(&config::cached_render_options(&render_options, folder_path));
+    render_markdown_line_with_highlights(request, highlights)
 }
 
-// Read directory contents recursively
+// Batch rendering for multiple lines (parallelized and cached for performance)
 #[tauri::command]
-fn read_directory(path: String) -> Result<Vec<FileEntry>, String> {
-    let dir_path = PathBuf::from(&path);
+fn render_markdown_batch(
+    folder_path: Option<String>,
+    mut requests: Vec<RenderRequest>,
+    render_cache: State<RenderCacheHandle>,
+    render_options: State<RenderOptionsHandle>,
+) -> Vec<LineRenderResult> {
+    let options = config::cached_render_options(&render_options, folder_path);
+    for request in &mut requests {
+        request.apply_options(&options);
+    }
+    render_markdown_lines(requests, &render_cache)
+}
 
-    if !dir_path.exists() {
-        return Err("Directory does not exist".to_string());
+// Drop all cached line renders and render settings, e.g. after a theme or settings change
+#[tauri::command]
+fn clear_render_cache(render_cache: State<RenderCacheHandle>, render_options: State<RenderOptionsHandle>) {
+    if let Ok(mut cache) = render_cache.lock() {
+        cache.clear();
     }
-
-    if !dir_path.is_dir() {
-        return Err("Path is not a directory".to_string());
+    if let Ok(mut options) = render_options.lock() {
+        options.take();
     }
-
-    read_dir_recursive(&dir_path)
 }
 
-fn read_dir_recursive(dir_path: &PathBuf) -> Result<Vec<FileEntry>, String> {
-    let mut entries = Vec::new();
-
-    let dir_entries = fs::read_dir(dir_path)
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
-
-    for entry in dir_entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
+// Render only a range of lines, with block context from the whole document
+#[tauri::command]
+fn render_markdown_range(
+    folder_path: Option<String>,
+    all_lines: Vec<String>,
+    start: usize,
+    end: usize,
+    is_editing: bool,
+    render_options: State<RenderOptionsHandle>,
+) -> Vec<LineRenderResult> {
+    let options = config::cached_render_options(&render_options, folder_path);
+    markdown::render_markdown_range(all_lines, start, end, is_editing, &options)
+}
 
-        // Skip hidden files and directories (starting with .)
-        if name.starts_with('.') {
-            continue;
-        }
+// Render a window of lines from the block context entering it, without the rest of the document;
+// the context after the window is returned for rendering the next one
+#[tauri::command]
+fn render_markdown_window(
+    folder_path: Option<String>,
+    window_lines: Vec<String>,
+    window_start: usize,
+    full_line_count: usize,
+    block_state_at_start: BlockContext,
+    is_editing: Option<bool>,
+    render_options: State<RenderOptionsHandle>,
+) -> WindowRenderResult {
+    markdown::render_markdown_window(
+        window_lines,
+        window_start,
+        full_line_count,
+        block_state_at_start,
+        is_editing.unwrap_or(false),
+        &config::cached_render_options(&render_options, folder_path),
+    )
+}
 
-        let is_dir = path.is_dir();
-        let path_str = path.to_string_lossy().to_string();
-
-        let children = if is_dir {
-            // Don't recursively read children here - we'll do it on demand in the UI
-            Some(Vec::new())
-        } else {
-            None
-        };
-
-        entries.push(FileEntry {
-            name,
-            path: path_str,
-            is_dir,
-            children,
-        });
-    }
+// Count the #tags used in a document
+#[tauri::command]
+fn extract_tags(content: String) -> Vec<TagCount> {
+    markdown::extract_tags(&content)
+}
 
-    // Sort: directories first, then files, alphabetically within each group
-    entries.sort_by(|a, b| {
-        match (a.is_dir, b.is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-        }
-    });
+// Find the code blocks, math blocks and heading sections the editor can fold
+#[tauri::command]
+fn get_foldable_regions(content: String) -> Vec<FoldRegion> {
+    markdown::get_foldable_regions(&content)
+}
 
-    Ok(entries)
+// Check a document for common markdown style issues
+#[tauri::command]
+fn lint_markdown(content: String) -> Vec<LintWarning> {
+    markdown::lint_markdown(&content)
 }
 
-// Read file contents from a path
+// Clean up spacing and list markers in a document
 #[tauri::command]
-fn read_file_from_path(path: String) -> Result<String, String> {
-    fs::read_to_string(&path)
-        .map_err(|e| format!("Failed to read file: {}", e))
+fn format_markdown(content: String) -> String {
+    markdown::format_markdown(&content)
 }
 
-// Create a new file
+// Rewrap paragraph text to a column width
 #[tauri::command]
-fn create_file(path: String) -> Result<(), String> {
-    let file_path = PathBuf::from(&path);
+fn reflow_paragraphs(content: String, width: usize) -> String {
+    markdown::reflow_paragraphs(&content, width)
+}
 
-    // Check if parent directory exists
-    if let Some(parent) = file_path.parent() {
-        if !parent.exists() {
-            return Err(format!("Parent directory does not exist: {:?}", parent));
-        }
-    }
+// Renumber ordered list items so each list counts up
+#[tauri::command]
+fn renumber_ordered_lists(content: String) -> String {
+    markdown::renumber_ordered_lists(&content)
+}
 
-    // Check if file already exists
-    if file_path.exists() {
-        return Err("File already exists".to_string());
-    }
+// Shift headings so the document's shallowest heading becomes H1
+#[tauri::command]
+fn normalize_heading_levels(content: String) -> String {
+    markdown::normalize_heading_levels(&content)
+}
 
-    // Create the file
-    fs::write(&file_path, "").map_err(|e| format!("Failed to create file: {}", e))?;
+// Sort the list block containing a line alphabetically
+#[tauri::command]
+fn sort_list_block(content: String, start_line: usize, ascending: bool) -> String {
+    markdown::sort_list_block(&content, start_line, ascending)
+}
 
-    // Verify the file was created
-    if !file_path.exists() {
-        return Err("File was not created successfully".to_string());
-    }
+// Indent (positive delta) or outdent lines start..=end by whole levels of tab_size columns
+#[tauri::command]
+fn reindent_lines(content: String, start: usize, end: usize, delta: i32, tab_size: u8) -> String {
+    markdown::reindent_lines(&content, start, end, delta, tab_size)
+}
 
-    println!("File created successfully at: {:?}", file_path);
-    Ok(())
+// Marker for the next list item when Enter is pressed on a list line (None ends the list)
+#[tauri::command]
+fn next_list_prefix(current_line: String) -> Option<String> {
+    markdown::next_list_prefix(&current_line)
 }
 
-// Create a new folder
+// Align the columns of the table containing a line
 #[tauri::command]
-fn create_folder(path: String) -> Result<(), String> {
-    let dir_path = PathBuf::from(&path);
+fn format_table(content: String, line: usize) -> String {
+    markdown::format_table(&content, line)
+}
 
-    // Check if parent directory exists
-    if let Some(parent) = dir_path.parent() {
-        if !parent.exists() {
-            return Err("Parent directory does not exist".to_string());
-        }
-    }
+// Wrap a selection in bold/italic/code/strikethrough/highlight markers, or unwrap it
+#[tauri::command]
+fn toggle_inline_format(text: String, format: InlineFormat) -> String {
+    markdown::toggle_inline_format(&text, format)
+}
 
-    // Check if folder already exists
-    if dir_path.exists() {
-        return Err("Folder already exists".to_string());
-    }
+// Convert pasted HTML to markdown
+#[tauri::command]
+fn html_to_markdown(html: String) -> String {
+    markdown::html_to_markdown(&html)
+}
 
-    // Create the folder
-    fs::create_dir(&dir_path)
-        .map_err(|e| format!("Failed to create folder: {}", e))
+// Strip scripts, frames and other unsafe markup from an HTML fragment
+#[tauri::command]
+fn sanitize_html(html: String) -> String {
+    markdown::sanitize_html(&html)
 }
 
-// Delete a file
+// Plain text of a document with all markdown removed (code blocks kept unless turned off)
 #[tauri::command]
-fn delete_file(path: String) -> Result<(), String> {
-    let file_path = PathBuf::from(&path);
+fn strip_markdown(content: String, keep_code_blocks: Option<bool>) -> String {
+    markdown::strip_markdown(&content, keep_code_blocks.unwrap_or(true))
+}
 
-    // Check if file exists
-    if !file_path.exists() {
-        return Err("File does not exist".to_string());
-    }
+// Word, character and line counts for a document
+#[tauri::command]
+fn document_stats(content: String) -> DocStats {
+    markdown::document_stats(&content)
+}
 
-    // Check if it's actually a file
-    if !file_path.is_file() {
-        return Err("Path is not a file".to_string());
-    }
+// The same counts for the selected part of a document
+#[tauri::command]
+fn selection_stats(text: String) -> DocStats {
+    markdown::selection_stats(&text)
+}
 
-    // Delete the file
-    fs::remove_file(&file_path)
-        .map_err(|e| format!("Failed to delete file: {}", e))?;
+// Render LaTeX to MathML without the frontend's KaTeX
+#[tauri::command]
+fn render_math(latex: String, display: bool) -> Result<String, String> {
+    markdown::render_math(&latex, display)
+}
 
-    println!("File deleted successfully: {:?}", file_path);
-    Ok(())
+// Read directory contents recursively
+#[tauri::command]
+fn read_directory(
+    path: String,
+    respect_gitignore: Option<bool>,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<Vec<FileEntry>, String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    file_ops::read_directory(path, respect_gitignore.unwrap_or(false))
 }
 
-// Delete a folder (recursively)
+// Read file contents from a path
 #[tauri::command]
-fn delete_folder(path: String) -> Result<(), String> {
-    let dir_path = PathBuf::from(&path);
+fn read_file_from_path(path: String, workspace_root: State<WorkspaceRootHandle>) -> Result<String, String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    file_ops::read_file_from_path(path)
+}
 
-    // Check if folder exists
-    if !dir_path.exists() {
-        return Err("Folder does not exist".to_string());
-    }
+// Read a file along with the encoding it was saved in
+#[tauri::command]
+fn read_file_with_encoding(path: String, workspace_root: State<WorkspaceRootHandle>) -> Result<DecodedFile, String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    text_file::read_text_file(Path::new(&path))
+}
 
-    // Check if it's actually a directory
-    if !dir_path.is_dir() {
-        return Err("Path is not a folder".to_string());
-    }
+// Read a file with its line endings converted to \n, and the ending it had
+#[tauri::command]
+fn read_file_normalized(path: String, workspace_root: State<WorkspaceRootHandle>) -> Result<(String, LineEnding), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    text_file::read_normalized(Path::new(&path))
+}
 
-    // Delete the folder recursively
-    fs::remove_dir_all(&dir_path)
-        .map_err(|e| format!("Failed to delete folder: {}", e))?;
+// Save a file in the encoding and line ending it was read with
+#[tauri::command]
+fn save_file_with_encoding(
+    path: String,
+    content: String,
+    encoding: String,
+    bom: bool,
+    line_ending: Option<LineEnding>,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<(), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    text_file::write_text_file(Path::new(&path), &content, &encoding, bom, line_ending)
+}
 
-    println!("Folder deleted successfully: {:?}", dir_path);
+// Save a file once typing pauses for debounce_ms, emitting autosave-done after the write;
+// without a format the file keeps the encoding and line ending it already has
+#[tauri::command]
+fn schedule_autosave(
+    path: String,
+    content: String,
+    format: Option<TextFormat>,
+    debounce_ms: u64,
+    app_handle: tauri::AppHandle,
+    workspace_root: State<WorkspaceRootHandle>,
+    autosave_state: State<AutosaveStateHandle>,
+) -> Result<(), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    autosave::schedule_autosave(
+        &autosave_state,
+        PathBuf::from(path),
+        content,
+        format,
+        Duration::from_millis(debounce_ms),
+        move |done| {
+            if let Err(e) = app_handle.emit("autosave-done", done) {
+                eprintln!("Failed to emit autosave-done event: {}", e);
+            }
+        },
+    );
     Ok(())
 }
 
-// Count contents of a folder (files and subfolders)
+// Create a new file, optionally pre-filled with content
 #[tauri::command]
-fn count_folder_contents(path: String) -> Result<(usize, usize), String> {
-    let dir_path = PathBuf::from(&path);
-
-    if !dir_path.exists() {
-        return Err("Folder does not exist".to_string());
-    }
-
-    if !dir_path.is_dir() {
-        return Err("Path is not a folder".to_string());
-    }
-
-    let mut file_count = 0;
-    let mut folder_count = 0;
+fn create_file(
+    path: String,
+    content: Option<String>,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<(), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    file_ops::create_file(path, content)
+}
 
-    let entries = fs::read_dir(&dir_path)
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
+// Create a new folder
+#[tauri::command]
+fn create_folder(path: String, workspace_root: State<WorkspaceRootHandle>) -> Result<(), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    file_ops::create_folder(path)
+}
 
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
+// Delete a file, to the trash unless the config turns that off
+#[tauri::command]
+fn delete_file(folder_path: Option<String>, path: String, workspace_root: State<WorkspaceRootHandle>) -> Result<(), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    file_ops::delete_file(path, config::load_use_trash(folder_path))
+}
 
-        // Skip hidden files and directories
-        if name.starts_with('.') {
-            continue;
-        }
+// Delete a folder (recursively), to the trash unless the config turns that off
+#[tauri::command]
+fn delete_folder(folder_path: Option<String>, path: String, workspace_root: State<WorkspaceRootHandle>) -> Result<(), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    file_ops::delete_folder(path, config::load_use_trash(folder_path))
+}
 
-        if path.is_dir() {
-            folder_count += 1;
-        } else {
-            file_count += 1;
-        }
-    }
+// Count contents of a folder (files and subfolders)
+#[tauri::command]
+fn count_folder_contents(path: String, workspace_root: State<WorkspaceRootHandle>) -> Result<(usize, usize), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    file_ops::count_folder_contents(path)
+}
 
-    Ok((file_count, folder_count))
+// Count all files and subfolders below a folder, for the delete confirmation
+#[tauri::command]
+fn count_folder_contents_recursive(
+    path: String,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<(usize, usize), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    file_ops::count_folder_contents_recursive(path)
 }
 
 // Rename a file or folder
 #[tauri::command]
-fn rename_path(old_path: String, new_name: String) -> Result<String, String> {
-    let old_path_buf = PathBuf::from(&old_path);
-
-    // Check if path exists
-    if !old_path_buf.exists() {
-        return Err("Path does not exist".to_string());
-    }
-
-    // Get parent directory
-    let parent = old_path_buf.parent()
-        .ok_or_else(|| "Cannot get parent directory".to_string())?;
-
-    // Create new path
-    let new_path_buf = parent.join(&new_name);
-
-    // Check if new path already exists
-    if new_path_buf.exists() {
-        return Err("A file or folder with that name already exists".to_string());
-    }
-
-    // Rename
-    fs::rename(&old_path_buf, &new_path_buf)
-        .map_err(|e| format!("Failed to rename: {}", e))?;
-
-    let new_path = new_path_buf.to_string_lossy().to_string();
-    println!("Renamed {:?} to {:?}", old_path, new_path);
-    Ok(new_path)
+fn rename_path(old_path: String, new_name: String, workspace_root: State<WorkspaceRootHandle>) -> Result<String, String> {
+    ensure_in_workspace(&workspace_root, &old_path)?;
+    file_ops::rename_path(old_path, new_name)
 }
 
 // Move a file or folder to a different directory
 #[tauri::command]
-fn move_path(source_path: String, dest_dir_path: String) -> Result<String, String> {
-    let source_path_buf = PathBuf::from(&source_path);
-    let dest_dir_buf = PathBuf::from(&dest_dir_path);
-
-    // Check if source exists
-    if !source_path_buf.exists() {
-        return Err("Source path does not exist".to_string());
-    }
-
-    // Check if destination directory exists
-    if !dest_dir_buf.exists() {
-        return Err("Destination directory does not exist".to_string());
-    }
-
-    // Check if destination is a directory
-    if !dest_dir_buf.is_dir() {
-        return Err("Destination must be a directory".to_string());
-    }
-
-    // Get the file/folder name
-    let name = source_path_buf.file_name()
-        .ok_or_else(|| "Cannot get source name".to_string())?;
-
-    // Create new path in destination directory
-    let new_path_buf = dest_dir_buf.join(name);
-
-    // Check if destination already has a file/folder with the same name
-    if new_path_buf.exists() {
-        return Err("A file or folder with that name already exists in the destination".to_string());
-    }
-
-    // Move (rename) the file/folder
-    fs::rename(&source_path_buf, &new_path_buf)
-        .map_err(|e| format!("Failed to move: {}", e))?;
-
-    let new_path = new_path_buf.to_string_lossy().to_string();
-    println!("Moved {:?} to {:?}", source_path, new_path);
-    Ok(new_path)
+fn move_path(
+    source_path: String,
+    dest_dir_path: String,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<String, String> {
+    ensure_in_workspace(&workspace_root, &source_path)?;
+    ensure_in_workspace(&workspace_root, &dest_dir_path)?;
+    file_ops::move_path(source_path, dest_dir_path)
 }
 
 // Copy a file or folder to a different directory
 #[tauri::command]
-fn copy_path(source_path: String, dest_dir_path: String) -> Result<String, String> {
-    let source_path_buf = PathBuf::from(&source_path);
-    let dest_dir_buf = PathBuf::from(&dest_dir_path);
-
-    // Check if source exists
-    if !source_path_buf.exists() {
-        return Err("Source path does not exist".to_string());
-    }
-
-    // Check if destination directory exists
-    if !dest_dir_buf.exists() {
-        return Err("Destination directory does not exist".to_string());
-    }
-
-    // Check if destination is a directory
-    if !dest_dir_buf.is_dir() {
-        return Err("Destination must be a directory".to_string());
-    }
-
-    // Get the file/folder name
-    let name = source_path_buf.file_name()
-        .ok_or_else(|| "Cannot get source name".to_string())?;
-
-    // Create new path in destination directory
-    let new_path_buf = dest_dir_buf.join(name);
-
-    // Check if destination already has a file/folder with the same name
-    if new_path_buf.exists() {
-        return Err("A file or folder with that name already exists in the destination".to_string());
-    }
-
-    // Copy the file or folder
-    if source_path_buf.is_file() {
-        // Copy file
-        fs::copy(&source_path_buf, &new_path_buf)
-            .map_err(|e| format!("Failed to copy file: {}", e))?;
-    } else if source_path_buf.is_dir() {
-        // Copy directory recursively with depth limit
-        const MAX_DEPTH: usize = 100;
-        copy_dir_recursive(&source_path_buf, &new_path_buf, 0, MAX_DEPTH)?;
-    } else {
-        return Err("Source is neither a file nor a directory".to_string());
-    }
-
-    let new_path = new_path_buf.to_string_lossy().to_string();
-    println!("Copied {:?} to {:?}", source_path, new_path);
-    Ok(new_path)
+fn copy_path(
+    source_path: String,
+    dest_dir_path: String,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<String, String> {
+    ensure_in_workspace(&workspace_root, &source_path)?;
+    ensure_in_workspace(&workspace_root, &dest_dir_path)?;
+    file_ops::copy_path(source_path, dest_dir_path)
 }
 
-// Helper function to copy directory recursively with depth limit
-fn copy_dir_recursive(src: &PathBuf, dest: &PathBuf, depth: usize, max_depth: usize) -> Result<(), String> {
-    // Check depth limit to prevent stack overflow
-    if depth >= max_depth {
-        return Err(format!("Directory depth exceeds maximum limit of {}", max_depth));
-    }
-
-    // Create destination directory
-    fs::create_dir(dest)
-        .map_err(|e| format!("Failed to create directory: {}", e))?;
-
-    // Read source directory
-    let entries = fs::read_dir(src)
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
-
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let path = entry.path();
-        let name = entry.file_name();
-        let name_str = name.to_string_lossy();
-        let dest_path = dest.join(&name);
-
-        // Skip hidden files and directories (starting with .)
-        if name_str.starts_with('.') {
-            println!("Skipping hidden file/directory: {:?}", name_str);
-            continue;
-        }
-
-        // Follow symlinks but don't copy the symlink itself
-        let metadata = fs::metadata(&path)
-            .map_err(|e| format!("Failed to read metadata: {}", e))?;
-
-        if metadata.is_file() {
-            fs::copy(&path, &dest_path)
-                .map_err(|e| format!("Failed to copy file: {}", e))?;
-        } else if metadata.is_dir() {
-            // Recursively copy subdirectory with incremented depth
-            copy_dir_recursive(&path, &dest_path, depth + 1, max_depth)?;
-        }
-        // Skip other types (symlinks, devices, etc.)
-    }
-
-    Ok(())
+// Run several file operations in order, e.g. for a multi-selection in the tree
+#[tauri::command]
+fn batch_file_operations(
+    folder_path: Option<String>,
+    ops: Vec<FileOp>,
+    stop_on_error: Option<bool>,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Vec<FileOpResult> {
+    let use_trash = config::load_use_trash(folder_path);
+    file_ops::batch_file_operations(ops, stop_on_error.unwrap_or(false), use_trash, |path| {
+        ensure_in_workspace(&workspace_root, path)
+    })
 }
 
 // Save image from base64 data to disk
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn save_image_from_clipboard(
     base64_data: String,
     save_dir: String,
     filename_prefix: Option<String>,
-) -> Result<String, String> {
+    relative_to: Option<String>,
+    max_width: Option<u32>,
+    max_height: Option<u32>,
+    quality: Option<u8>,
+    strip_metadata: Option<bool>,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<SavedImage, String> {
+    ensure_in_workspace(&workspace_root, &save_dir)?;
+
+    // The prefix becomes part of the file name, so it can't name another folder
+    let prefix = filename_prefix.unwrap_or_else(|| "image".to_string());
+    file_ops::validate_file_name(&prefix)?;
+
     // Decode base64 data
     let image_data = general_purpose::STANDARD
         .decode(&base64_data)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
 
+    // Downscale/compress or strip metadata if requested
+    let options = ImageOptions {
+        max_width,
+        max_height,
+        quality,
+        strip_metadata: strip_metadata.unwrap_or(false),
+    };
+    let image_data = process_image(image_data, &options)?;
+
     // Create save directory path
     let save_dir_path = PathBuf::from(&save_dir);
 
@@ -445,8 +471,13 @@ fn save_image_from_clipboard(
         .unwrap()
         .as_millis();
 
-    let prefix = filename_prefix.unwrap_or_else(|| "image".to_string());
-    let filename = format!("{}-{}.png", prefix, timestamp);
+    // Name the file after the actual image format
+    let extension = detect_image_extension(&image_data).unwrap_or_else(|| {
+        eprintln!("Unrecognized image format, saving as .png");
+        "png"
+    });
+
+    let filename = format!("{}-{}.{}", prefix, timestamp, extension);
     let file_path = save_dir_path.join(&filename);
 
     // Write image data to file
@@ -455,7 +486,13 @@ fn save_image_from_clipboard(
 
     let full_path = file_path.to_string_lossy().to_string();
     println!("Image saved successfully to: {:?}", full_path);
-    Ok(full_path)
+
+    let relative_path = relative_to.map(|base| relative_image_path(Path::new(&base), &file_path));
+
+    Ok(SavedImage {
+        path: full_path,
+        relative_path,
+    })
 }
 
 // Path utility commands
@@ -470,20 +507,34 @@ fn is_image_file(path: String) -> bool {
 
 // File watching commands
 
-/// Start watching a directory for file system changes
+/// Start watching a directory for file system changes, alongside any already watched
 #[tauri::command]
 fn start_watching_directory(
     path: String,
+    include_globs: Option<Vec<String>>,
+    exclude_globs: Option<Vec<String>>,
     app_handle: tauri::AppHandle,
     watcher_state: State<FileWatcherStateHandle>,
 ) -> Result<(), String> {
     let mut state = watcher_state.lock()
         .map_err(|e| format!("Failed to acquire watcher lock: {}", e))?;
 
-    state.start_watching(&path, app_handle)
+    state.start_watching(&path, include_globs, exclude_globs, app_handle)
 }
 
-/// Stop watching the current directory
+/// Stop watching a single directory, leaving any others active
+#[tauri::command]
+fn stop_watching_path(
+    path: String,
+    watcher_state: State<FileWatcherStateHandle>,
+) -> Result<(), String> {
+    let mut state = watcher_state.lock()
+        .map_err(|e| format!("Failed to acquire watcher lock: {}", e))?;
+
+    state.remove_watch(&path)
+}
+
+/// Stop watching all directories
 #[tauri::command]
 fn stop_watching_directory(
     watcher_state: State<FileWatcherStateHandle>,
@@ -495,6 +546,39 @@ fn stop_watching_directory(
     Ok(())
 }
 
+/// Watch the open folder's theme files, emitting `theme-changed` when one is edited
+///
+/// With no folder open, any previous theme watch is stopped instead.
+#[tauri::command]
+fn start_watching_themes(
+    folder_path: Option<String>,
+    app_handle: tauri::AppHandle,
+    watcher_state: State<FileWatcherStateHandle>,
+) -> Result<(), String> {
+    let mut state = watcher_state.lock()
+        .map_err(|e| format!("Failed to acquire watcher lock: {}", e))?;
+
+    match folder_path {
+        Some(folder) => state.watch_themes(folder, app_handle),
+        None => {
+            state.stop_watching_themes();
+            Ok(())
+        }
+    }
+}
+
+/// Stop watching theme files
+#[tauri::command]
+fn stop_watching_themes(
+    watcher_state: State<FileWatcherStateHandle>,
+) -> Result<(), String> {
+    let mut state = watcher_state.lock()
+        .map_err(|e| format!("Failed to acquire watcher lock: {}", e))?;
+
+    state.stop_watching_themes();
+    Ok(())
+}
+
 // Theme and config commands
 
 /// Initialize the .loom directory structure
@@ -535,53 +619,64 @@ fn get_config(folder_path: Option<String>) -> Result<AppConfig, String> {
     load_app_config(folder_path)
 }
 
-/// Update the full config (simplified version that can update any config field)
+/// Update some config fields, keeping the saved values of the rest
 #[tauri::command]
-fn update_config(folder_path: Option<String>, config: AppConfig) -> Result<(), String> {
-    save_app_config(folder_path, &config)
+fn update_config(folder_path: Option<String>, config: serde_json::Value) -> Result<(), String> {
+    config::update_app_config(folder_path, config)
 }
 
-/// Get the current theme configuration
+/// Report the merged settings in effect and where each value came from
 #[tauri::command]
-fn get_current_theme(folder_path: Option<String>) -> Result<ThemeConfig, String> {
-    match folder_path.clone() {
-        Some(_) => {
-            let config = load_app_config(folder_path.clone())?;
-            load_theme(folder_path, &config.current_theme)
-        }
-        None => {
-            // Return default dark theme when no folder is open
-            Ok(get_default_dark_theme_config())
-        }
-    }
+fn effective_config(folder_path: Option<String>, document_content: Option<String>) -> Result<EffectiveConfig, String> {
+    compute_effective_config(folder_path, document_content)
 }
 
-/// Get a theme by name
+/// Get the settings in effect for a file, with global config as the fallback
+#[tauri::command]
+fn get_file_settings(folder_path: Option<String>, file_path: String) -> Result<HashMap<String, serde_json::Value>, String> {
+    config::get_file_settings(folder_path, file_path)
+}
+
+/// Set a file's setting overrides (an empty map clears them)
+#[tauri::command]
+fn set_file_settings(folder_path: Option<String>, file_path: String, settings: HashMap<String, serde_json::Value>) -> Result<(), String> {
+    config::set_file_settings(folder_path, file_path, settings)
+}
+
+/// Check keybinds for chords bound to more than one action
+#[tauri::command]
+fn validate_keybinds(keybinds: HashMap<String, String>) -> Vec<KeybindConflict> {
+    config::validate_keybinds(&keybinds)
+}
+
+/// Restore the default keybinds, returning them
+#[tauri::command]
+fn reset_keybinds(folder_path: Option<String>) -> Result<HashMap<String, String>, String> {
+    config::reset_keybinds(folder_path)
+}
+
+/// Get the current theme configuration, falling back to the default theme with a warning
+#[tauri::command]
+fn get_current_theme(folder_path: Option<String>) -> Result<ThemeLoadResult, String> {
+    load_current_theme(folder_path)
+}
+
+/// Get a theme by name, from the app config directory when no folder is open
 #[tauri::command]
 fn get_theme(folder_path: Option<String>, theme_name: String) -> Result<ThemeConfig, String> {
-    match folder_path.clone() {
-        Some(_) => load_theme(folder_path, &theme_name),
-        None => {
-            // Return built-in themes when no folder is open
-            match theme_name.as_str() {
-                "dark" => Ok(get_default_dark_theme_config()),
-                "light" => Ok(get_default_light_theme_config()),
-                _ => Err("Theme not available without an open folder".to_string()),
-            }
-        }
-    }
+    load_theme(folder_path, &theme_name)
 }
 
 /// List all available themes
 #[tauri::command]
 fn get_available_themes(folder_path: Option<String>) -> Result<Vec<String>, String> {
-    match folder_path {
-        Some(_) => list_themes(folder_path),
-        None => {
-            // Return only built-in themes when no folder is open
-            Ok(vec!["dark".to_string(), "light".to_string()])
-        }
-    }
+    list_themes(folder_path)
+}
+
+/// List the required variables a theme is missing
+#[tauri::command]
+fn validate_theme(theme: ThemeConfig) -> Vec<String> {
+    config::validate_theme(&theme)
 }
 
 /// Import a theme from an external file
@@ -590,12 +685,132 @@ fn import_custom_theme(folder_path: Option<String>, source_path: String) -> Resu
     import_theme(folder_path, source_path)
 }
 
+/// Report foreground/background pairs in a theme with too little contrast
+#[tauri::command]
+fn check_theme_contrast(theme: ThemeConfig) -> Vec<ContrastWarning> {
+    config::check_theme_contrast(&theme)
+}
+
+/// Generate a complete theme from a single accent color
+#[tauri::command]
+fn generate_theme_from_accent(name: String, accent_hex: String, dark: bool) -> Result<ThemeConfig, String> {
+    config::generate_theme_from_accent(name, accent_hex, dark)
+}
+
+/// Delete a custom theme (built-in themes are refused)
+#[tauri::command]
+fn delete_custom_theme(folder_path: Option<String>, theme_name: String) -> Result<(), String> {
+    config::delete_theme(folder_path, theme_name)
+}
+
+/// Copy a theme under a new name for tweaking
+#[tauri::command]
+fn duplicate_theme(folder_path: Option<String>, source_name: String, new_name: String) -> Result<String, String> {
+    config::duplicate_theme(folder_path, source_name, new_name)
+}
+
 /// Export a theme to an external file
 #[tauri::command]
 fn export_custom_theme(folder_path: Option<String>, theme_name: String, dest_path: String) -> Result<(), String> {
     export_theme(folder_path, theme_name, dest_path)
 }
 
+/// Export all themes to a single zip archive
+#[tauri::command]
+fn export_all_themes(folder_path: Option<String>, dest_path: String) -> Result<(), String> {
+    config::export_all_themes(folder_path, dest_path)
+}
+
+/// Import all themes from a zip archive created by export_all_themes
+#[tauri::command]
+fn import_themes_archive(folder_path: Option<String>, archive_path: String) -> Result<Vec<String>, String> {
+    config::import_themes_archive(folder_path, archive_path)
+}
+
+/// Copy a directory of theme files into the folder's custom themes
+#[tauri::command]
+fn seed_themes_from(folder_path: Option<String>, source_dir: String) -> Result<Vec<String>, String> {
+    config::seed_themes_from(folder_path, source_dir)
+}
+
+// Template commands
+
+/// List the templates in .loom/templates
+#[tauri::command]
+fn list_templates(folder_path: Option<String>) -> Result<Vec<String>, String> {
+    templates::list_templates(folder_path)
+}
+
+/// Create a new file from a template
+#[tauri::command]
+fn apply_template(
+    folder_path: Option<String>,
+    template_name: String,
+    path: String,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<(), String> {
+    ensure_in_workspace(&workspace_root, &path)?;
+    templates::apply_template(folder_path, &template_name, path)
+}
+
+/// Fill in a template's {{placeholders}} and return it for insertion
+#[tauri::command]
+fn render_template(
+    template_name: String,
+    folder_path: Option<String>,
+    variables: HashMap<String, String>,
+) -> Result<String, String> {
+    templates::render_template(folder_path, &template_name, &variables)
+}
+
+// Snippet commands
+
+/// Get all snippets, keyed by trigger
+#[tauri::command]
+fn get_snippets(folder_path: Option<String>) -> Result<BTreeMap<String, String>, String> {
+    snippets::get_snippets(folder_path)
+}
+
+/// Add or update a snippet
+#[tauri::command]
+fn save_snippet(folder_path: Option<String>, trigger: String, body: String) -> Result<(), String> {
+    snippets::save_snippet(folder_path, &trigger, body)
+}
+
+/// Expand a snippet trigger to its text, or None if there's no such snippet
+#[tauri::command]
+fn expand_snippet(folder_path: Option<String>, trigger: String) -> Result<Option<String>, String> {
+    snippets::expand_snippet(folder_path, &trigger)
+}
+
+// Session commands
+
+/// Save the open tabs and layout for a folder
+#[tauri::command]
+fn save_session(folder_path: Option<String>, session: SessionState) -> Result<(), String> {
+    session::save_session(folder_path, &session)
+}
+
+/// Load the saved session for a folder, without files that have since been removed
+#[tauri::command]
+fn load_session(folder_path: Option<String>) -> Result<SessionState, String> {
+    session::load_session(folder_path)
+}
+
+// Plugin commands
+
+/// List the plugins installed in .loom/plugins
+#[tauri::command]
+fn list_plugins(folder_path: Option<String>) -> Result<Vec<PluginManifest>, String> {
+    plugins::list_plugins(folder_path)
+}
+
+/// Enable or disable a plugin by name
+#[tauri::command]
+fn set_plugin_enabled(folder_path: Option<String>, name: String, enabled: bool) -> Result<(), String> {
+    plugins::set_plugin_enabled(folder_path, &name, enabled)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -603,39 +818,116 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(create_watcher_state())
+        .manage(create_search_cancel_state())
+        .manage(create_workspace_root_state())
+        .manage(create_render_cache())
+        .manage(create_render_options_state())
+        .manage(create_autosave_state())
         .invoke_handler(tauri::generate_handler![
             render_markdown,
+            render_markdown_highlighted,
             render_markdown_batch,
+            render_markdown_range,
+            render_markdown_window,
+            clear_render_cache,
+            extract_tags,
+            get_foldable_regions,
+            lint_markdown,
+            format_markdown,
+            reflow_paragraphs,
+            renumber_ordered_lists,
+            normalize_heading_levels,
+            sort_list_block,
+            reindent_lines,
+            next_list_prefix,
+            format_table,
+            toggle_inline_format,
+            html_to_markdown,
+            sanitize_html,
+            strip_markdown,
+            document_stats,
+            selection_stats,
+            render_math,
+            set_workspace_root,
             read_directory,
             read_file_from_path,
+            read_file_with_encoding,
+            read_file_normalized,
+            save_file_with_encoding,
+            schedule_autosave,
             create_file,
             create_folder,
             delete_file,
             delete_folder,
             count_folder_contents,
+            count_folder_contents_recursive,
             rename_path,
             move_path,
             copy_path,
+            batch_file_operations,
             save_image_from_clipboard,
             is_image_file,
             start_watching_directory,
             stop_watching_directory,
+            stop_watching_path,
+            start_watching_themes,
+            stop_watching_themes,
             init_loom_dir,
             get_loom_directory,
             load_config,
             save_config,
             get_config,
             update_config,
+            effective_config,
+            get_file_settings,
+            set_file_settings,
+            validate_keybinds,
+            reset_keybinds,
             set_theme,
             get_current_theme,
             get_theme,
             get_available_themes,
+            validate_theme,
             import_custom_theme,
             export_custom_theme,
+            export_all_themes,
+            import_themes_archive,
+            render_theme_preview,
+            seed_themes_from,
+            list_templates,
+            apply_template,
+            render_template,
+            get_snippets,
+            save_snippet,
+            expand_snippet,
+            save_session,
+            load_session,
+            list_plugins,
+            set_plugin_enabled,
+            delete_custom_theme,
+            generate_theme_from_accent,
+            check_theme_contrast,
+            duplicate_theme,
             search_in_content,
+            validate_search_regex,
             replace_in_content,
+            preview_replace,
             search_in_directory,
+            count_matches_in_directory,
+            search_in_directory_streaming,
+            cancel_search,
+            find_files_by_tag,
+            collect_front_matter,
+            resolve_wikilink,
+            build_backlink_index,
+            check_links,
+            fuzzy_find_files,
+            git_status,
+            git_file_diff,
+            export_document_html,
+            export_document_pdf,
+            export_document_docx,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
-}
\ No newline at end of file
+}
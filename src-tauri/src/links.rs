@@ -0,0 +1,259 @@
+use crate::markdown::{heading_slugs, parse_wikilink, DocumentBlockMap, WIKILINK_RE};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+// [text](target) links; the optional leading ! also picks up images
+static MARKDOWN_LINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"!?\[([^\]]*)\]\(([^)]+)\)").unwrap());
+static URL_SCHEME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[a-zA-Z][a-zA-Z0-9+.-]*:").unwrap());
+
+/// Whether a link target is a URL with a scheme (http:, mailto:, data:, ...)
+/// rather than a local path; a Windows drive letter isn't a scheme
+pub fn is_remote_url(url: &str) -> bool {
+    let bytes = url.as_bytes();
+    let is_windows_path = bytes.len() > 2 && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/');
+    URL_SCHEME_RE.is_match(url) && !is_windows_path
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LinkState {
+    /// Local file exists or the anchor matches a heading
+    Ok,
+    /// Local file or heading anchor not found
+    Missing,
+    /// Remote URL, not checked
+    Unchecked,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkStatus {
+    pub line: usize,
+    pub text: String,
+    pub url: String,
+    pub status: LinkState,
+}
+
+/// Check every `[text](url)` link in a document
+///
+/// Local targets are looked up relative to `base_dir` and `#slug` links are
+/// matched against the document's own headings. Remote URLs aren't fetched.
+/// Line numbers are 1-based. Links inside code blocks are ignored.
+#[tauri::command]
+pub fn check_links(content: String, base_dir: String) -> Vec<LinkStatus> {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let slugs = heading_slugs(&content);
+    let base = Path::new(&base_dir);
+    let mut results = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        if map.state(i).in_code {
+            continue;
+        }
+
+        for cap in MARKDOWN_LINK_RE.captures_iter(line) {
+            // Drop an optional "title" and angle brackets around the target
+            let url = cap[2].split_whitespace().next().unwrap_or("");
+            let url = url.trim_start_matches('<').trim_end_matches('>');
+
+            let status = if is_remote_url(url) {
+                LinkState::Unchecked
+            } else if let Some(fragment) = url.strip_prefix('#') {
+                if slugs.iter().any(|slug| slug == fragment) {
+                    LinkState::Ok
+                } else {
+                    LinkState::Missing
+                }
+            } else {
+                let file_part = url.split(['#', '?']).next().unwrap_or("").replace("%20", " ");
+                if base.join(file_part).exists() {
+                    LinkState::Ok
+                } else {
+                    LinkState::Missing
+                }
+            };
+
+            results.push(LinkStatus {
+                line: i + 1,
+                text: cap[1].to_string(),
+                url: url.to_string(),
+                status,
+            });
+        }
+    }
+
+    results
+}
+
+/// Check whether a markdown file matches a wikilink target
+///
+/// The target is matched against the file name without `.md`, ignoring case.
+/// Targets with folders (`projects/alpha`) must match the end of the path.
+fn matches_wikilink_target(relative: &Path, target: &str) -> bool {
+    let without_ext = relative.with_extension("");
+    let candidate = without_ext.to_string_lossy().replace('\\', "/").to_lowercase();
+    let target = target.trim_end_matches(".md").to_lowercase();
+
+    candidate == target || candidate.ends_with(&format!("/{}", target))
+}
+
+/// Find the markdown file a `[[target]]` wikilink points to
+///
+/// Any `#heading` part of the target is ignored. When several files match,
+/// the one closest to the workspace root wins.
+#[tauri::command]
+pub fn resolve_wikilink(folder_path: String, target: String) -> Option<String> {
+    let root = Path::new(&folder_path);
+    let target = target.split('#').next().unwrap_or("").trim();
+    if target.is_empty() || !root.is_dir() {
+        return None;
+    }
+
+    WalkDir::new(root)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().extension().and_then(|s| s.to_str()) == Some("md"))
+        .filter(|entry| {
+            entry
+                .path()
+                .strip_prefix(root)
+                .map(|relative| matches_wikilink_target(relative, target))
+                .unwrap_or(false)
+        })
+        .min_by_key(|entry| entry.depth())
+        .map(|entry| entry.path().to_string_lossy().to_string())
+}
+
+/// Map each wikilink target to the files that link to it
+///
+/// Targets are keyed as written in the link, without any alias or
+/// `#heading` part. Each referencing file is listed once per target.
+#[tauri::command]
+pub fn build_backlink_index(dir_path: String) -> Result<HashMap<String, Vec<String>>, String> {
+    let path = Path::new(&dir_path);
+    if !path.exists() || !path.is_dir() {
+        return Err("Directory does not exist".to_string());
+    }
+
+    let mut index: HashMap<String, Vec<String>> = HashMap::new();
+
+    for entry in WalkDir::new(path)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let entry_path = entry.path();
+
+        // Only look at .md files
+        if !entry_path.is_file() {
+            continue;
+        }
+
+        if entry_path.extension().and_then(|s| s.to_str()) != Some("md") {
+            continue;
+        }
+
+        let content = match fs::read_to_string(entry_path) {
+            Ok(c) => c,
+            Err(_) => continue, // Skip files we can't read
+        };
+
+        let file_path = entry_path.to_string_lossy().to_string();
+
+        for cap in WIKILINK_RE.captures_iter(&content) {
+            let (target, _) = parse_wikilink(&cap[1]);
+            let target = target.split('#').next().unwrap_or("").trim();
+            if target.is_empty() {
+                continue;
+            }
+
+            let sources = index.entry(target.to_string()).or_default();
+            if !sources.contains(&file_path) {
+                sources.push(file_path.clone());
+            }
+        }
+    }
+
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_wikilink() {
+        let dir = std::env::temp_dir().join(format!("loom-wikilinks-{}", std::process::id()));
+        fs::create_dir_all(dir.join("projects")).unwrap();
+        fs::write(dir.join("Other Note.md"), "# Other").unwrap();
+        fs::write(dir.join("projects").join("alpha.md"), "# Alpha").unwrap();
+        let folder = dir.to_string_lossy().to_string();
+
+        let plain = resolve_wikilink(folder.clone(), "other note".to_string());
+        let nested = resolve_wikilink(folder.clone(), "projects/alpha#Goals".to_string());
+        let by_name = resolve_wikilink(folder.clone(), "alpha".to_string());
+        let missing = resolve_wikilink(folder, "Nowhere".to_string());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(plain.unwrap().ends_with("Other Note.md"));
+        assert!(nested.unwrap().ends_with("alpha.md"));
+        assert!(by_name.unwrap().ends_with("alpha.md"));
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn test_build_backlink_index() {
+        let dir = std::env::temp_dir().join(format!("loom-backlinks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), "Links to [[Target]] twice: [[Target|again]]").unwrap();
+        fs::write(dir.join("b.md"), "See [[Target#Section]]").unwrap();
+        fs::write(dir.join("Target.md"), "# Target\nNo links here").unwrap();
+
+        let index = build_backlink_index(dir.to_string_lossy().to_string()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(index.len(), 1);
+        let sources = &index["Target"];
+        assert_eq!(sources.len(), 2);
+        assert!(sources[0].ends_with("a.md"));
+        assert!(sources[1].ends_with("b.md"));
+    }
+
+    #[test]
+    fn test_check_links() {
+        let dir = std::env::temp_dir().join(format!("loom-check-links-{}", std::process::id()));
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        fs::write(dir.join("docs").join("guide.md"), "# Guide").unwrap();
+
+        let content = "# Getting Started\n\
+            See [the guide](docs/guide.md#intro) and [old notes](docs/old.md).\n\
+            Jump to [start](#getting-started) or [nowhere](#missing).\n\
+            Visit [site](https://example.com).";
+        let results = check_links(content.to_string(), dir.to_string_lossy().to_string());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let statuses: Vec<(usize, &str, LinkState)> = results
+            .iter()
+            .map(|r| (r.line, r.url.as_str(), r.status.clone()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (2, "docs/guide.md#intro", LinkState::Ok),
+                (2, "docs/old.md", LinkState::Missing),
+                (3, "#getting-started", LinkState::Ok),
+                (3, "#missing", LinkState::Missing),
+                (4, "https://example.com", LinkState::Unchecked),
+            ]
+        );
+    }
+}
@@ -5,6 +5,33 @@
  * to ensure proper context-aware rendering.
  */
 
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Get the length of the backtick fence a line opens with, if any
+///
+/// A fence is a run of at least three backticks at the start of the
+/// (already trimmed) line.
+pub fn fence_length(trimmed: &str) -> Option<usize> {
+    let len = trimmed.chars().take_while(|c| *c == '`').count();
+    if len >= 3 {
+        Some(len)
+    } else {
+        None
+    }
+}
+
+/// Check if a trimmed line closes a code block opened with a fence of `open_len`
+///
+/// The closing fence must be at least as long as the opening one and carry
+/// no info string, so shorter fences can appear literally inside the block.
+pub fn is_closing_fence(trimmed: &str, open_len: usize) -> bool {
+    match fence_length(trimmed) {
+        Some(len) => len >= open_len && len == trimmed.len(),
+        None => false,
+    }
+}
+
 /// Check if a line is inside a code block
 ///
 /// Returns a tuple of (in_block, is_start, is_end)
@@ -12,23 +39,39 @@
 /// - is_start: true if this line starts a code block
 /// - is_end: true if this line ends a code block
 pub fn is_in_code_block(line_index: usize, all_lines: &[String]) -> (bool, bool, bool) {
-    let mut in_block = false;
+    // Length of the fence that opened the current block
+    let mut open_fence: Option<usize> = None;
 
     for (i, line) in all_lines.iter().enumerate() {
         if i > line_index {
             break;
         }
 
-        if line.trim().starts_with("```") {
-            if i == line_index {
-                // This line is a code block boundary
-                return (true, !in_block, in_block);
+        let trimmed = line.trim();
+
+        match open_fence {
+            None => {
+                if let Some(len) = fence_length(trimmed) {
+                    if i == line_index {
+                        // This line opens a code block
+                        return (true, true, false);
+                    }
+                    open_fence = Some(len);
+                }
+            }
+            Some(open_len) => {
+                if is_closing_fence(trimmed, open_len) {
+                    if i == line_index {
+                        // This line closes a code block
+                        return (true, false, true);
+                    }
+                    open_fence = None;
+                }
             }
-            in_block = !in_block;
         }
     }
 
-    (in_block, false, false)
+    (open_fence.is_some(), false, false)
 }
 
 /// Check if a line is inside a math block
@@ -57,6 +100,152 @@ pub fn is_in_math_block(line_index: usize, all_lines: &[String]) -> (bool, bool,
     (in_block, false, false)
 }
 
+/// Code and math block state for a single line
+///
+/// Each pair of flags mirrors the (in_block, is_start, is_end) tuples
+/// returned by `is_in_code_block` and `is_in_math_block`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LineBlockState {
+    pub in_code: bool,
+    pub code_start: bool,
+    pub code_end: bool,
+    pub in_math: bool,
+    pub math_start: bool,
+    pub math_end: bool,
+}
+
+/// Block context carried from one line to the next
+///
+/// Callers rendering part of a document pass the context entering it, so
+/// the rest of the document isn't needed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockContext {
+    /// Backtick count of the open code fence, if inside a code block
+    pub open_fence: Option<usize>,
+    pub in_math: bool,
+}
+
+impl BlockContext {
+    /// Block state of each of `lines`, continuing from this context, and
+    /// the context after the last of them
+    pub fn scan(self, lines: &[String]) -> (Vec<LineBlockState>, BlockContext) {
+        let mut carry = self;
+        let states = lines
+            .iter()
+            .map(|line| {
+                let (state, after) = scan_line(carry, line);
+                carry = after;
+                state
+            })
+            .collect();
+        (states, carry)
+    }
+}
+
+/// Work out a line's block state from the context before it,
+/// returning the line state and the context after it
+fn scan_line(before: BlockContext, line: &str) -> (LineBlockState, BlockContext) {
+    let trimmed = line.trim();
+    let mut state = LineBlockState::default();
+    let mut after = before;
+
+    match before.open_fence {
+        None => {
+            if let Some(len) = fence_length(trimmed) {
+                state.in_code = true;
+                state.code_start = true;
+                after.open_fence = Some(len);
+            }
+        }
+        Some(open_len) => {
+            state.in_code = true;
+            if is_closing_fence(trimmed, open_len) {
+                state.code_end = true;
+                after.open_fence = None;
+            }
+        }
+    }
+
+    if trimmed == "$$" {
+        state.in_math = true;
+        state.math_start = !before.in_math;
+        state.math_end = before.in_math;
+        after.in_math = !before.in_math;
+    } else {
+        state.in_math = before.in_math;
+    }
+
+    (state, after)
+}
+
+/// Block state for every line of a document
+///
+/// Building the map is a single pass over the document, and after an edit
+/// it can be updated incrementally: lines before the edit keep their state,
+/// and the rescan stops as soon as it rejoins the previous scan after the
+/// edited range.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentBlockMap {
+    states: Vec<LineBlockState>,
+    after: Vec<BlockContext>,
+}
+
+impl DocumentBlockMap {
+    /// Build the block map for a whole document
+    pub fn build(all_lines: &[String]) -> Self {
+        let mut map = Self::default();
+        map.update(all_lines, 0..all_lines.len());
+        map
+    }
+
+    /// Block state of a line
+    pub fn state(&self, line_index: usize) -> LineBlockState {
+        self.states.get(line_index).copied().unwrap_or_default()
+    }
+
+    /// Update the map after an edit
+    ///
+    /// `all_lines` is the document after the edit and `edited` the range of
+    /// lines in it that changed (empty for a pure deletion). Returns the range
+    /// of lines whose state was recomputed.
+    pub fn update(&mut self, all_lines: &[String], edited: Range<usize>) -> Range<usize> {
+        let old_len = self.states.len();
+        let new_len = all_lines.len();
+        let start = edited.start.min(old_len).min(new_len);
+
+        let old_states = self.states.split_off(start);
+        let old_after = self.after.split_off(start);
+
+        let mut carry = self.after.last().copied().unwrap_or_default();
+
+        for (i, line) in all_lines.iter().enumerate().skip(start) {
+            let (state, after) = scan_line(carry, line);
+            self.states.push(state);
+            self.after.push(after);
+            carry = after;
+
+            // Past the edit, the remaining lines are the old lines shifted by
+            // the change in length. Once the scan agrees with the old scan
+            // again, everything after it is unchanged too.
+            if i + 1 >= edited.end {
+                let old_index = (i + old_len).checked_sub(new_len);
+                let rejoined = old_index
+                    .and_then(|j| j.checked_sub(start))
+                    .filter(|j| old_after.get(*j) == Some(&after) && old_states.get(*j) == Some(&state));
+
+                if let Some(j) = rejoined {
+                    self.states.extend_from_slice(&old_states[j + 1..]);
+                    self.after.extend_from_slice(&old_after[j + 1..]);
+                    return start..i + 1;
+                }
+            }
+        }
+
+        start..new_len
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,6 +276,40 @@ mod tests {
         assert!(!in_block && !is_start && !is_end);
     }
 
+    #[test]
+    fn test_longer_fence_contains_shorter_fence() {
+        let lines = vec![
+            "````markdown".to_string(),
+            "```rust".to_string(),
+            "fn main() {}".to_string(),
+            "```".to_string(),
+            "````".to_string(),
+            "After".to_string(),
+        ];
+
+        let (_in_block, is_start, _is_end) = is_in_code_block(0, &lines);
+        assert!(is_start);
+
+        // Three-backtick fences are content inside a four-backtick block
+        for i in 1..=3 {
+            let (in_block, is_start, is_end) = is_in_code_block(i, &lines);
+            assert!(in_block && !is_start && !is_end, "line {} should be content", i);
+        }
+
+        let (_in_block, is_start, is_end) = is_in_code_block(4, &lines);
+        assert!(is_end && !is_start);
+
+        let (in_block, is_start, is_end) = is_in_code_block(5, &lines);
+        assert!(!in_block && !is_start && !is_end);
+    }
+
+    #[test]
+    fn test_fence_length() {
+        assert_eq!(fence_length("```rust"), Some(3));
+        assert_eq!(fence_length("````"), Some(4));
+        assert_eq!(fence_length("``not a fence"), None);
+    }
+
     #[test]
     fn test_math_block_detection() {
         let lines = vec![
@@ -112,4 +335,79 @@ mod tests {
         let (in_block, is_start, is_end) = is_in_math_block(4, &lines);
         assert!(!in_block && !is_start && !is_end);
     }
+
+    fn full_scan_states(lines: &[String]) -> Vec<LineBlockState> {
+        (0..lines.len())
+            .map(|i| {
+                let (in_code, code_start, code_end) = is_in_code_block(i, lines);
+                let (in_math, math_start, math_end) = is_in_math_block(i, lines);
+                LineBlockState { in_code, code_start, code_end, in_math, math_start, math_end }
+            })
+            .collect()
+    }
+
+    fn document() -> Vec<String> {
+        vec![
+            "# Title", "```rust", "fn main() {}", "```", "$$", "x^2", "$$", "Text", "More text", "End",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect()
+    }
+
+    #[test]
+    fn test_block_map_matches_line_scans() {
+        let lines = document();
+        let map = DocumentBlockMap::build(&lines);
+        let states: Vec<_> = (0..lines.len()).map(|i| map.state(i)).collect();
+        assert_eq!(states, full_scan_states(&lines));
+    }
+
+    #[test]
+    fn test_block_map_edit_after_last_fence() {
+        let mut lines = document();
+        let mut map = DocumentBlockMap::build(&lines);
+
+        lines[8] = "Edited text".to_string();
+        let recomputed = map.update(&lines, 8..9);
+
+        // Nothing before the edit is recomputed and the scan rejoins right after it
+        assert_eq!(recomputed, 8..9);
+        let states: Vec<_> = (0..lines.len()).map(|i| map.state(i)).collect();
+        assert_eq!(states, full_scan_states(&lines));
+    }
+
+    #[test]
+    fn test_block_map_edit_fence_recomputes_downstream() {
+        let mut lines = document();
+        let mut map = DocumentBlockMap::build(&lines);
+
+        // Removing the closing fence leaves the rest of the document in the code block
+        lines[3] = "// no longer a fence".to_string();
+        let recomputed = map.update(&lines, 3..4);
+
+        assert_eq!(recomputed, 3..lines.len());
+        let states: Vec<_> = (0..lines.len()).map(|i| map.state(i)).collect();
+        assert_eq!(states, full_scan_states(&lines));
+        assert_eq!(states, (0..lines.len()).map(|i| DocumentBlockMap::build(&lines).state(i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_block_map_line_count_changes() {
+        let mut lines = document();
+        let mut map = DocumentBlockMap::build(&lines);
+
+        // Insert two lines inside the code block
+        lines.insert(2, "let a = 1;".to_string());
+        lines.insert(3, "let b = 2;".to_string());
+        map.update(&lines, 2..4);
+        let states: Vec<_> = (0..lines.len()).map(|i| map.state(i)).collect();
+        assert_eq!(states, full_scan_states(&lines));
+
+        // Delete the opening fence
+        lines.remove(1);
+        map.update(&lines, 1..1);
+        let states: Vec<_> = (0..lines.len()).map(|i| map.state(i)).collect();
+        assert_eq!(states, full_scan_states(&lines));
+    }
 }
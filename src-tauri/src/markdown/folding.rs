@@ -0,0 +1,135 @@
+/**
+ * Folding regions
+ *
+ * Finds the line ranges the editor can fold: fenced code blocks, math
+ * blocks and heading sections.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use super::block_detection::DocumentBlockMap;
+use super::HEADER_RE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FoldKind {
+    Code,
+    Math,
+    Heading,
+}
+
+/// A foldable range of 0-based lines, both ends inclusive
+///
+/// Folding hides the lines after `start` up to and including `end`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FoldRegion {
+    pub start: usize,
+    pub end: usize,
+    pub kind: FoldKind,
+}
+
+/// Find every foldable region of a document, ordered by start line
+///
+/// Code and math blocks run from their opening to their closing fence, or
+/// to the end of the document if never closed. A heading's section runs
+/// to the line before the next heading of the same or a higher level,
+/// leaving out trailing blank lines. Regions with nothing to hide are
+/// skipped.
+pub fn get_foldable_regions(content: &str) -> Vec<FoldRegion> {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let last_line = lines.len().saturating_sub(1);
+
+    let mut regions = Vec::new();
+    let mut open_code: Option<usize> = None;
+    let mut open_math: Option<usize> = None;
+    // Headings whose section is still open, innermost last, as (line, level)
+    let mut open_headings: Vec<(usize, usize)> = Vec::new();
+
+    let last_content_line = |from: usize, before: usize| {
+        (from..before).rev().find(|&i| !lines[i].trim().is_empty()).unwrap_or(from)
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        let state = map.state(i);
+
+        if state.code_start {
+            open_code = Some(i);
+        } else if state.code_end {
+            if let Some(start) = open_code.take() {
+                regions.push(FoldRegion { start, end: i, kind: FoldKind::Code });
+            }
+        }
+        if state.in_code {
+            continue;
+        }
+
+        if state.math_start {
+            open_math = Some(i);
+        } else if state.math_end {
+            if let Some(start) = open_math.take() {
+                regions.push(FoldRegion { start, end: i, kind: FoldKind::Math });
+            }
+        }
+        if state.in_math {
+            continue;
+        }
+
+        if let Some(cap) = HEADER_RE.captures(line) {
+            let level = cap[1].len();
+            while let Some(&(start, _)) = open_headings.last().filter(|(_, open_level)| *open_level >= level) {
+                open_headings.pop();
+                regions.push(FoldRegion { start, end: last_content_line(start, i), kind: FoldKind::Heading });
+            }
+            open_headings.push((i, level));
+        }
+    }
+
+    if let Some(start) = open_code {
+        regions.push(FoldRegion { start, end: last_line, kind: FoldKind::Code });
+    }
+    if let Some(start) = open_math {
+        regions.push(FoldRegion { start, end: last_line, kind: FoldKind::Math });
+    }
+    for (start, _) in open_headings {
+        regions.push(FoldRegion { start, end: last_content_line(start, lines.len()), kind: FoldKind::Heading });
+    }
+
+    regions.retain(|region| region.end > region.start);
+    regions.sort_by_key(|region| (region.start, std::cmp::Reverse(region.end)));
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn region(start: usize, end: usize, kind: FoldKind) -> FoldRegion {
+        FoldRegion { start, end, kind }
+    }
+
+    #[test]
+    fn test_code_and_math_blocks() {
+        let content = "Intro\n```rust\n# not a heading\n```\n$$\nx^2\n$$\n```\nunclosed";
+
+        assert_eq!(
+            get_foldable_regions(content),
+            [region(1, 3, FoldKind::Code), region(4, 6, FoldKind::Math), region(7, 8, FoldKind::Code)]
+        );
+    }
+
+    #[test]
+    fn test_nested_heading_sections() {
+        let content = "# One\ntext\n## Two\nmore\n### Three\n\n## Four\nlast\n\n# Five\n# Six\nend\n";
+
+        assert_eq!(
+            get_foldable_regions(content),
+            [
+                region(0, 7, FoldKind::Heading),
+                region(2, 4, FoldKind::Heading),
+                region(6, 7, FoldKind::Heading),
+                region(10, 11, FoldKind::Heading),
+            ]
+        );
+    }
+}
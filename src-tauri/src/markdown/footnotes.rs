@@ -0,0 +1,158 @@
+/**
+ * Footnotes
+ *
+ * Collects `[^id]: text` definitions from a document so `[^id]` references
+ * can be linked to a footnotes section appended after the document.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+
+use super::block_detection::DocumentBlockMap;
+use super::inline_rendering::render_inline_markdown;
+
+static DEFINITION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[\^([^\]\s]+)\]:\s*(.*)$").unwrap());
+// Code spans are matched first so references inside them are skipped
+static REFERENCE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`[^`]*`|\[\^([^\]\s]+)\]").unwrap());
+// A reference stands in as `\x01index\x01` while its line is rendered; tags
+// are matched first so markers that end up inside one can be told apart
+static MARKER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>|\x01(\d+)\x01").unwrap());
+static MARKER_IN_TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x01(\d+)\x01").unwrap());
+
+/// Footnote definitions of a document and the references seen so far
+#[derive(Debug, Default)]
+pub struct Footnotes {
+    definitions: HashMap<String, String>,
+    /// Referenced ids in order of first reference; a footnote's number is its position + 1
+    referenced: Vec<String>,
+}
+
+impl Footnotes {
+    /// Collect the definitions of a document, skipping code blocks
+    pub fn collect(lines: &[String], map: &DocumentBlockMap) -> Self {
+        let mut footnotes = Self::default();
+        for (i, line) in lines.iter().enumerate() {
+            if map.state(i).in_code {
+                continue;
+            }
+            if let Some(cap) = DEFINITION_RE.captures(line) {
+                footnotes.definitions.entry(cap[1].to_string()).or_insert_with(|| cap[2].to_string());
+            }
+        }
+        footnotes
+    }
+
+    /// Whether a line is a footnote definition
+    pub fn is_definition(line: &str) -> bool {
+        DEFINITION_RE.is_match(line)
+    }
+
+    /// Render a line with `render`, linking references to defined footnotes
+    ///
+    /// References are swapped for markers before rendering and linked in the
+    /// output, so `[^id]` in a code span or produced by the rendering is left
+    /// alone. A reference that ends up inside a tag, like a link's URL, is
+    /// put back as written. References to undefined footnotes are left as
+    /// written.
+    pub fn link_references(&mut self, line: &str, render: impl FnOnce(&str) -> String) -> String {
+        let mut ids = Vec::new();
+        let marked = REFERENCE_RE.replace_all(line, |caps: &regex::Captures| match caps.get(1) {
+            Some(id) if self.definitions.contains_key(id.as_str()) => {
+                ids.push(id.as_str().to_string());
+                format!("\x01{}\x01", ids.len() - 1)
+            }
+            _ => caps[0].to_string(),
+        });
+        if ids.is_empty() {
+            return render(line);
+        }
+
+        let id_at = |index: &str| index.parse::<usize>().ok().and_then(|index| ids.get(index));
+        MARKER_RE
+            .replace_all(&render(&marked), |caps: &regex::Captures| {
+                let Some(index) = caps.get(1) else {
+                    return MARKER_IN_TAG_RE
+                        .replace_all(&caps[0], |marker: &regex::Captures| match id_at(&marker[1]) {
+                            Some(id) => html_escape::encode_quoted_attribute(&format!("[^{}]", id)).to_string(),
+                            None => marker[0].to_string(),
+                        })
+                        .to_string();
+                };
+                match id_at(index.as_str()) {
+                    Some(id) => self.reference_link(id),
+                    None => caps[0].to_string(),
+                }
+            })
+            .to_string()
+    }
+
+    // Superscript link for a reference, numbering the footnote on its first reference
+    fn reference_link(&mut self, id: &str) -> String {
+        // Only the first reference gets the id the back link points to
+        let (number, anchor) = match self.referenced.iter().position(|seen| seen == id) {
+            Some(index) => (index + 1, String::new()),
+            None => {
+                self.referenced.push(id.to_string());
+                let number = self.referenced.len();
+                (number, format!(" id=\"fnref-{}\"", number))
+            }
+        };
+        format!(
+            "<sup class=\"footnote-ref\"><a href=\"#fn-{}\"{}>{}</a></sup>",
+            number, anchor, number
+        )
+    }
+
+    /// The footnotes section for every referenced footnote, or "" if none were referenced
+    pub fn render_section(&self) -> String {
+        if self.referenced.is_empty() {
+            return String::new();
+        }
+
+        let items: String = self
+            .referenced
+            .iter()
+            .enumerate()
+            .map(|(index, id)| {
+                format!(
+                    "<li id=\"fn-{number}\">{} <a href=\"#fnref-{number}\" class=\"footnote-backref\">\u{21A9}</a></li>",
+                    render_inline_markdown(&self.definitions[id]),
+                    number = index + 1
+                )
+            })
+            .collect();
+        format!("<section class=\"footnotes\"><ol>{}</ol></section>\n", items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn footnotes_for(content: &str) -> Footnotes {
+        let lines: Vec<String> = content.lines().map(String::from).collect();
+        Footnotes::collect(&lines, &DocumentBlockMap::build(&lines))
+    }
+
+    #[test]
+    fn test_reference_links_to_definition() {
+        let mut footnotes = footnotes_for("Text\n\n[^b]: Second\n[^a]: First *note*");
+
+        assert_eq!(
+            footnotes.link_references("One[^a], two[^b], again[^a]", str::to_string),
+            "One<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup>, \
+             two<sup class=\"footnote-ref\"><a href=\"#fn-2\" id=\"fnref-2\">2</a></sup>, \
+             again<sup class=\"footnote-ref\"><a href=\"#fn-1\">1</a></sup>"
+        );
+        assert!(footnotes.render_section().contains("<li id=\"fn-1\">First <em>note</em> <a href=\"#fnref-1\""));
+    }
+
+    #[test]
+    fn test_undefined_and_code_references_stay_literal() {
+        let mut footnotes = footnotes_for("```\n[^code]: not a definition\n```\n[^1]: Real");
+
+        assert_eq!(footnotes.link_references("[^code] and `[^1]`", str::to_string), "[^code] and `[^1]`");
+        assert_eq!(footnotes.render_section(), "");
+    }
+}
@@ -0,0 +1,547 @@
+/**
+ * Markdown formatting
+ *
+ * Normalizes whitespace and markers in a document and rewraps paragraph
+ * text. Code and math blocks are copied through untouched.
+ */
+
+use super::block_detection::DocumentBlockMap;
+use super::inline_rendering::visible_text;
+use super::{HEADER_RE, HR_RE, LIST_RE};
+
+/// Normalize a markdown document
+///
+/// - runs of blank lines are collapsed to a single blank line
+/// - trailing whitespace is removed, except a two-space line break
+/// - headings and list items get exactly one space after the marker
+/// - unordered list markers become `-`
+pub fn format_markdown(content: &str) -> String {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    let mut previous_blank = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        let state = map.state(i);
+        if state.in_code || state.in_math {
+            output.push(line.clone());
+            previous_blank = false;
+            continue;
+        }
+
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            if !previous_blank {
+                output.push(String::new());
+            }
+            previous_blank = true;
+            continue;
+        }
+        previous_blank = false;
+
+        // Headings can't contain a line break, so their trailing spaces always go
+        if let Some(cap) = HEADER_RE.captures(trimmed) {
+            output.push(format!("{} {}", &cap[1], cap[2].trim_start()));
+            continue;
+        }
+
+        let line_break = if line[trimmed.len()..].starts_with("  ") { "  " } else { "" };
+
+        let formatted = if let Some(cap) = LIST_RE.captures(trimmed) {
+            let marker = if cap[2].starts_with(|c: char| c.is_ascii_digit()) { &cap[2] } else { "-" };
+            format!("{}{} {}", &cap[1], marker, &cap[4])
+        } else {
+            trimmed.to_string()
+        };
+
+        output.push(format!("{}{}", formatted, line_break));
+    }
+
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Rewrap paragraph text to `width` columns
+///
+/// Only plain paragraphs are rewrapped; headings, list items, blockquotes,
+/// tables, rules and code or math blocks keep their line structure, as do
+/// lines that continue a list item or blockquote. A line ending in two
+/// spaces is a hard break and stays the end of a line. Words longer than
+/// `width` are put on a line of their own rather than split.
+pub fn reflow_paragraphs(content: &str, width: usize) -> String {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    // Words of the paragraph text since the last line break
+    let mut words: Vec<&str> = Vec::new();
+    // Set after a list item or blockquote until the next blank line
+    let mut in_container = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        let state = map.state(i);
+        let trimmed = line.trim();
+
+        let is_paragraph_text = !state.in_code
+            && !state.in_math
+            && !trimmed.is_empty()
+            && !in_container
+            && !HEADER_RE.is_match(trimmed)
+            && !HR_RE.is_match(trimmed)
+            && !trimmed.starts_with('|')
+            && !LIST_RE.is_match(line)
+            && !trimmed.starts_with('>');
+
+        if !is_paragraph_text {
+            flush_words(&mut words, width, "", &mut output);
+            if !state.in_code && !state.in_math {
+                if trimmed.is_empty() {
+                    in_container = false;
+                } else if LIST_RE.is_match(line) || trimmed.starts_with('>') {
+                    in_container = true;
+                }
+            }
+            output.push(line.clone());
+            continue;
+        }
+
+        words.extend(trimmed.split_whitespace());
+        if line.ends_with("  ") {
+            flush_words(&mut words, width, "  ", &mut output);
+        }
+    }
+    flush_words(&mut words, width, "", &mut output);
+
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Renumber ordered list items
+///
+/// Each contiguous list at an indent level counts up from the number of its
+/// first item, and nested lists keep their own counter. An unordered item
+/// at the same indent, or unindented text, ends the run.
+pub fn renumber_ordered_lists(content: &str) -> String {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let mut output: Vec<String> = Vec::with_capacity(lines.len());
+    // Open lists from outermost to innermost as (indent, next number), with
+    // no number for unordered lists
+    let mut lists: Vec<(usize, Option<usize>)> = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let state = map.state(i);
+        let indented = line.starts_with(char::is_whitespace);
+
+        if state.in_code || state.in_math {
+            if !indented {
+                lists.clear();
+            }
+            output.push(line.clone());
+            continue;
+        }
+
+        let Some(cap) = LIST_RE.captures(line) else {
+            if !indented && !line.trim().is_empty() {
+                lists.clear();
+            }
+            output.push(line.clone());
+            continue;
+        };
+
+        let indent = cap[1].len();
+        let digits = cap.get(3);
+        let number = digits.and_then(|n| n.as_str().parse::<usize>().ok());
+
+        while lists.last().is_some_and(|&(open, _)| open > indent) {
+            lists.pop();
+        }
+        if lists.last().is_some_and(|&(open, next)| open == indent && next.is_some() != number.is_some()) {
+            lists.pop();
+        }
+
+        match lists.last_mut() {
+            Some((open, Some(next))) if *open == indent => {
+                output.push(format!("{}{}{}", &cap[1], next, &line[digits.unwrap().end()..]));
+                *next += 1;
+            }
+            Some((open, None)) if *open == indent => output.push(line.clone()),
+            _ => {
+                lists.push((indent, number.map(|n| n + 1)));
+                output.push(line.clone());
+            }
+        }
+    }
+
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Shift all headings so the shallowest one becomes H1
+///
+/// Relative structure is kept, so a document using H3 and H4 ends up with
+/// H1 and H2. Headings in code and math blocks aren't counted or changed.
+pub fn normalize_heading_levels(content: &str) -> String {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let is_heading = |i: usize, line: &str| {
+        let state = map.state(i);
+        !state.in_code && !state.in_math && HEADER_RE.is_match(line)
+    };
+
+    let shift = lines
+        .iter()
+        .enumerate()
+        .filter(|(i, line)| is_heading(*i, line))
+        .map(|(_, line)| line.len() - line.trim_start_matches('#').len())
+        .min()
+        .map_or(0, |level| level - 1);
+    if shift == 0 {
+        return content.to_string();
+    }
+
+    let output: Vec<&str> =
+        lines.iter().enumerate().map(|(i, line)| if is_heading(i, line) { &line[shift..] } else { line.as_str() }).collect();
+
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// The marker to start the next item with when Enter is pressed on a list item
+///
+/// Unordered items repeat their indent and bullet, and ordered items count
+/// up keeping their delimiter. An item with no text gives `None`, so Enter
+/// on an empty item ends the list, as does a line that isn't a list item.
+pub fn next_list_prefix(line: &str) -> Option<String> {
+    let cap = LIST_RE.captures(line)?;
+    if cap[4].trim().is_empty() {
+        return None;
+    }
+
+    let indent = &cap[1];
+    match cap.get(3) {
+        Some(number) => {
+            let delimiter = &cap[2][number.len()..];
+            let next = increment_number(number.as_str())?;
+            Some(format!("{}{}{} ", indent, next, delimiter))
+        }
+        None => Some(format!("{}{} ", indent, &cap[2])),
+    }
+}
+
+// Add one to a string of ASCII digits, however long, dropping leading zeros
+fn increment_number(digits: &str) -> Option<String> {
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut next: Vec<u8> = digits.trim_start_matches('0').bytes().collect();
+    match next.iter().rposition(|&digit| digit != b'9') {
+        Some(pos) => {
+            next[pos] += 1;
+            next[pos + 1..].fill(b'0');
+        }
+        None => {
+            next.fill(b'0');
+            next.insert(0, b'1');
+        }
+    }
+    String::from_utf8(next).ok()
+}
+
+/// Most levels `reindent_lines` shifts by in one call
+const MAX_REINDENT_LEVELS: i32 = 16;
+
+/// Indent or outdent lines `start..=end` (0-based) by `delta` levels
+///
+/// A level is `tab_size` columns. Leading whitespace is rewritten as
+/// spaces, with tabs counting as a full level, and outdenting stops at
+/// column zero. Blank lines are left alone, and ordered list numbers are
+/// kept as written; `renumber_ordered_lists` can fix them up afterwards.
+/// `delta` is clamped to `MAX_REINDENT_LEVELS` either way.
+pub fn reindent_lines(content: &str, start: usize, end: usize, delta: i32, tab_size: u8) -> String {
+    let tab_size = tab_size.max(1) as i64;
+    let delta = delta.clamp(-MAX_REINDENT_LEVELS, MAX_REINDENT_LEVELS);
+    let shift = delta as i64 * tab_size;
+
+    let output: Vec<String> = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if i < start || i > end || line.trim().is_empty() {
+                return line.to_string();
+            }
+
+            let text = line.trim_start();
+            let leading = &line[..line.len() - text.len()];
+            let columns: i64 = leading.chars().map(|c| if c == '\t' { tab_size } else { 1 }).sum();
+            let indent = (columns + shift).max(0) as usize;
+            format!("{}{}", " ".repeat(indent), text)
+        })
+        .collect();
+
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Sort the items of the list containing `start_line` (0-based)
+///
+/// The list is the contiguous run of items at the same indent and of the
+/// same kind (ordered or unordered) as the item on `start_line`. Items are
+/// compared case-insensitively by their visible text, and nested lines move
+/// with their parent. Item markers stay in place so ordered lists keep
+/// counting up. The document is returned unchanged if `start_line` is not
+/// a list item.
+pub fn sort_list_block(content: &str, start_line: usize, ascending: bool) -> String {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+
+    let item_at = |i: usize| {
+        if map.state(i).in_code || map.state(i).in_math {
+            return None;
+        }
+        LIST_RE.captures(&lines[i]).map(|cap| {
+            let ordered = cap.get(3).is_some();
+            (cap[1].len(), ordered, cap.get(4).unwrap().start())
+        })
+    };
+    let indent_of = |line: &str| line.len() - line.trim_start().len();
+
+    let Some((indent, ordered, _)) = lines.get(start_line).and_then(|_| item_at(start_line)) else {
+        return content.to_string();
+    };
+    let is_block_item = |i: usize| item_at(i).is_some_and(|(n, o, _)| n == indent && o == ordered);
+    let is_child = |i: usize| !lines[i].trim().is_empty() && indent_of(&lines[i]) > indent;
+
+    // Walk back to the first item of the block, then forward past its last line
+    let mut start = start_line;
+    let mut i = start_line;
+    while i > 0 && (is_block_item(i - 1) || is_child(i - 1)) {
+        i -= 1;
+        if is_block_item(i) {
+            start = i;
+        }
+    }
+    let mut end = start_line + 1;
+    while end < lines.len() && (is_block_item(end) || is_child(end)) {
+        end += 1;
+    }
+
+    // Split the block into items, each a marker line followed by its children
+    let mut items: Vec<(String, Vec<String>)> = Vec::new();
+    let mut markers: Vec<String> = Vec::new();
+    for (i, line) in lines.iter().enumerate().take(end).skip(start) {
+        match item_at(i) {
+            Some((_, _, text_start)) if is_block_item(i) => {
+                let (marker, text) = line.split_at(text_start);
+                markers.push(marker.to_string());
+                items.push((visible_text(text).to_lowercase(), vec![text.to_string()]));
+            }
+            _ => items.last_mut().unwrap().1.push(line.clone()),
+        }
+    }
+
+    items.sort_by(|a, b| if ascending { a.0.cmp(&b.0) } else { b.0.cmp(&a.0) });
+
+    let mut output: Vec<String> = lines[..start].to_vec();
+    for (marker, (_, mut item_lines)) in markers.into_iter().zip(items) {
+        item_lines[0].insert_str(0, &marker);
+        output.extend(item_lines);
+    }
+    output.extend_from_slice(&lines[end..]);
+
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Greedily fill lines of at most `width` columns with `words`, ending the
+/// last line with `line_break`
+fn flush_words(words: &mut Vec<&str>, width: usize, line_break: &str, output: &mut Vec<String>) {
+    if words.is_empty() {
+        return;
+    }
+
+    let mut current = String::new();
+    for word in words.drain(..) {
+        if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width {
+            output.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    current.push_str(line_break);
+    output.push(current);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_blocks_are_untouched() {
+        let code = "```python\n#   comment   \n*   not a list\n\n\n\n  indented  \n```";
+        let content = format!("#  Title\n\n{}\n", code);
+
+        let formatted = format_markdown(&content);
+
+        assert_eq!(formatted, format!("# Title\n\n{}\n", code));
+    }
+
+    #[test]
+    fn test_heading_and_list_spacing() {
+        let content = "##   Heading   \n*   one\n+ two\n   1.   first\n\n\n\nline with break  \ntrailing \t";
+
+        assert_eq!(
+            format_markdown(content),
+            "## Heading\n- one\n- two\n   1. first\n\nline with break  \ntrailing"
+        );
+    }
+
+    #[test]
+    fn test_reflow_long_paragraph() {
+        let content = "# A heading that is much longer than forty columns wide\n\n\
+                       The quick brown fox jumps over the lazy dog and keeps on running far past the\n\
+                       end of the field.\n\n\
+                       - a list item that is also longer than forty columns wide";
+
+        assert_eq!(
+            reflow_paragraphs(content, 40),
+            "# A heading that is much longer than forty columns wide\n\n\
+             The quick brown fox jumps over the lazy\n\
+             dog and keeps on running far past the\n\
+             end of the field.\n\n\
+             - a list item that is also longer than forty columns wide"
+        );
+    }
+
+    #[test]
+    fn test_reflow_keeps_hard_breaks() {
+        let content = "first line ends here  \nsecond\nline";
+
+        assert_eq!(reflow_paragraphs(content, 40), "first line ends here  \nsecond line");
+    }
+
+    #[test]
+    fn test_reflow_leaves_code_blocks_untouched() {
+        let code = "```\nshort\nlines that would otherwise be joined into one\n```";
+        let content = format!("text\n{}\nmore text\n", code);
+
+        assert_eq!(reflow_paragraphs(&content, 40), format!("text\n{}\nmore text\n", code));
+    }
+
+    #[test]
+    fn test_renumber_flat_list() {
+        let content = "1. one\n1. two\n5. three\n\ntext\n\n3. again\n3. and again\n";
+
+        assert_eq!(
+            renumber_ordered_lists(content),
+            "1. one\n2. two\n3. three\n\ntext\n\n3. again\n4. and again\n"
+        );
+    }
+
+    #[test]
+    fn test_next_list_prefix() {
+        assert_eq!(next_list_prefix("- item"), Some("- ".to_string()));
+        assert_eq!(next_list_prefix("    * nested"), Some("    * ".to_string()));
+        assert_eq!(next_list_prefix("9. ninth"), Some("10. ".to_string()));
+        assert_eq!(next_list_prefix("  3) third"), Some("  4) ".to_string()));
+        assert_eq!(next_list_prefix("007. bond"), Some("8. ".to_string()));
+        assert_eq!(next_list_prefix("18446744073709551615. x"), Some("18446744073709551616. ".to_string()));
+        assert_eq!(next_list_prefix("99999999999999999999999. x"), Some("100000000000000000000000. ".to_string()));
+    }
+
+    #[test]
+    fn test_next_list_prefix_ends_list() {
+        assert_eq!(next_list_prefix("- "), None);
+        assert_eq!(next_list_prefix("2.   "), None);
+        assert_eq!(next_list_prefix("plain text"), None);
+    }
+
+    #[test]
+    fn test_normalize_heading_levels() {
+        let content = "### Title\n\n#### Section\n\n```\n# comment\n```\n### Other\n";
+
+        assert_eq!(
+            normalize_heading_levels(content),
+            "# Title\n\n## Section\n\n```\n# comment\n```\n# Other\n"
+        );
+        assert_eq!(normalize_heading_levels("# Top\n### Deep"), "# Top\n### Deep");
+        assert_eq!(normalize_heading_levels("no headings"), "no headings");
+    }
+
+    #[test]
+    fn test_indent_list_items() {
+        let content = "- one\n- two\n- three\n";
+
+        assert_eq!(reindent_lines(content, 1, 2, 1, 2), "- one\n  - two\n  - three\n");
+        assert_eq!(reindent_lines("1. a\n\tb\n\n2. c", 0, 3, 1, 4), "    1. a\n        b\n\n    2. c");
+    }
+
+    #[test]
+    fn test_outdent_stops_at_zero() {
+        let content = "   - nested\n- top\ntext";
+
+        assert_eq!(reindent_lines(content, 0, 2, -1, 2), " - nested\n- top\ntext");
+        assert_eq!(reindent_lines(content, 0, 1, -3, 2), "- nested\n- top\ntext");
+        assert_eq!(reindent_lines("x", 0, 0, i32::MAX, 255).len(), 16 * 255 + 1);
+        assert_eq!(reindent_lines("  x", 0, 0, i32::MIN, 255), "x");
+    }
+
+    #[test]
+    fn test_renumber_nested_list() {
+        let content = "1. one\n   1. a\n   1. b\n1. two\n   3. c\n   - d\n   1. e\n1. three";
+
+        assert_eq!(
+            renumber_ordered_lists(content),
+            "1. one\n   1. a\n   2. b\n2. two\n   3. c\n   - d\n   1. e\n3. three"
+        );
+    }
+
+    #[test]
+    fn test_sort_list_ascending() {
+        let content = "Fruit:\n- banana\n- **cherry**\n- [apple](apple.md)\n\n- zucchini\n";
+
+        assert_eq!(
+            sort_list_block(content, 2, true),
+            "Fruit:\n- [apple](apple.md)\n- banana\n- **cherry**\n\n- zucchini\n"
+        );
+    }
+
+    #[test]
+    fn test_sort_list_descending() {
+        let content = "1. alpha\n2. Charlie\n3. bravo";
+
+        assert_eq!(sort_list_block(content, 0, false), "1. Charlie\n2. bravo\n3. alpha");
+    }
+
+    #[test]
+    fn test_sort_list_moves_children_with_parent() {
+        let content = "- b\n  - b child\n    more b\n- a\n  1. a child\n- c";
+
+        assert_eq!(
+            sort_list_block(content, 5, true),
+            "- a\n  1. a child\n- b\n  - b child\n    more b\n- c"
+        );
+        // A nested line sorts its own list only
+        assert_eq!(sort_list_block("- x\n  - z\n  - y", 1, true), "- x\n  - y\n  - z");
+    }
+}
@@ -0,0 +1,137 @@
+/**
+ * Search highlighting
+ *
+ * Wraps ranges of a source line in highlight spans inside its rendered HTML.
+ * Ranges are given in source columns, so they're mapped past markdown markers
+ * that don't appear in the output and HTML entities that stand for one character.
+ */
+
+use html_escape::decode_html_entities;
+
+const HIGHLIGHT_OPEN: &str = "<span class=\"search-highlight\">";
+const HIGHLIGHT_CLOSE: &str = "</span>";
+
+// Longest entity we try to decode, e.g. "&#x1F600;"
+const MAX_ENTITY_LENGTH: usize = 10;
+
+/// A tag (`text` is `None`) or one visible character of rendered HTML
+struct Piece<'a> {
+    raw: &'a str,
+    text: Option<char>,
+}
+
+/// Sort ranges, dropping empty ones and merging any that overlap or touch
+fn merge_ranges(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.retain(|(start, end)| start < end);
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+/// Whether a `<` starts a tag rather than being literal text like `a < b`
+fn starts_tag(html: &str) -> bool {
+    html[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!')
+}
+
+/// Byte length of the tag at the start of `html`, skipping `>` inside quoted attributes
+fn tag_length(html: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    html.len()
+}
+
+/// The character an entity at the start of `html` stands for, and its byte length
+fn entity_at(html: &str) -> Option<(char, usize)> {
+    let end = html.find(';').filter(|&end| end < MAX_ENTITY_LENGTH)?;
+    let entity = &html[..=end];
+    let decoded = decode_html_entities(entity);
+    let mut chars = decoded.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if decoded != entity => Some((c, entity.len())),
+        _ => None,
+    }
+}
+
+/// Split HTML into tags and visible characters
+fn html_pieces(html: &str) -> Vec<Piece<'_>> {
+    let mut pieces = Vec::new();
+    let mut i = 0;
+
+    while let Some(c) = html[i..].chars().next() {
+        let rest = &html[i..];
+        let (length, text) = match c {
+            '<' if starts_tag(rest) => (tag_length(rest), None),
+            '&' => entity_at(rest).map_or((1, Some('&')), |(c, length)| (length, Some(c))),
+            _ => (c.len_utf8(), Some(c)),
+        };
+        pieces.push(Piece { raw: &rest[..length], text });
+        i += length;
+    }
+    pieces
+}
+
+/// Source column of each visible character
+///
+/// The visible text is matched in order against the source, so markers
+/// missing from the output are skipped over. Characters the renderer added
+/// (list bullets, emoji) aren't found and get no column.
+fn align(source: &[char], visible: &[char]) -> Vec<Option<usize>> {
+    let mut next = 0;
+    visible
+        .iter()
+        .map(|c| {
+            let column = source[next..].iter().position(|s| s == c).map(|offset| next + offset);
+            if let Some(column) = column {
+                next = column + 1;
+            }
+            column
+        })
+        .collect()
+}
+
+/// Wrap the characters rendered from `ranges` of `line` in search highlight spans
+///
+/// Ranges are 0-based character columns, end exclusive. A highlight is
+/// closed before each tag and reopened after it so spans always nest.
+pub fn highlight_html(html: &str, line: &str, ranges: Vec<(usize, usize)>) -> String {
+    let ranges = merge_ranges(ranges);
+    if ranges.is_empty() {
+        return html.to_string();
+    }
+
+    let pieces = html_pieces(html);
+    let source: Vec<char> = line.chars().collect();
+    let visible: Vec<char> = pieces.iter().filter_map(|piece| piece.text).collect();
+    let mut columns = align(&source, &visible).into_iter();
+    let highlighted = |column: usize| ranges.iter().any(|&(start, end)| start <= column && column < end);
+
+    let mut output = String::with_capacity(html.len() + ranges.len() * (HIGHLIGHT_OPEN.len() + HIGHLIGHT_CLOSE.len()));
+    let mut open = false;
+    for piece in pieces {
+        let highlight = piece.text.is_some() && columns.next().flatten().is_some_and(highlighted);
+        if highlight != open {
+            output.push_str(if highlight { HIGHLIGHT_OPEN } else { HIGHLIGHT_CLOSE });
+            open = highlight;
+        }
+        output.push_str(piece.raw);
+    }
+    if open {
+        output.push_str(HIGHLIGHT_CLOSE);
+    }
+
+    output
+}
@@ -0,0 +1,158 @@
+/**
+ * HTML to markdown conversion
+ *
+ * Turns pasted HTML back into markdown, the reverse of the inline
+ * renderer. Elements without a markdown equivalent are dropped but their
+ * text is kept.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node};
+
+use super::format::format_markdown;
+
+static WHITESPACE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\s+").unwrap());
+
+/// Convert an HTML fragment to markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    // Blocks are padded with blank lines on both sides; formatting collapses them
+    format_markdown(&convert_children(fragment.root_element())).trim().to_string()
+}
+
+fn convert_children(element: ElementRef) -> String {
+    let mut output = String::new();
+    for child in element.children() {
+        if let Some(child) = ElementRef::wrap(child) {
+            output.push_str(&convert_element(child));
+        } else if let Node::Text(text) = child.value() {
+            output.push_str(&WHITESPACE_RE.replace_all(text, " "));
+        }
+    }
+    output
+}
+
+/// Wrap inline content in a marker, leaving empty content out entirely
+fn wrap_inline(marker: &str, content: &str) -> String {
+    let content = content.trim();
+    if content.is_empty() {
+        String::new()
+    } else {
+        format!("{}{}{}", marker, content, marker)
+    }
+}
+
+fn convert_element(element: ElementRef) -> String {
+    let name = element.value().name();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            format!("\n\n{} {}\n\n", "#".repeat(level), convert_children(element).trim())
+        }
+        "p" | "div" => format!("\n\n{}\n\n", convert_children(element).trim()),
+        "br" => "  \n".to_string(),
+        "hr" => "\n\n---\n\n".to_string(),
+        "strong" | "b" => wrap_inline("**", &convert_children(element)),
+        "em" | "i" => wrap_inline("*", &convert_children(element)),
+        "del" | "s" => wrap_inline("~~", &convert_children(element)),
+        "code" => wrap_inline("`", &element.text().collect::<String>()),
+        "pre" => {
+            let code = element.text().collect::<String>();
+            let language = element
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter_map(|child| child.value().attr("class"))
+                .flat_map(str::split_whitespace)
+                .find_map(|class| class.strip_prefix("language-"))
+                .unwrap_or("");
+            format!("\n\n```{}\n{}\n```\n\n", language, code.trim_end_matches('\n'))
+        }
+        "a" => {
+            let text = convert_children(element);
+            match element.value().attr("href") {
+                Some(href) => format!("[{}]({})", text.trim(), href),
+                None => text,
+            }
+        }
+        "img" => format!(
+            "![{}]({})",
+            element.value().attr("alt").unwrap_or(""),
+            element.value().attr("src").unwrap_or("")
+        ),
+        "ul" | "ol" => {
+            let mut number = element.value().attr("start").and_then(|s| s.parse::<usize>().ok()).unwrap_or(1);
+            let mut items = Vec::new();
+            for item in element.children().filter_map(ElementRef::wrap) {
+                if item.value().name() != "li" {
+                    continue;
+                }
+                let marker = if name == "ol" { format!("{}. ", number) } else { "- ".to_string() };
+                number += 1;
+                items.push(convert_list_item(item, &marker));
+            }
+            format!("\n\n{}\n\n", items.join("\n"))
+        }
+        "blockquote" => {
+            let content = format_markdown(&convert_children(element)).trim().to_string();
+            let quoted: Vec<String> = content
+                .lines()
+                .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+                .collect();
+            format!("\n\n{}\n\n", quoted.join("\n"))
+        }
+        "script" | "style" | "head" => String::new(),
+        _ => convert_children(element),
+    }
+}
+
+/// Convert a list item, indenting its continuation lines under the marker
+fn convert_list_item(item: ElementRef, marker: &str) -> String {
+    let content = convert_children(item);
+    let indent = " ".repeat(marker.len());
+    let mut lines = content.lines().map(str::trim_end).filter(|line| !line.trim().is_empty());
+
+    let mut output = format!("{}{}", marker, lines.next().unwrap_or("").trim_start());
+    for line in lines {
+        output.push('\n');
+        output.push_str(&indent);
+        output.push_str(line);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading() {
+        assert_eq!(html_to_markdown("<h2>Getting <em>started</em></h2><p>Intro</p>"), "## Getting *started*\n\nIntro");
+    }
+
+    #[test]
+    fn test_bulleted_list() {
+        let html = "<ul>\n  <li>one</li>\n  <li>two\n    <ol><li>a</li><li>b</li></ol>\n  </li>\n</ul>";
+
+        assert_eq!(html_to_markdown(html), "- one\n- two\n  1. a\n  2. b");
+    }
+
+    #[test]
+    fn test_link_and_unknown_tags() {
+        let html = "<p>See <span class=\"x\"><a href=\"https://example.com\">the docs</a></span> &amp; more</p>";
+
+        assert_eq!(html_to_markdown(html), "See [the docs](https://example.com) & more");
+    }
+
+    #[test]
+    fn test_bold_run() {
+        assert_eq!(html_to_markdown("<b>bold</b> and <strong>strong</strong><strong> </strong>"), "**bold** and **strong**");
+    }
+
+    #[test]
+    fn test_code_block_keeps_whitespace() {
+        let html = "<pre><code class=\"language-rust\">fn main() {\n    println!();\n}\n</code></pre>";
+
+        assert_eq!(html_to_markdown(html), "```rust\nfn main() {\n    println!();\n}\n```");
+    }
+}
@@ -0,0 +1,100 @@
+/**
+ * Inline format toggling
+ *
+ * Wraps a selection in the markers for bold, italic, code, strikethrough
+ * or highlight, or removes them if the selection already has them, using
+ * the same markers the inline renderer understands.
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InlineFormat {
+    Bold,
+    Italic,
+    Code,
+    Strikethrough,
+    Highlight,
+}
+
+impl InlineFormat {
+    fn marker(self) -> &'static str {
+        match self {
+            InlineFormat::Bold => "**",
+            InlineFormat::Italic => "*",
+            InlineFormat::Code => "`",
+            InlineFormat::Strikethrough => "~~",
+            InlineFormat::Highlight => "==",
+        }
+    }
+}
+
+/// Whether `text` is already wrapped in `format`'s markers
+///
+/// Bold and italic share `*`, so they are told apart by how many stars
+/// wrap the text: `*a*` is italic, `**a**` bold and `***a***` both.
+fn is_wrapped(text: &str, format: InlineFormat) -> bool {
+    let stars = text.len() - text.trim_start_matches('*').len();
+    let closing = text.len() - text.trim_end_matches('*').len();
+    let wrapping = stars.min(closing).min(text.len() / 2);
+
+    match format {
+        InlineFormat::Bold => wrapping >= 2,
+        InlineFormat::Italic => wrapping % 2 == 1,
+        _ => {
+            let marker = format.marker();
+            text.len() >= 2 * marker.len() && text.starts_with(marker) && text.ends_with(marker)
+        }
+    }
+}
+
+/// Wrap a selection in a format's markers, or unwrap it if already wrapped
+///
+/// Whitespace around the selection stays outside the markers, since the
+/// renderer ignores emphasis that starts or ends with a space. An empty
+/// selection gives just the pair of markers, for the cursor to go between.
+pub fn toggle_inline_format(text: &str, format: InlineFormat) -> String {
+    let marker = format.marker();
+    let core = text.trim();
+    if core.is_empty() {
+        return format!("{}{}{}", text, marker, marker);
+    }
+
+    let start = text.len() - text.trim_start().len();
+    let (leading, trailing) = (&text[..start], &text[start + core.len()..]);
+
+    if is_wrapped(core, format) {
+        let inner = &core[marker.len()..core.len() - marker.len()];
+        format!("{}{}{}", leading, inner, trailing)
+    } else {
+        format!("{}{}{}{}{}", leading, marker, core, marker, trailing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_bold() {
+        assert_eq!(toggle_inline_format("word", InlineFormat::Bold), "**word**");
+        assert_eq!(toggle_inline_format("**word**", InlineFormat::Bold), "word");
+        assert_eq!(toggle_inline_format(" two words ", InlineFormat::Bold), " **two words** ");
+        assert_eq!(toggle_inline_format("", InlineFormat::Bold), "****");
+    }
+
+    #[test]
+    fn test_toggle_code() {
+        assert_eq!(toggle_inline_format("let x", InlineFormat::Code), "`let x`");
+        assert_eq!(toggle_inline_format("`let x`", InlineFormat::Code), "let x");
+    }
+
+    #[test]
+    fn test_bold_and_italic_share_stars() {
+        assert_eq!(toggle_inline_format("**word**", InlineFormat::Italic), "***word***");
+        assert_eq!(toggle_inline_format("***word***", InlineFormat::Italic), "**word**");
+        assert_eq!(toggle_inline_format("***word***", InlineFormat::Bold), "*word*");
+        assert_eq!(toggle_inline_format("*word*", InlineFormat::Bold), "***word***");
+    }
+}
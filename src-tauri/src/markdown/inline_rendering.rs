@@ -7,57 +7,288 @@
 
 use regex::Regex;
 use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+use super::sanitize::sanitize_html;
 
 // Pre-compiled regex patterns for better performance
-static BOLD_ITALIC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*\*(.+?)\*\*\*").unwrap());
-static BOLD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*(.+?)\*\*").unwrap());
-static BOLD_UNDERSCORE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"__(.+?)__").unwrap());
-static ITALIC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*(.+?)\*").unwrap());
-static ITALIC_UNDERSCORE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"_(.+?)_").unwrap());
-static STRIKE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"~~(.+?)~~").unwrap());
+// Emphasis content can't start or end with whitespace, so `a * b * c` stays literal
+static BOLD_ITALIC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*\*([^\s*](?:.*?[^\s*])??)\*\*\*").unwrap());
+static BOLD_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*\*([^\s*](?:.*?[^\s*])??)\*\*").unwrap());
+static BOLD_UNDERSCORE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"__([^\s_](?:.*?[^\s_])??)__").unwrap());
+static ITALIC_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\*([^\s*](?:.*?[^\s*])??)\*").unwrap());
+static ITALIC_UNDERSCORE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"_([^\s_](?:.*?[^\s_])??)_").unwrap());
+static STRIKE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"~~([^\s~](?:.*?[^\s~])??)~~").unwrap());
+static HIGHLIGHT_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"==([^\s=](?:.*?[^\s=])??)==").unwrap());
 static CODE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"`([^`]+)`").unwrap());
 static IMAGE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"!\[([^\]]*)\]\(([^\)]+)\)").unwrap());
 static LINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[([^\]]+)\]\(([^\)]+)\)").unwrap());
+pub static WIKILINK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[\[([^\]]+)\]\]").unwrap());
+// Stands in for a code span while the markers around it are stripped
+static CODE_PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x00(\d+)\x00").unwrap());
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+// Code spans, link targets and bare URLs are matched first so shortcodes inside them are skipped
+static EMOJI_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"`[^`]*`|\]\([^)]*\)|[a-zA-Z][a-zA-Z0-9+.-]*://\S+|:([a-z0-9_+\-]+):").unwrap()
+});
+
+/// Lines longer than this (in bytes) are shown as plain text
+///
+/// Each pattern is matched in linear time, but emphasis matches that get
+/// skipped restart the search, so very long lines dense with delimiters
+/// could still take a while.
+const MAX_INLINE_LENGTH: usize = 20_000;
+
+/// Shortcode to emoji table, following GitHub's names
+const EMOJI_SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "\u{1F44D}"),
+    ("-1", "\u{1F44E}"),
+    ("100", "\u{1F4AF}"),
+    ("bug", "\u{1F41B}"),
+    ("bulb", "\u{1F4A1}"),
+    ("calendar", "\u{1F4C6}"),
+    ("check", "\u{2714}\u{FE0F}"),
+    ("clap", "\u{1F44F}"),
+    ("coffee", "\u{2615}"),
+    ("construction", "\u{1F6A7}"),
+    ("cry", "\u{1F622}"),
+    ("eyes", "\u{1F440}"),
+    ("fire", "\u{1F525}"),
+    ("grin", "\u{1F601}"),
+    ("heart", "\u{2764}\u{FE0F}"),
+    ("heavy_check_mark", "\u{2714}\u{FE0F}"),
+    ("hourglass", "\u{231B}"),
+    ("information_source", "\u{2139}\u{FE0F}"),
+    ("joy", "\u{1F602}"),
+    ("laughing", "\u{1F606}"),
+    ("link", "\u{1F517}"),
+    ("lock", "\u{1F512}"),
+    ("memo", "\u{1F4DD}"),
+    ("question", "\u{2753}"),
+    ("rocket", "\u{1F680}"),
+    ("sad", "\u{1F61E}"),
+    ("smile", "\u{1F604}"),
+    ("smiley", "\u{1F603}"),
+    ("sparkles", "\u{2728}"),
+    ("star", "\u{2B50}"),
+    ("sunglasses", "\u{1F60E}"),
+    ("tada", "\u{1F389}"),
+    ("thinking", "\u{1F914}"),
+    ("thumbsdown", "\u{1F44E}"),
+    ("thumbsup", "\u{1F44D}"),
+    ("warning", "\u{26A0}\u{FE0F}"),
+    ("wave", "\u{1F44B}"),
+    ("white_check_mark", "\u{2705}"),
+    ("wink", "\u{1F609}"),
+    ("x", "\u{274C}"),
+    ("zap", "\u{26A1}"),
+];
+
+static EMOJI: Lazy<HashMap<&'static str, &'static str>> =
+    Lazy::new(|| EMOJI_SHORTCODES.iter().copied().collect());
+
+/// Split the inside of a `[[...]]` wikilink into (target, display text)
+///
+/// Supports the `[[Target|Display]]` alias form; without an alias the
+/// target is also the display text.
+pub fn parse_wikilink(inner: &str) -> (&str, &str) {
+    match inner.split_once('|') {
+        Some((target, display)) => (target.trim(), display.trim()),
+        None => (inner.trim(), inner.trim()),
+    }
+}
+
+/// Replace emphasis matches of `re`, expanding `replacement` like `replace_all`
+///
+/// A match touching another `delimiter` on either side is part of a longer
+/// delimiter run (like the `**` around bold text), so it's skipped.
+/// Underscores also can't open or close emphasis inside a word, so
+/// `snake_case_name` stays as written.
+///
+/// Every opening delimiter inside a skipped match pairs with the same
+/// closing delimiter, so the text up to it is scanned once rather than
+/// searched again from each opening; this keeps long lines linear.
+fn replace_emphasis(text: &str, re: &Regex, delimiter: char, replacement: &str) -> String {
+    let blocked = |c: char| c == delimiter || (delimiter == '_' && c.is_alphanumeric());
+    let blocked_before = |pos: usize| text[..pos].chars().next_back().is_some_and(blocked);
+    let mut output = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut search = 0;
+
+    while let Some(caps) = re.captures_at(text, search) {
+        let m = caps.get(0).unwrap();
+        let opening = &text[m.start()..caps.get(1).unwrap().start()];
+        let closing = m.end() - opening.len();
+
+        // A blocked closing delimiter blocks every match ending at it
+        if text[m.end()..].chars().next().is_some_and(blocked) {
+            search = closing;
+            continue;
+        }
+
+        // Otherwise look for a later opening that isn't blocked itself
+        let caps = if blocked_before(m.start()) {
+            let next_opening = text[m.start()..closing]
+                .char_indices()
+                .skip(1)
+                .map(|(i, _)| m.start() + i)
+                .find(|&pos| {
+                    let content = pos + opening.len();
+                    text[pos..].starts_with(opening)
+                        && content < closing
+                        && text[content..].chars().next().is_some_and(|c| !c.is_whitespace() && c != delimiter)
+                        && !blocked_before(pos)
+                });
+            match next_opening.and_then(|pos| re.captures_at(text, pos)) {
+                Some(caps) => caps,
+                None => {
+                    search = closing;
+                    continue;
+                }
+            }
+        } else {
+            caps
+        };
+
+        let m = caps.get(0).unwrap();
+        output.push_str(&text[copied..m.start()]);
+        caps.expand(replacement, &mut output);
+        copied = m.end();
+        search = m.end();
+    }
+
+    output.push_str(&text[copied..]);
+    output
+}
+
+/// Replace known `:shortcode:`s with their emoji, leaving unknown ones as written
+fn render_emoji(text: &str) -> String {
+    EMOJI_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            caps.get(1)
+                .and_then(|name| EMOJI.get(name.as_str()))
+                .map(|emoji| emoji.to_string())
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .to_string()
+}
+
+fn render_wikilinks(text: &str, with_markers: bool) -> String {
+    WIKILINK_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            let (target, display) = parse_wikilink(&caps[1]);
+            let label = if with_markers { &caps[0] } else { display };
+            format!(
+                "<a class=\"wikilink\" data-target=\"{}\">{}</a>",
+                html_escape::encode_double_quoted_attribute(target),
+                label
+            )
+        })
+        .to_string()
+}
+
+/// Escape tag brackets so HTML typed into a note shows as text
+///
+/// Ampersands are left alone so entities like `&copy;` still render.
+fn escape_tags(text: &str) -> String {
+    text.replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Encode text for a quoted attribute value
+///
+/// The text may already have entities from earlier escaping, so it's
+/// decoded first to avoid escaping them twice.
+fn attribute_value(text: &str) -> String {
+    html_escape::encode_quoted_attribute(&html_escape::decode_html_entities(text)).to_string()
+}
+
+/// Encode a link or image target for an attribute, replacing URLs with
+/// unsafe schemes by `#`
+///
+/// Script URLs are always rejected, as are `data:` URLs other than images.
+fn url_attribute(url: &str, is_image: bool) -> String {
+    // Browsers skip whitespace and control characters when reading the scheme
+    let scheme: String = html_escape::decode_html_entities(url)
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .take(16)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let is_unsafe = scheme.starts_with("javascript:")
+        || scheme.starts_with("vbscript:")
+        || (scheme.starts_with("data:") && !(is_image && scheme.starts_with("data:image/")));
+
+    if is_unsafe {
+        "#".to_string()
+    } else {
+        attribute_value(url)
+    }
+}
 
 /// Render inline markdown (bold, italic, code, links, images, etc.)
 ///
+/// Any HTML in the text is escaped.
+///
 /// Note: LaTeX rendering is still handled on the frontend via KaTeX
 pub fn render_inline_markdown(text: &str) -> String {
-    let mut result = text.to_string();
+    if text.len() > MAX_INLINE_LENGTH {
+        return html_escape::encode_text(text).to_string();
+    }
+    render_inline_spans(&escape_tags(text))
+}
+
+/// Render inline markdown, keeping HTML in the text after sanitizing it
+pub fn render_inline_markdown_with_html(text: &str) -> String {
+    let sanitized = sanitize_html(text);
+    if text.len() > MAX_INLINE_LENGTH {
+        return sanitized;
+    }
+    render_inline_spans(&sanitized)
+}
+
+fn render_inline_spans(text: &str) -> String {
+    // Emoji first, while code spans still have their backticks
+    let mut result = render_emoji(text);
 
     // Bold + Italic (must come before individual bold/italic)
-    result = BOLD_ITALIC_RE
-        .replace_all(&result, "<strong><em>$1</em></strong>")
-        .to_string();
+    result = replace_emphasis(&result, &BOLD_ITALIC_RE, '*', "<strong><em>$1</em></strong>");
 
     // Bold
-    result = BOLD_RE
-        .replace_all(&result, "<strong>$1</strong>")
-        .to_string();
-    result = BOLD_UNDERSCORE_RE
-        .replace_all(&result, "<strong>$1</strong>")
-        .to_string();
+    result = replace_emphasis(&result, &BOLD_RE, '*', "<strong>$1</strong>");
+    result = replace_emphasis(&result, &BOLD_UNDERSCORE_RE, '_', "<strong>$1</strong>");
 
     // Italic
-    result = ITALIC_RE.replace_all(&result, "<em>$1</em>").to_string();
-    result = ITALIC_UNDERSCORE_RE
-        .replace_all(&result, "<em>$1</em>")
-        .to_string();
+    result = replace_emphasis(&result, &ITALIC_RE, '*', "<em>$1</em>");
+    result = replace_emphasis(&result, &ITALIC_UNDERSCORE_RE, '_', "<em>$1</em>");
 
     // Strikethrough
-    result = STRIKE_RE.replace_all(&result, "<del>$1</del>").to_string();
+    result = replace_emphasis(&result, &STRIKE_RE, '~', "<del>$1</del>");
+
+    // Highlight
+    result = replace_emphasis(&result, &HIGHLIGHT_RE, '=', "<mark>$1</mark>");
 
     // Inline code
     result = CODE_RE.replace_all(&result, "<code>$1</code>").to_string();
 
+    // Wikilinks
+    result = render_wikilinks(&result, false);
+
     // Images (must come before links to avoid conflict)
     result = IMAGE_RE
-        .replace_all(&result, "<img src=\"$2\" alt=\"$1\" class=\"markdown-image\" />")
+        .replace_all(&result, |caps: &regex::Captures| {
+            format!(
+                "<img src=\"{}\" alt=\"{}\" class=\"markdown-image\" />",
+                url_attribute(&caps[2], true),
+                attribute_value(&caps[1])
+            )
+        })
         .to_string();
 
     // Links
     result = LINK_RE
-        .replace_all(&result, "<a href=\"$2\">$1</a>")
+        .replace_all(&result, |caps: &regex::Captures| {
+            format!("<a href=\"{}\">{}</a>", url_attribute(&caps[2], false), &caps[1])
+        })
         .to_string();
 
     result
@@ -65,53 +296,104 @@ pub fn render_inline_markdown(text: &str) -> String {
 
 /// Render inline markdown with markers visible (for editing mode)
 pub fn render_inline_markdown_with_markers(text: &str) -> String {
-    let mut result = text.to_string();
+    if text.len() > MAX_INLINE_LENGTH {
+        return html_escape::encode_text(text).to_string();
+    }
+
+    let mut result = escape_tags(text);
 
     // Bold + Italic (must come before individual bold/italic)
-    result = BOLD_ITALIC_RE
-        .replace_all(&result, "<strong><em>***$1***</em></strong>")
-        .to_string();
+    result = replace_emphasis(&result, &BOLD_ITALIC_RE, '*', "<strong><em>***$1***</em></strong>");
 
     // Bold
-    result = BOLD_RE
-        .replace_all(&result, "<strong>**$1**</strong>")
-        .to_string();
-    result = BOLD_UNDERSCORE_RE
-        .replace_all(&result, "<strong>__$1__</strong>")
-        .to_string();
+    result = replace_emphasis(&result, &BOLD_RE, '*', "<strong>**$1**</strong>");
+    result = replace_emphasis(&result, &BOLD_UNDERSCORE_RE, '_', "<strong>__$1__</strong>");
 
     // Italic
-    result = ITALIC_RE
-        .replace_all(&result, "<em>*$1*</em>")
-        .to_string();
-    result = ITALIC_UNDERSCORE_RE
-        .replace_all(&result, "<em>_$1_</em>")
-        .to_string();
+    result = replace_emphasis(&result, &ITALIC_RE, '*', "<em>*$1*</em>");
+    result = replace_emphasis(&result, &ITALIC_UNDERSCORE_RE, '_', "<em>_$1_</em>");
 
     // Strikethrough
-    result = STRIKE_RE
-        .replace_all(&result, "<del>~~$1~~</del>")
-        .to_string();
+    result = replace_emphasis(&result, &STRIKE_RE, '~', "<del>~~$1~~</del>");
+
+    // Highlight
+    result = replace_emphasis(&result, &HIGHLIGHT_RE, '=', "<mark>==$1==</mark>");
 
     // Inline code
     result = CODE_RE
         .replace_all(&result, "<code>`$1`</code>")
         .to_string();
 
+    // Wikilinks (raw [[...]] stays visible)
+    result = render_wikilinks(&result, true);
+
     // Images (must come before links to avoid conflict)
     // In editing mode, show syntax but still render the image inline
     result = IMAGE_RE
-        .replace_all(&result, "<span class=\"image-inline\"><img src=\"$2\" alt=\"$1\" class=\"markdown-image-editing\" /><span class=\"image-syntax\">![$1]($2)</span></span>")
+        .replace_all(&result, |caps: &regex::Captures| {
+            format!(
+                "<span class=\"image-inline\"><img src=\"{}\" alt=\"{}\" class=\"markdown-image-editing\" /><span class=\"image-syntax\">{}</span></span>",
+                url_attribute(&caps[2], true),
+                attribute_value(&caps[1]),
+                &caps[0]
+            )
+        })
         .to_string();
 
     // Links
     result = LINK_RE
-        .replace_all(&result, "<a href=\"$2\">[$1]($2)</a>")
+        .replace_all(&result, |caps: &regex::Captures| {
+            format!("<a href=\"{}\">{}</a>", url_attribute(&caps[2], false), &caps[0])
+        })
         .to_string();
 
     result
 }
 
+/// Remove inline markers, keeping the text they wrap
+///
+/// Links and images keep their text (or alt text) and drop the URL, and
+/// wikilinks keep their display text. Code spans are set aside first so
+/// their contents stay as written, as do emoji shortcodes.
+pub fn strip_inline_markdown(text: &str) -> String {
+    let mut code_spans = Vec::new();
+    let mut result = CODE_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            code_spans.push(caps[1].to_string());
+            format!("\u{0}{}\u{0}", code_spans.len() - 1)
+        })
+        .to_string();
+
+    result = replace_emphasis(&result, &BOLD_ITALIC_RE, '*', "$1");
+    result = replace_emphasis(&result, &BOLD_RE, '*', "$1");
+    result = replace_emphasis(&result, &BOLD_UNDERSCORE_RE, '_', "$1");
+    result = replace_emphasis(&result, &ITALIC_RE, '*', "$1");
+    result = replace_emphasis(&result, &ITALIC_UNDERSCORE_RE, '_', "$1");
+    result = replace_emphasis(&result, &STRIKE_RE, '~', "$1");
+    result = replace_emphasis(&result, &HIGHLIGHT_RE, '=', "$1");
+    result = WIKILINK_RE
+        .replace_all(&result, |caps: &regex::Captures| parse_wikilink(&caps[1]).1.to_string())
+        .to_string();
+    result = IMAGE_RE.replace_all(&result, "$1").to_string();
+    result = LINK_RE.replace_all(&result, "$1").to_string();
+
+    CODE_PLACEHOLDER_RE
+        .replace_all(&result, |caps: &regex::Captures| {
+            caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| code_spans.get(i).cloned())
+                .unwrap_or_else(|| caps[0].to_string())
+        })
+        .to_string()
+}
+
+/// Text of inline markdown as it reads once rendered, without markers
+pub fn visible_text(text: &str) -> String {
+    let rendered = render_inline_markdown(text);
+    html_escape::decode_html_entities(&TAG_RE.replace_all(&rendered, "")).to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -147,10 +429,122 @@ mod tests {
         assert!(result.contains("<a href=\"https://example.com\">this link</a>"));
     }
 
+    #[test]
+    fn test_link_and_image_attributes_are_escaped() {
+        let result = render_inline_markdown("[x](a\" onmouseover=\"alert(1))");
+        assert_eq!(result, "<a href=\"a&quot; onmouseover=&quot;alert(1\">x</a>)");
+        assert!(!result.contains("\" onmouseover"));
+
+        let result = render_inline_markdown("![a\" onerror=\"x](pic.png?a=1&b=2)");
+        assert!(result.contains("src=\"pic.png?a=1&amp;b=2\""));
+        assert!(result.contains("alt=\"a&quot; onerror=&quot;x\""));
+
+        let result = render_inline_markdown_with_markers("[x](it's)");
+        assert!(result.contains("href=\"it&#x27;s\""));
+    }
+
+    #[test]
+    fn test_unsafe_link_schemes_are_dropped() {
+        assert_eq!(render_inline_markdown("[x](javascript:alert(1))"), "<a href=\"#\">x</a>)");
+        assert!(render_inline_markdown("[x]( JavaScript:alert)").contains("href=\"#\""));
+        assert!(render_inline_markdown("[x](data:text/html,hi)").contains("href=\"#\""));
+        assert!(render_inline_markdown("![x](vbscript:run)").contains("src=\"#\""));
+        assert!(render_inline_markdown("![x](data:image/png;base64,AAAA)").contains("src=\"data:image/png;base64,AAAA\""));
+        assert!(render_inline_markdown("[x](https://example.com/a)").contains("href=\"https://example.com/a\""));
+    }
+
     #[test]
     fn test_strikethrough() {
         let text = "This is ~~strikethrough~~";
         let result = render_inline_markdown(text);
         assert!(result.contains("<del>strikethrough</del>"));
     }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(render_inline_markdown("Read ==this== first"), "Read <mark>this</mark> first");
+        assert_eq!(render_inline_markdown_with_markers("==this=="), "<mark>==this==</mark>");
+        assert_eq!(render_inline_markdown("a == b == c"), "a == b == c");
+    }
+
+    #[test]
+    fn test_wikilinks() {
+        let result = render_inline_markdown("See [[Other Note]] and [[projects/alpha|Alpha]]");
+        assert!(result.contains("<a class=\"wikilink\" data-target=\"Other Note\">Other Note</a>"));
+        assert!(result.contains("<a class=\"wikilink\" data-target=\"projects/alpha\">Alpha</a>"));
+
+        let result = render_inline_markdown_with_markers("See [[Target|Display]]");
+        assert!(result.contains("<a class=\"wikilink\" data-target=\"Target\">[[Target|Display]]</a>"));
+    }
+
+    #[test]
+    fn test_visible_text() {
+        assert_eq!(visible_text("**bold** and [a link](x.md) and `code`"), "bold and a link and code");
+        assert_eq!(visible_text("[[Page|Alias]]"), "Alias");
+    }
+
+    #[test]
+    fn test_strip_inline_markdown() {
+        assert_eq!(
+            strip_inline_markdown("***Both*** and **bold** and `a*b*c` and ![logo](logo.png)"),
+            "Both and bold and a*b*c and logo"
+        );
+        assert_eq!(strip_inline_markdown("snake_case_name stays"), "snake_case_name stays");
+    }
+
+    #[test]
+    fn test_emoji_shortcodes() {
+        assert_eq!(render_inline_markdown("Shipped :tada: :white_check_mark:"), "Shipped \u{1F389} \u{2705}");
+        assert_eq!(render_inline_markdown("Unknown :not-an-emoji: stays"), "Unknown :not-an-emoji: stays");
+        assert_eq!(render_inline_markdown_with_markers("Keep :smile:"), "Keep :smile:");
+    }
+
+    #[test]
+    fn test_emoji_skips_code_and_urls() {
+        assert_eq!(render_inline_markdown("`:tada:` :tada:"), "<code>:tada:</code> \u{1F389}");
+        assert_eq!(
+            render_inline_markdown("[docs](https://example.com/:tada:) https://example.com/:smile:"),
+            "<a href=\"https://example.com/:tada:\">docs</a> https://example.com/:smile:"
+        );
+    }
+
+    #[test]
+    fn test_emphasis_needs_flanking_delimiters() {
+        assert_eq!(render_inline_markdown("a * b * c"), "a * b * c");
+        assert_eq!(render_inline_markdown("a * b * c * d"), "a * b * c * d");
+        assert_eq!(render_inline_markdown("**a** **b**"), "<strong>a</strong> <strong>b</strong>");
+        assert_eq!(render_inline_markdown("*a*b*c*"), "<em>a</em>b<em>c</em>");
+        assert_eq!(render_inline_markdown("snake_case_name and _word_"), "snake_case_name and <em>word</em>");
+        assert_eq!(
+            render_inline_markdown_with_markers("***both*** **b**"),
+            "<strong><em>***both***</em></strong> <strong>**b**</strong>"
+        );
+    }
+
+    #[test]
+    fn test_long_line_renders_as_plain_text() {
+        let line = "*a".repeat(25_000) + "<b>";
+        let start = std::time::Instant::now();
+
+        let rendered = render_inline_markdown(&line);
+        let with_markers = render_inline_markdown_with_markers(&line);
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        assert!(rendered.starts_with("*a*a") && rendered.ends_with("&lt;b&gt;"));
+        assert_eq!(rendered, with_markers);
+    }
+
+    #[test]
+    fn test_blocked_emphasis_is_linear() {
+        // Every underscore opens a match running to the blocked one at the end
+        let line = " _b".repeat(6_600) + "b_c";
+        let start = std::time::Instant::now();
+
+        let rendered = render_inline_markdown(&line);
+
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        assert_eq!(rendered, line);
+        assert_eq!(render_inline_markdown("snake_case _em_"), "snake_case <em>em</em>");
+        assert_eq!(render_inline_markdown("_a_b _c_"), "_a_b <em>c</em>");
+    }
 }
@@ -0,0 +1,157 @@
+/**
+ * Markdown linting
+ *
+ * Flags common style issues in a document. Lines inside code blocks are
+ * only checked for an unclosed fence.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use super::block_detection::DocumentBlockMap;
+use super::{HEADER_RE, LIST_RE};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LintWarning {
+    /// 1-based line number
+    pub line: usize,
+    pub rule: String,
+    pub message: String,
+}
+
+impl LintWarning {
+    fn new(line_index: usize, rule: &str, message: String) -> Self {
+        Self {
+            line: line_index + 1,
+            rule: rule.to_string(),
+            message,
+        }
+    }
+}
+
+/// Lint a markdown document, returning warnings in line order
+pub fn lint_markdown(content: &str) -> Vec<LintWarning> {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let mut warnings = Vec::new();
+
+    let mut previous_heading: Option<usize> = None;
+    let mut blank_run = 0;
+    // Marker of the first item in the current unordered list block
+    let mut list_marker: Option<String> = None;
+    let mut open_fence: Option<usize> = None;
+
+    for (i, line) in lines.iter().enumerate() {
+        let state = map.state(i);
+
+        if state.code_start {
+            open_fence = Some(i);
+        }
+        if state.code_end {
+            open_fence = None;
+        }
+        if state.in_code {
+            blank_run = 0;
+            list_marker = None;
+            continue;
+        }
+
+        // Trailing whitespace (two spaces is an intentional line break)
+        let trimmed_end = line.trim_end();
+        let trailing = &line[trimmed_end.len()..];
+        if !trimmed_end.is_empty() && !trailing.is_empty() && trailing != "  " {
+            warnings.push(LintWarning::new(i, "trailing-whitespace", "Trailing whitespace".to_string()));
+        }
+
+        // Consecutive blank lines
+        if trimmed_end.is_empty() {
+            blank_run += 1;
+            if blank_run == 2 {
+                warnings.push(LintWarning::new(i, "multiple-blank-lines", "Multiple consecutive blank lines".to_string()));
+            }
+            list_marker = None;
+            continue;
+        }
+        blank_run = 0;
+
+        // Heading levels should only increase one at a time
+        if let Some(cap) = HEADER_RE.captures(line) {
+            let level = cap[1].len();
+            if let Some(previous) = previous_heading {
+                if level > previous + 1 {
+                    warnings.push(LintWarning::new(
+                        i,
+                        "heading-increment",
+                        format!("Heading level skips from H{} to H{}", previous, level),
+                    ));
+                }
+            }
+            previous_heading = Some(level);
+        }
+
+        // Unordered list markers should be consistent within a list
+        match LIST_RE.captures(line) {
+            Some(cap) if !cap[2].starts_with(|c: char| c.is_ascii_digit()) => {
+                let marker = cap[2].to_string();
+                match &list_marker {
+                    Some(expected) if *expected != marker => {
+                        warnings.push(LintWarning::new(
+                            i,
+                            "list-marker-style",
+                            format!("Inconsistent list marker '{}', expected '{}'", marker, expected),
+                        ));
+                    }
+                    Some(_) => {}
+                    None => list_marker = Some(marker),
+                }
+            }
+            Some(_) => {}
+            None => list_marker = None,
+        }
+    }
+
+    if let Some(start) = open_fence {
+        warnings.push(LintWarning::new(start, "unclosed-code-fence", "Code fence is never closed".to_string()));
+        warnings.sort_by_key(|w| w.line);
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(content: &str) -> Vec<(usize, String)> {
+        lint_markdown(content).into_iter().map(|w| (w.line, w.rule)).collect()
+    }
+
+    #[test]
+    fn test_skipped_heading_level() {
+        assert_eq!(
+            rules("# Title\n### Too deep\n## Fine\n### Fine"),
+            vec![(2, "heading-increment".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_unclosed_fence() {
+        assert_eq!(
+            rules("# Title\n```rust\nfn main() {}   \n"),
+            vec![(2, "unclosed-code-fence".to_string())]
+        );
+        assert!(rules("```\ncode\n```").is_empty());
+    }
+
+    #[test]
+    fn test_whitespace_and_lists() {
+        assert_eq!(
+            rules("text \nbreak  \n\n\n- one\n* two\n- three"),
+            vec![
+                (1, "trailing-whitespace".to_string()),
+                (4, "multiple-blank-lines".to_string()),
+                (6, "list-marker-style".to_string()),
+            ]
+        );
+    }
+}
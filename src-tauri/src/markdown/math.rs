@@ -0,0 +1,41 @@
+/**
+ * Server-side math rendering
+ *
+ * Renders LaTeX to MathML with KaTeX so math shows up without the
+ * frontend's KaTeX, e.g. in exported HTML.
+ */
+
+use katex::{Opts, OutputType};
+
+/// Render a LaTeX expression to MathML
+///
+/// `display` selects display (block) mode instead of inline mode. Invalid
+/// LaTeX is reported as an error rather than rendered in red.
+pub fn render_math(latex: &str, display: bool) -> Result<String, String> {
+    let opts = Opts::builder()
+        .display_mode(display)
+        .output_type(OutputType::Mathml)
+        .throw_on_error(true)
+        .build()
+        .map_err(|e| format!("Invalid math options: {}", e))?;
+
+    katex::render_with_opts(latex, &opts).map_err(|e| format!("Failed to render math: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_simple_expression() {
+        let html = render_math("x^2", true).unwrap();
+        assert!(html.contains("<math"));
+        assert!(html.contains("display=\"block\""));
+        assert!(html.contains("<msup><mi>x</mi><mn>2</mn></msup>"));
+    }
+
+    #[test]
+    fn test_render_invalid_expression() {
+        assert!(render_math("\\frac{1", false).is_err());
+    }
+}
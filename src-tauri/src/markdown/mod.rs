@@ -8,19 +8,62 @@
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
 
 mod block_detection;
+mod folding;
+mod footnotes;
+mod format;
+mod highlight;
+mod html_import;
+mod inline_format;
 mod inline_rendering;
+mod lint;
+mod math;
+mod plain_text;
+mod render_cache;
+mod sanitize;
+mod stats;
+mod table;
+mod tags;
 
-use block_detection::{is_in_code_block, is_in_math_block};
-use inline_rendering::{render_inline_markdown, render_inline_markdown_with_markers};
+use block_detection::{is_in_code_block, is_in_math_block, LineBlockState};
+use footnotes::Footnotes;
+use highlight::highlight_html;
+use inline_rendering::{render_inline_markdown_with_html, render_inline_markdown_with_markers};
+use render_cache::RenderKey;
+pub use block_detection::{BlockContext, DocumentBlockMap};
+pub use inline_rendering::{parse_wikilink, WIKILINK_RE};
+pub(crate) use inline_rendering::render_inline_markdown;
+pub use folding::{get_foldable_regions, FoldRegion};
+pub use format::{
+    format_markdown, next_list_prefix, normalize_heading_levels, reflow_paragraphs, reindent_lines,
+    renumber_ordered_lists, sort_list_block,
+};
+pub use html_import::html_to_markdown;
+pub use inline_format::{toggle_inline_format, InlineFormat};
+pub use lint::{lint_markdown, LintWarning};
+pub use math::render_math;
+pub use plain_text::strip_markdown;
+pub use render_cache::{create_render_cache, RenderCache, RenderCacheHandle};
+pub use sanitize::sanitize_html;
+pub use stats::{document_stats, selection_stats, DocStats};
+pub use table::format_table;
+pub(crate) use table::{is_delimiter_row, split_row};
+pub use tags::{extract_tags, tag_lines, TagCount};
 
 // Pre-compiled regex patterns for block-level elements
-static LANG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^```(\w+)?").unwrap());
-static HR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(---+|\*\*\*+|___+)$").unwrap());
-static HEADER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(#{1,6})\s+(.+)$").unwrap());
-static LIST_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\s*)([-*+]|\d+\.)\s+(.+)$").unwrap());
-static BLOCKQUOTE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^>\s*(.+)$").unwrap());
+static LANG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^`{3,}(\w+)?").unwrap());
+pub(crate) static HR_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(---+|\*\*\*+|___+)$").unwrap());
+pub(crate) static HEADER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(#{1,6})\s+(.+)$").unwrap());
+// Groups: indent, marker, ordered item number (if ordered), text
+pub(crate) static LIST_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\s*)([-*+]|(\d+)[.)])\s+(.+)$").unwrap());
+pub(crate) static BLOCKQUOTE_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^>\s*(.+)$").unwrap());
+
+// Block map of the last batch-rendered document, updated incrementally on the next batch
+static BLOCK_MAP_CACHE: Lazy<Mutex<(Vec<String>, DocumentBlockMap)>> =
+    Lazy::new(|| Mutex::new((Vec::new(), DocumentBlockMap::default())));
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LineRenderResult {
@@ -28,12 +71,126 @@ pub struct LineRenderResult {
     pub is_code_block_boundary: bool,
 }
 
+/// The rendered lines of a window and the block context after it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowRenderResult {
+    pub lines: Vec<LineRenderResult>,
+    /// Context entering the line after the window, for rendering the next window
+    pub context_after: BlockContext,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenderRequest {
     pub line: String,
     pub line_index: usize,
     pub all_lines: Vec<String>,
     pub is_editing: bool,
+    /// Shift applied to heading levels in the rendered output (clamped to 1-6)
+    #[serde(default)]
+    pub heading_offset: i8,
+    /// Render math block lines to MathML here instead of leaving them to KaTeX
+    #[serde(default)]
+    pub server_side_math: bool,
+    /// How horizontal rules are drawn in view mode: "solid", "dashed" or
+    /// "dashes" for a line of box-drawing characters (anything else is solid)
+    #[serde(default)]
+    pub hr_style: String,
+    /// Keep HTML typed into paragraphs (sanitized) instead of escaping it
+    #[serde(default)]
+    pub allow_raw_html: bool,
+    /// Language of the fenced code block the line is in, added as a
+    /// `language-*` class on code lines in view mode
+    #[serde(default)]
+    pub code_language: String,
+    /// In view mode, render only the first of a run of blank lines as a break
+    #[serde(default)]
+    pub collapse_blank_lines: bool,
+    /// Anchor id of a heading line in view mode, instead of the slug of its
+    /// own text; document renderers set it so repeated headings differ
+    #[serde(default)]
+    pub heading_id: Option<String>,
+    /// Unordered list bullets in view mode by nesting level, cycling when
+    /// there are more levels than glyphs ("•" when empty)
+    #[serde(default)]
+    pub bullet_glyphs: Vec<String>,
+    /// The line is an item of a list with blank lines between its items,
+    /// marked `list-loose` in view mode; set by document renderers
+    #[serde(default)]
+    pub loose_list: bool,
+    /// Columns a tab counts for when working out a list item's depth
+    #[serde(default = "default_tab_size")]
+    pub tab_size: u8,
+    /// Nesting depth of a list item, counting the items it's nested under;
+    /// set by document renderers, otherwise estimated from the line's indent
+    #[serde(default)]
+    pub list_depth: Option<usize>,
+    /// Whether the line before this one is blank, for `collapse_blank_lines`;
+    /// set by document renderers, otherwise read from `all_lines`
+    #[serde(default)]
+    pub follows_blank: Option<bool>,
+}
+
+fn default_tab_size() -> u8 {
+    4
+}
+
+impl Default for RenderRequest {
+    fn default() -> Self {
+        Self {
+            line: String::new(),
+            line_index: 0,
+            all_lines: Vec::new(),
+            is_editing: false,
+            heading_offset: 0,
+            server_side_math: false,
+            hr_style: String::new(),
+            allow_raw_html: false,
+            code_language: String::new(),
+            collapse_blank_lines: false,
+            heading_id: None,
+            bullet_glyphs: Vec::new(),
+            loose_list: false,
+            tab_size: default_tab_size(),
+            list_depth: None,
+            follows_blank: None,
+        }
+    }
+}
+
+/// Render settings from the app config, applied to every line of a render
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub server_side_math: bool,
+    pub hr_style: String,
+    pub allow_raw_html: bool,
+    pub collapse_blank_lines: bool,
+    pub bullet_glyphs: Vec<String>,
+    pub tab_size: u8,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            server_side_math: false,
+            hr_style: String::new(),
+            allow_raw_html: false,
+            collapse_blank_lines: false,
+            bullet_glyphs: Vec::new(),
+            tab_size: default_tab_size(),
+        }
+    }
+}
+
+impl RenderRequest {
+    /// Replace the request's config-driven settings with `options`
+    pub fn apply_options(&mut self, options: &RenderOptions) {
+        self.server_side_math = options.server_side_math;
+        self.hr_style = options.hr_style.clone();
+        self.allow_raw_html = options.allow_raw_html;
+        self.collapse_blank_lines = options.collapse_blank_lines;
+        self.bullet_glyphs = options.bullet_glyphs.clone();
+        self.tab_size = options.tab_size;
+    }
 }
 
 /// Escape HTML entities
@@ -41,23 +198,616 @@ fn escape_html(text: &str) -> String {
     html_escape::encode_text(text).to_string()
 }
 
+/// Apply a heading offset to a level, keeping the result within h1-h6
+fn offset_heading_level(level: usize, offset: i8) -> usize {
+    (level as i32 + offset as i32).clamp(1, 6) as usize
+}
+
+/// GitHub-style anchor slug for a heading's text
+///
+/// Lowercases the text, drops punctuation and turns spaces into hyphens.
+pub fn heading_slug(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-' || *c == '_')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+/// Slugs of every heading in a document, in order
+///
+/// Repeated slugs get `-1`, `-2`, ... appended like GitHub does. Headings
+/// inside code blocks are skipped.
+pub fn heading_slugs(content: &str) -> Vec<String> {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    heading_ids(&lines, &map).into_iter().flatten().collect()
+}
+
+/// The slug of each heading line as `heading_slugs` gives it, None for other lines
+fn heading_ids(all_lines: &[String], map: &DocumentBlockMap) -> Vec<Option<String>> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    all_lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if map.state(i).in_code {
+                return None;
+            }
+
+            let cap = HEADER_RE.captures(line)?;
+            let base = heading_slug(&cap[2]);
+            let count = seen.entry(base.clone()).or_insert(0);
+            let slug = if *count == 0 { base } else { format!("{}-{}", base, count) };
+            *count += 1;
+            Some(slug)
+        })
+        .collect()
+}
+
+/// Get the block state of a single line by scanning the document up to it
+fn scan_block_state(line_index: usize, all_lines: &[String]) -> LineBlockState {
+    let (in_code, code_start, code_end) = is_in_code_block(line_index, all_lines);
+    let (in_math, math_start, math_end) = is_in_math_block(line_index, all_lines);
+
+    LineBlockState { in_code, code_start, code_end, in_math, math_start, math_end }
+}
+
+/// Whether a line is a blank line hidden by `collapse_blank_lines`, being
+/// a later line of a run of blank lines outside code and math blocks
+fn is_collapsed_blank(request: &RenderRequest, state: LineBlockState) -> bool {
+    let follows_blank = request.follows_blank.unwrap_or_else(|| {
+        request
+            .line_index
+            .checked_sub(1)
+            .and_then(|i| request.all_lines.get(i))
+            .is_some_and(|previous| previous.trim().is_empty())
+    });
+
+    request.collapse_blank_lines
+        && !request.is_editing
+        && !state.in_code
+        && !state.in_math
+        && request.line.trim().is_empty()
+        && follows_blank
+}
+
+/// Whether the line before each line is blank
+fn follows_blank_lines(all_lines: &[String]) -> Vec<bool> {
+    let mut previous_blank = false;
+    all_lines
+        .iter()
+        .map(|line| std::mem::replace(&mut previous_blank, line.trim().is_empty()))
+        .collect()
+}
+
+/// The bullet for an unordered item at nesting depth `depth`
+fn bullet_glyph(glyphs: &[String], depth: usize) -> String {
+    if glyphs.is_empty() {
+        return "•".to_string();
+    }
+    escape_html(&glyphs[depth % glyphs.len()])
+}
+
+/// Whether each line is an item of a loose list
+///
+/// List items separated only by blank lines count as one list, nested
+/// items included, and the list is loose if any blank line separates them.
+fn loose_list_lines(all_lines: &[String], map: &DocumentBlockMap) -> Vec<bool> {
+    let outside_blocks = |i: usize| !map.state(i).in_code && !map.state(i).in_math;
+    let is_item = |i: usize| outside_blocks(i) && LIST_RE.is_match(&all_lines[i]);
+    let is_blank = |i: usize| outside_blocks(i) && all_lines[i].trim().is_empty();
+
+    let mut loose = vec![false; all_lines.len()];
+    let mut i = 0;
+    while i < all_lines.len() {
+        if !is_item(i) {
+            i += 1;
+            continue;
+        }
+
+        let mut items = vec![i];
+        let mut has_gap = false;
+        while let Some(next) = (i + 1..all_lines.len()).find(|&j| !is_blank(j)).filter(|&j| is_item(j)) {
+            has_gap |= next > i + 1;
+            items.push(next);
+            i = next;
+        }
+
+        if has_gap {
+            for item in items {
+                loose[item] = true;
+            }
+        }
+        i += 1;
+    }
+
+    loose
+}
+
+/// Width of an indent in columns, with each tab counting as `tab_size`
+fn indent_columns(indent: &str, tab_size: u8) -> usize {
+    indent.chars().map(|c| if c == '\t' { tab_size.max(1) as usize } else { 1 }).sum()
+}
+
+/// Nesting depth of each list item line, `None` for other lines
+///
+/// An item is nested under each earlier item of the same list with a
+/// smaller indent, so two-space and four-space nesting both count one level
+/// per step. Blank lines and indented text don't end a list; unindented
+/// text does.
+pub(crate) fn list_depths(all_lines: &[String], state: impl Fn(usize) -> LineBlockState, tab_size: u8) -> Vec<Option<usize>> {
+    // Indents of the open items, outermost first
+    let mut parents: Vec<usize> = Vec::new();
+
+    all_lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let indented = line.starts_with(char::is_whitespace);
+            let line_state = state(i);
+            let cap = LIST_RE.captures(line).filter(|_| !line_state.in_code && !line_state.in_math);
+            let Some(cap) = cap else {
+                if !indented && !line.trim().is_empty() {
+                    parents.clear();
+                }
+                return None;
+            };
+
+            let columns = indent_columns(&cap[1], tab_size);
+            while parents.last().is_some_and(|&parent| parent >= columns) {
+                parents.pop();
+            }
+            let depth = parents.len();
+            parents.push(columns);
+            Some(depth)
+        })
+        .collect()
+}
+
+/// Nesting depth of a list item without the rest of its list, one level per
+/// `tab_size` columns of indent, rounding up
+fn estimated_list_depth(indent: &str, tab_size: u8) -> usize {
+    indent_columns(indent, tab_size).div_ceil(tab_size.max(1) as usize)
+}
+
+/// Language of the enclosing code block for each line, "" outside code blocks
+fn code_languages<'a>(all_lines: &'a [String], map: &DocumentBlockMap) -> Vec<&'a str> {
+    let mut language = "";
+    all_lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let state = map.state(i);
+            if state.code_start {
+                language = fence_language(line);
+            }
+            if state.in_code { language } else { "" }
+        })
+        .collect()
+}
+
+/// Get the block map for a document, reusing the previous batch's map
+///
+/// Lines shared with the previously rendered document at the start and end
+/// are treated as unchanged, so only the edited region is rescanned.
+fn cached_block_map(all_lines: &[String]) -> DocumentBlockMap {
+    let mut cache = match BLOCK_MAP_CACHE.lock() {
+        Ok(cache) => cache,
+        Err(_) => return DocumentBlockMap::build(all_lines),
+    };
+    let (cached_lines, map) = &mut *cache;
+
+    let prefix = cached_lines
+        .iter()
+        .zip(all_lines)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let max_suffix = cached_lines.len().min(all_lines.len()) - prefix;
+    let suffix = cached_lines
+        .iter()
+        .rev()
+        .zip(all_lines.iter().rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    map.update(all_lines, prefix..all_lines.len() - suffix);
+    *cached_lines = all_lines.to_vec();
+
+    map.clone()
+}
+
+/// Hash of a document's lines, so requests can be matched to a batch's
+/// document without comparing every line
+fn document_hash(all_lines: &[String]) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    all_lines.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Block state of each line in a batch, sharing one block map between lines of the same document
+///
+/// The batch's document is the first request's `all_lines`; later requests
+/// may leave theirs empty to share it. Lines of that document inside a code
+/// block also get the block's language, headings in view mode their anchor
+/// id and list items their depth, unless the request already gives one.
+fn batch_block_states(requests: &mut [RenderRequest]) -> Vec<LineBlockState> {
+    let Some(first) = requests.first() else {
+        return Vec::new();
+    };
+    let all_lines = first.all_lines.clone();
+    let hash = document_hash(&all_lines);
+    let map = cached_block_map(&all_lines);
+    let mut languages = None;
+    let mut ids = None;
+    let mut depths = None;
+    let mut loose = None;
+
+    requests
+        .iter_mut()
+        .map(|request| {
+            let shared = request.all_lines.is_empty()
+                || (request.all_lines.len() == all_lines.len() && document_hash(&request.all_lines) == hash);
+            if !shared {
+                return scan_block_state(request.line_index, &request.all_lines);
+            }
+            if request.follows_blank.is_none() {
+                let previous = request.line_index.checked_sub(1).and_then(|i| all_lines.get(i));
+                request.follows_blank = Some(previous.is_some_and(|line| line.trim().is_empty()));
+            }
+            let state = map.state(request.line_index);
+            if state.in_code && request.code_language.is_empty() {
+                let languages = languages.get_or_insert_with(|| code_languages(&all_lines, &map));
+                request.code_language = languages[request.line_index].to_string();
+            }
+            if !request.is_editing && request.heading_id.is_none() && HEADER_RE.is_match(&request.line) {
+                let ids = ids.get_or_insert_with(|| heading_ids(&all_lines, &map));
+                request.heading_id = ids[request.line_index].clone();
+            }
+            if !request.is_editing && request.list_depth.is_none() && LIST_RE.is_match(&request.line) {
+                let depths = depths.get_or_insert_with(|| list_depths(&all_lines, |i| map.state(i), request.tab_size));
+                request.list_depth = depths[request.line_index];
+            }
+            if !request.loose_list && LIST_RE.is_match(&request.line) {
+                let loose = loose.get_or_insert_with(|| loose_list_lines(&all_lines, &map));
+                request.loose_list = loose[request.line_index];
+            }
+            state
+        })
+        .collect()
+}
+
+/// Render lines whose block state is already known
+fn render_with_states(lines: Vec<(RenderRequest, LineBlockState)>) -> Vec<LineRenderResult> {
+    use rayon::prelude::*;
+
+    let render = |(request, state)| render_markdown_line_with_state(request, state);
+
+    // Use parallel iterator for large batches (>50 lines)
+    if lines.len() > 50 {
+        lines.into_par_iter().map(render).collect()
+    } else {
+        // For small batches, sequential is faster (no thread overhead)
+        lines.into_iter().map(render).collect()
+    }
+}
+
+/// Render a batch of lines, reusing cached results for lines already rendered
+/// with the same text, block context and options
+pub fn render_markdown_lines(mut requests: Vec<RenderRequest>, cache: &Mutex<RenderCache>) -> Vec<LineRenderResult> {
+    let states = batch_block_states(&mut requests);
+    let keys: Vec<RenderKey> = requests
+        .iter()
+        .zip(&states)
+        .map(|(request, state)| RenderKey::new(request, *state))
+        .collect();
+
+    let mut results: Vec<Option<LineRenderResult>> = match cache.lock() {
+        Ok(mut cache) => keys.iter().map(|key| cache.get(key)).collect(),
+        Err(_) => vec![None; keys.len()],
+    };
+
+    let (missed, lines): (Vec<usize>, Vec<(RenderRequest, LineBlockState)>) = requests
+        .into_iter()
+        .zip(states)
+        .enumerate()
+        .filter(|(i, _)| results[*i].is_none())
+        .unzip();
+    let rendered = render_with_states(lines);
+
+    if let Ok(mut cache) = cache.lock() {
+        for (&i, result) in missed.iter().zip(&rendered) {
+            cache.insert(keys[i].clone(), result.clone());
+        }
+    }
+    for (i, result) in missed.into_iter().zip(rendered) {
+        results[i] = Some(result);
+    }
+
+    results.into_iter().flatten().collect()
+}
+
+/// Render only lines `start..=end` of a document
+///
+/// Block state comes from the whole document, so a range starting inside a
+/// code or math block renders with that context. `end` is clamped to the
+/// last line; an empty or inverted range renders nothing.
+pub fn render_markdown_range(
+    all_lines: Vec<String>,
+    start: usize,
+    end: usize,
+    is_editing: bool,
+    options: &RenderOptions,
+) -> Vec<LineRenderResult> {
+    let end = end.min(all_lines.len().saturating_sub(1));
+    if all_lines.is_empty() || start > end {
+        return Vec::new();
+    }
+
+    let map = cached_block_map(&all_lines);
+    let languages = code_languages(&all_lines, &map);
+    let mut ids = heading_ids(&all_lines, &map);
+    let loose = loose_list_lines(&all_lines, &map);
+    let depths = list_depths(&all_lines, |i| map.state(i), options.tab_size);
+    let follows_blank = follows_blank_lines(&all_lines);
+    (start..=end)
+        .map(|line_index| {
+            let state = map.state(line_index);
+            let request = RenderRequest {
+                line: all_lines[line_index].clone(),
+                line_index,
+                // The block state is already known, so the document isn't needed again
+                all_lines: Vec::new(),
+                is_editing,
+                heading_offset: 0,
+                server_side_math: options.server_side_math,
+                hr_style: options.hr_style.clone(),
+                allow_raw_html: options.allow_raw_html,
+                code_language: languages[line_index].to_string(),
+                collapse_blank_lines: options.collapse_blank_lines,
+                heading_id: ids[line_index].take(),
+                bullet_glyphs: options.bullet_glyphs.clone(),
+                loose_list: loose[line_index],
+                tab_size: options.tab_size,
+                list_depth: depths[line_index],
+                follows_blank: Some(follows_blank[line_index]),
+            };
+            render_markdown_line_with_state(request, state)
+        })
+        .collect()
+}
+
+/// Render a window of a document without the rest of its lines
+///
+/// `window_start` is the index of the window's first line and `context`
+/// the code/math block context entering it: the default for a window at
+/// the start of the document, or the `context_after` of the window ending
+/// where this one starts. Lines past `full_line_count` are not rendered.
+/// Code lines of a block opened before the window have no language class,
+/// and list item depths are estimated from their indent.
+pub fn render_markdown_window(
+    window_lines: Vec<String>,
+    window_start: usize,
+    full_line_count: usize,
+    context: BlockContext,
+    is_editing: bool,
+    options: &RenderOptions,
+) -> WindowRenderResult {
+    let mut window_lines = window_lines;
+    window_lines.truncate(full_line_count.saturating_sub(window_start));
+    let (states, context_after) = context.scan(&window_lines);
+    let follows_blank = follows_blank_lines(&window_lines);
+    let mut code_language = String::new();
+
+    let lines = window_lines
+        .into_iter()
+        .zip(states)
+        .enumerate()
+        .map(|(i, (line, state))| {
+            if state.code_start {
+                code_language = fence_language(&line).to_string();
+            }
+            let request = RenderRequest {
+                line,
+                line_index: window_start + i,
+                all_lines: Vec::new(),
+                is_editing,
+                heading_offset: 0,
+                server_side_math: options.server_side_math,
+                hr_style: options.hr_style.clone(),
+                allow_raw_html: options.allow_raw_html,
+                code_language: if state.in_code { code_language.clone() } else { String::new() },
+                collapse_blank_lines: options.collapse_blank_lines,
+                heading_id: None,
+                bullet_glyphs: options.bullet_glyphs.clone(),
+                loose_list: false,
+                tab_size: options.tab_size,
+                list_depth: None,
+                follows_blank: Some(follows_blank[i]),
+            };
+            (request, state)
+        })
+        .collect();
+
+    WindowRenderResult { lines: render_with_states(lines), context_after }
+}
+
+/// Render a whole document in view mode, one `editor-line` div per line
+///
+/// Mermaid code blocks become a single `mermaid` div holding the diagram
+/// source, and each math block becomes one `math-block` div with its LaTeX
+/// joined into `data-latex`, so multi-line environments render as a single
+/// expression. With `server_side_math` set in `options`, math blocks are
+/// rendered to MathML.
+/// Footnote definitions are moved to a footnotes section at the end, and a
+/// term followed by `: definition` lines becomes a definition list.
+pub fn render_document(content: &str, options: &RenderOptions) -> String {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let mut footnotes = Footnotes::collect(&lines, &map);
+    let languages = code_languages(&lines, &map);
+    let mut ids = heading_ids(&lines, &map);
+    let loose = loose_list_lines(&lines, &map);
+    let depths = list_depths(&lines, |i| map.state(i), options.tab_size);
+    let follows_blank = follows_blank_lines(&lines);
+    let mut output = String::new();
+    let mut line_index = 0;
+
+    while line_index < lines.len() {
+        let line = &lines[line_index];
+        let state = map.state(line_index);
+
+        if state.code_start && fence_language(line) == "mermaid" {
+            let end = (line_index + 1..lines.len())
+                .find(|&i| map.state(i).code_end)
+                .unwrap_or(lines.len());
+            // Mermaid reads the element's text content, which decodes the escaping
+            output.push_str(&format!(
+                "<div class=\"editor-line\"><div class=\"mermaid\">{}</div></div>\n",
+                escape_html(&lines[line_index + 1..end].join("\n"))
+            ));
+            line_index = end + 1;
+            continue;
+        }
+
+        // `$$` lines inside code fences are code, and mermaid blocks were consumed above
+        if state.math_start && !state.in_code {
+            let end = (line_index + 1..lines.len())
+                .find(|&i| map.state(i).math_end && !map.state(i).in_code)
+                .unwrap_or(lines.len());
+            let latex = lines[line_index + 1..end].join("\n");
+            // Without server-side math, the frontend renders the block from its source
+            let rendered = if options.server_side_math { render_math(&latex, true).ok() } else { None };
+            let (class, body) = match rendered {
+                Some(math) => ("math-block math-rendered", math),
+                None => ("math-block", escape_html(&latex)),
+            };
+            output.push_str(&format!(
+                "<div class=\"editor-line\"><div class=\"{}\" data-latex=\"{}\">{}</div></div>\n",
+                class,
+                html_escape::encode_double_quoted_attribute(&latex),
+                body
+            ));
+            line_index = end + 1;
+            continue;
+        }
+
+        let in_block = state.in_code || state.in_math;
+        if !in_block && Footnotes::is_definition(line) {
+            line_index += 1;
+            continue;
+        }
+
+        let is_definition = |i: usize| {
+            lines.get(i).is_some_and(|l| l.starts_with(": ")) && !map.state(i).in_code && !map.state(i).in_math
+        };
+        if !in_block && !line.trim().is_empty() && is_definition(line_index + 1) {
+            let mut html = format!("<dt>{}</dt>", footnotes.link_references(line.trim(), render_inline_markdown));
+            line_index += 1;
+            while is_definition(line_index) {
+                let definition = footnotes.link_references(lines[line_index][2..].trim(), render_inline_markdown);
+                html.push_str(&format!("<dd>{}</dd>", definition));
+                line_index += 1;
+            }
+            output.push_str(&format!("<div class=\"editor-line\"><dl>{}</dl></div>\n", html));
+            continue;
+        }
+
+        // Block state comes from the map, so the lines don't need to be copied into each request
+        let request = RenderRequest {
+            line: line.clone(),
+            line_index,
+            all_lines: Vec::new(),
+            is_editing: false,
+            heading_offset: 0,
+            server_side_math: options.server_side_math,
+            hr_style: options.hr_style.clone(),
+            allow_raw_html: options.allow_raw_html,
+            code_language: languages[line_index].to_string(),
+            collapse_blank_lines: options.collapse_blank_lines,
+            heading_id: ids[line_index].take(),
+            bullet_glyphs: options.bullet_glyphs.clone(),
+            loose_list: loose[line_index],
+            tab_size: options.tab_size,
+            list_depth: depths[line_index],
+            follows_blank: Some(follows_blank[line_index]),
+        };
+        // References are linked around rendering so their markup isn't escaped
+        let render = |line: &str| render_markdown_line_with_state(RenderRequest { line: line.to_string(), ..request }, state).html;
+        let html = if in_block { render(line) } else { footnotes.link_references(line, render) };
+        output.push_str(&format!("<div class=\"editor-line\">{}</div>\n", html));
+        line_index += 1;
+    }
+
+    output.push_str(&footnotes.render_section());
+    output
+}
+
+/// View mode HTML for a horizontal rule in the given style
+///
+/// The rule is colored with the theme's `hr-color` variable.
+fn render_hr(style: &str) -> String {
+    match style {
+        "dashed" => "<hr class=\"hr hr-dashed\">".to_string(),
+        "dashes" => "<span class=\"hr\">───────────────────────────────────────</span>".to_string(),
+        _ => "<hr class=\"hr hr-solid\">".to_string(),
+    }
+}
+
+/// Language named on an opening code fence, or "" if there is none
+fn fence_language(line: &str) -> &str {
+    LANG_RE
+        .captures(line.trim())
+        .and_then(|cap| cap.get(1))
+        .map(|m| m.as_str())
+        .unwrap_or("")
+}
+
 /// Render a single markdown line to HTML
-pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
+pub fn render_markdown_line(mut request: RenderRequest) -> LineRenderResult {
+    let state = scan_block_state(request.line_index, &request.all_lines);
+    if state.in_code && request.code_language.is_empty() {
+        let map = DocumentBlockMap::build(&request.all_lines);
+        request.code_language = code_languages(&request.all_lines, &map)[request.line_index].to_string();
+    }
+    let in_document = request.all_lines.get(request.line_index) == Some(&request.line);
+    if in_document && !request.is_editing && request.list_depth.is_none() && LIST_RE.is_match(&request.line) {
+        let map = DocumentBlockMap::build(&request.all_lines);
+        let depths = list_depths(&request.all_lines, |i| map.state(i), request.tab_size);
+        request.list_depth = depths[request.line_index];
+    }
+    render_markdown_line_with_state(request, state)
+}
+
+/// Render a single line with character ranges wrapped in search highlight spans
+///
+/// Ranges are 0-based character columns of `request.line`, end exclusive,
+/// and overlapping ranges are merged.
+pub fn render_markdown_line_with_highlights(request: RenderRequest, highlights: Vec<(usize, usize)>) -> LineRenderResult {
+    let line = request.line.clone();
+    let result = render_markdown_line(request);
+    LineRenderResult {
+        html: highlight_html(&result.html, &line, highlights),
+        ..result
+    }
+}
+
+/// Render a single markdown line to HTML given its already known block state
+fn render_markdown_line_with_state(request: RenderRequest, block_state: LineBlockState) -> LineRenderResult {
     let line = &request.line;
-    let line_index = request.line_index;
-    let all_lines = &request.all_lines;
     let is_editing = request.is_editing;
 
     // Check if this line is part of a code block
-    let (in_block, is_start, is_end) = is_in_code_block(line_index, all_lines);
+    let (in_block, is_start, is_end) = (block_state.in_code, block_state.code_start, block_state.code_end);
 
     if is_start {
-        // Starting ``` line - extract language if present
-        let lang = LANG_RE
-            .captures(line.trim())
-            .and_then(|cap| cap.get(1))
-            .map(|m| m.as_str())
-            .unwrap_or("");
+        // Opening fence line - extract language if present
+        let lang = fence_language(line);
 
         if is_editing {
             return LineRenderResult {
@@ -77,7 +827,7 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
     }
 
     if is_end {
-        // Ending ``` line
+        // Closing fence line
         if is_editing {
             return LineRenderResult {
                 html: format!("<span class=\"code-block-end\">{}</span>", escape_html(line.trim())),
@@ -99,15 +849,19 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
                 is_code_block_boundary: false,
             };
         } else {
+            let class = match request.code_language.as_str() {
+                "" => "code-block-line".to_string(),
+                lang => format!("code-block-line language-{}", lang),
+            };
             return LineRenderResult {
-                html: format!("<code class=\"code-block-line\">{}</code>", escape_html(line)),
+                html: format!("<code class=\"{}\">{}</code>", class, escape_html(line)),
                 is_code_block_boundary: false,
             };
         }
     }
 
     // Check if this line is part of a math block
-    let (in_math_block, is_math_start, is_math_end) = is_in_math_block(line_index, all_lines);
+    let (in_math_block, is_math_start, is_math_end) = (block_state.in_math, block_state.math_start, block_state.math_end);
 
     if is_math_start {
         // Starting $$ line
@@ -147,17 +901,23 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
                 is_code_block_boundary: false,
             };
         } else {
+            // Lines that KaTeX can't render on their own fall back to the frontend
+            let rendered = if request.server_side_math { render_math(line, true).ok() } else { None };
             return LineRenderResult {
-                html: format!("<span class=\"math-block-line\">{}</span>", escape_html(line)),
+                html: match rendered {
+                    Some(math) => format!("<span class=\"math-block-line math-rendered\">{}</span>", math),
+                    None => format!("<span class=\"math-block-line\">{}</span>", escape_html(line)),
+                },
                 is_code_block_boundary: false,
             };
         }
     }
 
-    // Empty line
+    // Empty line, with the rest of a run dropped when collapsing blank lines
     if line.trim().is_empty() {
+        let collapsed = is_collapsed_blank(&request, block_state);
         return LineRenderResult {
-            html: "<br>".to_string(),
+            html: if collapsed { String::new() } else { "<br>".to_string() },
             is_code_block_boundary: false,
         };
     }
@@ -171,7 +931,7 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
             };
         } else {
             return LineRenderResult {
-                html: "<span class=\"hr\">───────────────────────────────────────</span>".to_string(),
+                html: render_hr(&request.hr_style),
                 is_code_block_boundary: false,
             };
         }
@@ -191,8 +951,11 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
             };
         } else {
             let processed_text = render_inline_markdown(text);
+            let level = offset_heading_level(level, request.heading_offset);
+            let id = request.heading_id.clone().unwrap_or_else(|| heading_slug(text));
+            let id_attribute = if id.is_empty() { String::new() } else { format!(" id=\"{}\"", id) };
             return LineRenderResult {
-                html: format!("<span class=\"heading h{}\">{}</span>", level, processed_text),
+                html: format!("<span class=\"heading h{}\"{}>{}</span>", level, id_attribute, processed_text),
                 is_code_block_boundary: false,
             };
         }
@@ -203,8 +966,9 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
         let indent_spaces = cap.get(1).unwrap().as_str();
         let indent = indent_spaces.len();
         let marker = cap.get(2).unwrap().as_str();
-        let text = cap.get(3).unwrap().as_str();
-        let is_ordered = marker.chars().next().unwrap().is_numeric();
+        let number = cap.get(3).map(|m| m.as_str());
+        let text = cap.get(4).unwrap().as_str();
+        let is_ordered = number.is_some();
         let marker_class = if is_ordered { "ordered" } else { "unordered" };
 
         if is_editing {
@@ -218,14 +982,26 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
             };
         } else {
             let processed_text = render_inline_markdown(text);
-            let display_marker = if is_ordered { marker } else { "•" };
+            let depth = request.list_depth.unwrap_or_else(|| estimated_list_depth(indent_spaces, request.tab_size));
+            let display_marker = match number {
+                Some(_) => marker.to_string(),
+                None => bullet_glyph(&request.bullet_glyphs, depth),
+            };
+            // An ordered item's number, which for the first item is the list's start value
+            let start = number.map(|n| format!(" data-start=\"{}\"", n)).unwrap_or_default();
+            let loose_class = if request.loose_list { " list-loose" } else { "" };
             return LineRenderResult {
                 html: format!(
-                    "<span class=\"list-item\" style=\"padding-left: {}px\">\
+                    "<span class=\"list-item{}\" style=\"padding-left: {}px\" \
+                    data-depth=\"{}\" data-list-type=\"{}\"{}>\
                     <span class=\"list-marker {}\">{}</span>\
                     {}\
                     </span>",
+                    loose_class,
                     indent * 20,
+                    depth,
+                    marker_class,
+                    start,
                     marker_class,
                     display_marker,
                     processed_text
@@ -260,6 +1036,11 @@ pub fn render_markdown_line(request: RenderRequest) -> LineRenderResult {
             html: render_inline_markdown_with_markers(line),
             is_code_block_boundary: false,
         }
+    } else if request.allow_raw_html {
+        LineRenderResult {
+            html: render_inline_markdown_with_html(line),
+            is_code_block_boundary: false,
+        }
     } else {
         LineRenderResult {
             html: render_inline_markdown(line),
@@ -279,6 +1060,7 @@ mod tests {
             line_index: 0,
             all_lines: vec!["# Hello World".to_string()],
             is_editing: false,
+            ..Default::default()
         };
         let result = render_markdown_line(request);
         assert!(result.html.contains("heading h1"));
@@ -298,6 +1080,7 @@ mod tests {
             line_index: 0,
             all_lines: lines.clone(),
             is_editing: false,
+            ..Default::default()
         });
         assert!(result0.html.contains("code-block-start"));
 
@@ -306,8 +1089,597 @@ mod tests {
             line_index: 1,
             all_lines: lines.clone(),
             is_editing: false,
+            ..Default::default()
         });
         assert!(result1.html.contains("code-block-line"));
         assert!(result1.html.contains("fn main() {}"));
     }
+
+    #[test]
+    fn test_heading_offset_shifts_level() {
+        let request = RenderRequest {
+            line: "# Embedded".to_string(),
+            line_index: 0,
+            all_lines: vec!["# Embedded".to_string()],
+            is_editing: false,
+            heading_offset: 1,
+            ..Default::default()
+        };
+        let result = render_markdown_line(request);
+        assert!(result.html.contains("heading h2"));
+    }
+
+    #[test]
+    fn test_heading_offset_clamps_at_six() {
+        let request = RenderRequest {
+            line: "##### Deep".to_string(),
+            line_index: 0,
+            all_lines: vec!["##### Deep".to_string()],
+            is_editing: false,
+            heading_offset: 3,
+            ..Default::default()
+        };
+        let result = render_markdown_line(request);
+        assert!(result.html.contains("heading h6"));
+    }
+
+    #[test]
+    fn test_four_backtick_fence_language() {
+        let lines = vec![
+            "````markdown".to_string(),
+            "```".to_string(),
+            "````".to_string(),
+        ];
+
+        let result0 = render_markdown_line(RenderRequest {
+            line: lines[0].clone(),
+            line_index: 0,
+            all_lines: lines.clone(),
+            is_editing: false,
+            ..Default::default()
+        });
+        assert!(result0.html.contains("data-lang=\"markdown\""));
+
+        let result1 = render_markdown_line(RenderRequest {
+            line: lines[1].clone(),
+            line_index: 1,
+            all_lines: lines.clone(),
+            is_editing: false,
+            ..Default::default()
+        });
+        assert!(result1.html.contains("code-block-line"));
+        assert!(result1.html.contains("```"));
+    }
+
+    #[test]
+    fn test_batch_matches_single_line_rendering() {
+        let lines: Vec<String> = vec!["# Title", "```", "code", "```", "$$", "x", "$$", "*text*"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let requests: Vec<RenderRequest> = (0..lines.len())
+            .map(|i| RenderRequest {
+                line: lines[i].clone(),
+                line_index: i,
+                all_lines: lines.clone(),
+                is_editing: false,
+                ..Default::default()
+            })
+            .collect();
+
+        let batch = render_markdown_lines(requests.clone(), &Mutex::new(RenderCache::new(100)));
+        let single: Vec<_> = requests.into_iter().map(render_markdown_line).collect();
+        for (b, s) in batch.iter().zip(&single) {
+            assert_eq!(b.html, s.html);
+        }
+    }
+
+    #[test]
+    fn test_code_lines_carry_language() {
+        let content = "```python\nprint(1)\n```\n```\nplain\n```";
+        let lines: Vec<String> = content.lines().map(String::from).collect();
+
+        let document = render_document(content, &RenderOptions::default());
+        let single = render_markdown_line(RenderRequest {
+            line: lines[1].clone(),
+            line_index: 1,
+            all_lines: lines.clone(),
+            ..paragraph("")
+        });
+        let range = render_markdown_range(lines, 1, 4, false, &RenderOptions::default());
+
+        assert!(document.contains("<code class=\"code-block-line language-python\">print(1)</code>"));
+        assert!(document.contains("<code class=\"code-block-line\">plain</code>"));
+        assert_eq!(single.html, "<code class=\"code-block-line language-python\">print(1)</code>");
+        assert_eq!(range[0].html, single.html);
+        assert_eq!(range[3].html, "<code class=\"code-block-line\">plain</code>");
+    }
+
+    #[test]
+    fn test_heading_slugs() {
+        assert_eq!(heading_slug("Getting Started!"), "getting-started");
+
+        let slugs = heading_slugs("# Intro\n## Setup\n```\n# not a heading\n```\n## Setup");
+        assert_eq!(slugs, vec!["intro", "setup", "setup-1"]);
+    }
+
+    #[test]
+    fn test_heading_ids_in_rendered_output() {
+        let content = "# Intro\n## Setup!\n```\n## Setup!\n```\n## Setup!";
+        let lines: Vec<String> = content.lines().map(String::from).collect();
+
+        let single = render_markdown_line(RenderRequest { line: lines[1].clone(), ..paragraph("") });
+        let document = render_document(content, &RenderOptions::default());
+        let range = render_markdown_range(lines, 5, 5, false, &RenderOptions::default());
+
+        assert_eq!(single.html, "<span class=\"heading h2\" id=\"setup\">Setup!</span>");
+        assert!(document.contains("<span class=\"heading h1\" id=\"intro\">Intro</span>"));
+        assert!(document.contains("<span class=\"heading h2\" id=\"setup-1\">Setup!</span>"));
+        assert_eq!(document.matches("id=\"setup\"").count(), 1);
+        assert_eq!(range[0].html, "<span class=\"heading h2\" id=\"setup-1\">Setup!</span>");
+    }
+
+    #[test]
+    fn test_server_side_math_block() {
+        let lines: Vec<String> = ["$$", "x^2", "\\frac{1", "$$"].iter().map(|l| l.to_string()).collect();
+        let render = |line_index: usize| {
+            render_markdown_line(RenderRequest {
+                line: lines[line_index].clone(),
+                line_index,
+                all_lines: lines.clone(),
+                is_editing: false,
+                server_side_math: true,
+                ..Default::default()
+            })
+        };
+
+        let rendered = render(1).html;
+        assert!(rendered.starts_with("<span class=\"math-block-line math-rendered\">"));
+        assert!(rendered.contains("<math"));
+
+        // Invalid LaTeX falls back to the frontend's KaTeX
+        assert_eq!(render(2).html, "<span class=\"math-block-line\">\\frac{1</span>");
+    }
+
+    #[test]
+    fn test_math_block_in_document() {
+        let html = render_document("$$\n\\begin{aligned} a &= b \\\\\nc &< d \\end{aligned}\n$$\nafter", &RenderOptions::default());
+
+        assert_eq!(html.matches("class=\"math-block\"").count(), 1);
+        assert!(html.contains(
+            "<div class=\"math-block\" data-latex=\"\\begin{aligned} a &amp;= b \\\\\nc &amp;&lt; d \\end{aligned}\">"
+        ));
+        assert!(!html.contains("math-block-line"));
+        assert!(html.contains("after"));
+    }
+
+    #[test]
+    fn test_math_delimiters_in_code_block_stay_code() {
+        let html = render_document("```latex\n$$\nx^2\n$$\n```\n```mermaid\ngraph TD\n$$\n```", &RenderOptions::default());
+
+        assert!(!html.contains("math-block"));
+        assert_eq!(html.matches("<code class=\"code-block-line language-latex\">").count(), 3);
+        assert!(html.contains("x^2"));
+        assert!(html.contains("<div class=\"mermaid\">graph TD\n$$</div>"));
+    }
+
+    #[test]
+    fn test_mermaid_source_is_escaped() {
+        let html = render_document("```mermaid\ngraph TD\n  A[\"<img src=x onerror=alert(1)>\"]\n```", &RenderOptions::default());
+
+        assert!(html.contains("A[\"&lt;img src=x onerror=alert(1)&gt;\"]"));
+        assert!(!html.contains("<img"));
+    }
+
+    #[test]
+    fn test_mermaid_block_in_document() {
+        let content = "```mermaid\ngraph TD\n  A-->B\n```\n```rust\nlet a = b<c;\n```";
+        let html = render_document(content, &RenderOptions::default());
+
+        assert!(html.contains("<div class=\"mermaid\">graph TD\n  A--&gt;B</div>"));
+        assert_eq!(html.matches("class=\"mermaid\"").count(), 1);
+        assert!(html.contains("<code class=\"code-block-line language-rust\">let a = b&lt;c;</code>"));
+        assert!(html.contains("data-lang=\"rust\""));
+    }
+
+    #[test]
+    fn test_footnotes_in_document() {
+        let html = render_document("Claim[^1] and [^missing]\n\n[^1]: Source", &RenderOptions::default());
+
+        assert!(html.contains("Claim<sup class=\"footnote-ref\"><a href=\"#fn-1\" id=\"fnref-1\">1</a></sup> and [^missing]"));
+        assert!(html.ends_with("<section class=\"footnotes\"><ol><li id=\"fn-1\">Source <a href=\"#fnref-1\" class=\"footnote-backref\">\u{21A9}</a></li></ol></section>\n"));
+        assert!(!html.contains("[^1]:"));
+    }
+
+    #[test]
+    fn test_footnote_references_in_code_and_urls_stay_literal() {
+        let html = render_document("Run `[^1]` or see [docs](https://example.com/[^1])\n\n[^1]: Source", &RenderOptions::default());
+
+        assert!(html.contains("<code>[^1]</code>"));
+        assert!(html.contains("href=\"https://example.com/[^1]\""));
+        assert!(!html.contains("footnote-ref"));
+    }
+
+    #[test]
+    fn test_tight_and_loose_lists_in_document() {
+        let html = render_document("- tight\n- list\n\nText\n\n1. loose\n\n2. list\n   - nested", &RenderOptions::default());
+
+        assert!(html.contains("<span class=\"list-item\" style=\"padding-left: 0px\""));
+        assert_eq!(html.matches("class=\"list-item\"").count(), 2);
+        assert_eq!(html.matches("class=\"list-item list-loose\"").count(), 3);
+    }
+
+    #[test]
+    fn test_loose_lists_in_batch() {
+        let lines: Vec<String> = ["- tight", "- list", "", "Text", "", "1. loose", "", "2. list"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let requests: Vec<RenderRequest> = (0..lines.len())
+            .map(|line_index| RenderRequest {
+                line: lines[line_index].clone(),
+                line_index,
+                all_lines: lines.clone(),
+                ..Default::default()
+            })
+            .collect();
+
+        let html: String = render_markdown_lines(requests, &Mutex::new(RenderCache::new(100)))
+            .into_iter()
+            .map(|r| r.html)
+            .collect();
+        assert_eq!(html.matches("class=\"list-item\"").count(), 2);
+        assert_eq!(html.matches("class=\"list-item list-loose\"").count(), 2);
+    }
+
+    #[test]
+    fn test_definition_list_in_document() {
+        let html = render_document("Intro\n\n**Term**\n: The *definition*", &RenderOptions::default());
+        assert!(html.contains("<div class=\"editor-line\"><dl><dt><strong>Term</strong></dt><dd>The <em>definition</em></dd></dl></div>"));
+
+        let html = render_document("Bank\n: Edge of a river\n: A financial institution\nAfter", &RenderOptions::default());
+        assert!(html.starts_with("<div class=\"editor-line\"><dl><dt>Bank</dt><dd>Edge of a river</dd><dd>A financial institution</dd></dl></div>\n"));
+        assert!(html.ends_with("<div class=\"editor-line\">After</div>\n"));
+    }
+
+    #[test]
+    fn test_hr_style() {
+        let render = |is_editing: bool, hr_style: &str| {
+            render_markdown_line(RenderRequest {
+                line: "---".to_string(),
+                line_index: 0,
+                all_lines: vec!["---".to_string()],
+                is_editing,
+                hr_style: hr_style.to_string(),
+                ..Default::default()
+            })
+            .html
+        };
+
+        assert_eq!(render(false, ""), "<hr class=\"hr hr-solid\">");
+        assert_eq!(render(false, "solid"), "<hr class=\"hr hr-solid\">");
+        assert_eq!(render(false, "dashed"), "<hr class=\"hr hr-dashed\">");
+        assert!(render(false, "dashes").starts_with("<span class=\"hr\">───"));
+        assert_eq!(render(true, "dashed"), "<span class=\"hr\">---</span>");
+    }
+
+    #[test]
+    fn test_ordered_list_start_and_delimiter() {
+        let render = |line: &str, is_editing: bool| {
+            render_markdown_line(RenderRequest {
+                line: line.to_string(),
+                line_index: 0,
+                all_lines: vec![line.to_string()],
+                is_editing,
+                ..Default::default()
+            })
+            .html
+        };
+
+        let html = render("3. third", false);
+        assert!(html.contains("data-start=\"3\""));
+        assert!(html.contains("<span class=\"list-marker ordered\">3.</span>third"));
+
+        let html = render("1) first", false);
+        assert!(html.contains("data-start=\"1\""));
+        assert!(html.contains("<span class=\"list-marker ordered\">1)</span>first"));
+
+        assert_eq!(render("1) first", true), "<span class=\"list-item\">1) first</span>");
+        assert!(!render("- item", false).contains("data-start"));
+    }
+
+    #[test]
+    fn test_mixed_list_depth_and_type() {
+        let item = |line: &str| {
+            let html = render_markdown_line(RenderRequest { line: line.to_string(), ..paragraph("") }).html;
+            let attribute = |name: &str| {
+                let start = html.find(&format!("{}=\"", name)).unwrap() + name.len() + 2;
+                html[start..].split('"').next().unwrap().to_string()
+            };
+            (attribute("data-depth"), attribute("data-list-type"))
+        };
+
+        assert_eq!(item("1. first"), ("0".to_string(), "ordered".to_string()));
+        assert_eq!(item("    - nested"), ("1".to_string(), "unordered".to_string()));
+        assert_eq!(item("\t- tabbed"), ("1".to_string(), "unordered".to_string()));
+        assert_eq!(item("\t    2. deeper"), ("2".to_string(), "ordered".to_string()));
+    }
+
+    #[test]
+    fn test_two_space_nesting_depth() {
+        let content = "- a\n  - b\n    - c\n\n  - d\nText\n  - e";
+        let html = render_document(content, &RenderOptions::default());
+        let depths: Vec<&str> = html.split("data-depth=\"").skip(1).map(|rest| &rest[..1]).collect();
+
+        assert_eq!(depths, ["0", "1", "2", "1", "0"]);
+    }
+
+    #[test]
+    fn test_bullet_glyphs_by_depth() {
+        let glyphs = vec!["–".to_string(), "▪".to_string()];
+        let lines: Vec<String> = ["- top", "  - nested", "    - deeper", "  1. item"].iter().map(|l| l.to_string()).collect();
+        let marker = |line_index: usize, bullet_glyphs: Vec<String>| {
+            let request = RenderRequest { line: lines[line_index].clone(), line_index, all_lines: lines.clone(), bullet_glyphs, ..paragraph("") };
+            render_markdown_line(request).html
+        };
+
+        assert!(marker(0, glyphs.clone()).contains("<span class=\"list-marker unordered\">–</span>top"));
+        assert!(marker(1, glyphs.clone()).contains("<span class=\"list-marker unordered\">▪</span>nested"));
+        assert!(marker(2, glyphs.clone()).contains("<span class=\"list-marker unordered\">–</span>deeper"));
+        assert!(marker(1, Vec::new()).contains("<span class=\"list-marker unordered\">•</span>nested"));
+        assert!(marker(3, glyphs).contains("<span class=\"list-marker ordered\">1.</span>item"));
+    }
+
+    #[test]
+    fn test_math_block_editing_output() {
+        let lines: Vec<String> = ["$$", "a < b", "$$"].iter().map(|l| l.to_string()).collect();
+        let html: Vec<String> = (0..lines.len())
+            .map(|line_index| {
+                render_markdown_line(RenderRequest {
+                    line: lines[line_index].clone(),
+                    line_index,
+                    all_lines: lines.clone(),
+                    is_editing: true,
+                    ..Default::default()
+                })
+                .html
+            })
+            .collect();
+
+        assert_eq!(
+            html,
+            [
+                "<span class=\"math-block-start\">$$</span>",
+                "<span class=\"math-block-line-editing\">a &lt; b</span>",
+                "<span class=\"math-block-end\">$$</span>",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_range_inside_code_block() {
+        let lines: Vec<String> = ["# Title", "```rust", "let x = 1;", "# not a heading", "```", "after"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+
+        let range = render_markdown_range(lines.clone(), 3, 4, false, &RenderOptions::default());
+        let expected: Vec<String> = (3..=4)
+            .map(|line_index| {
+                render_markdown_line(RenderRequest {
+                    line: lines[line_index].clone(),
+                    line_index,
+                    all_lines: lines.clone(),
+                    is_editing: false,
+                    ..Default::default()
+                })
+                .html
+            })
+            .collect();
+
+        assert_eq!(range.len(), 2);
+        assert!(!range[0].html.contains("<h1"));
+        assert!(range[1].is_code_block_boundary);
+        assert_eq!(range.iter().map(|r| r.html.clone()).collect::<Vec<_>>(), expected);
+        assert_eq!(render_markdown_range(lines.clone(), 5, 100, false, &RenderOptions::default()).len(), 1);
+        assert!(render_markdown_range(lines, 4, 2, false, &RenderOptions::default()).is_empty());
+    }
+
+
+    #[test]
+    fn test_window_starting_inside_code_block() {
+        let lines: Vec<String> = ["# Title", "```rust", "let x = 1;", "# not a heading", "```", "# after"]
+            .iter()
+            .map(|l| l.to_string())
+            .collect();
+        let context = BlockContext { open_fence: Some(3), in_math: false };
+
+        let result = render_markdown_window(lines[3..].to_vec(), 3, lines.len(), context, false, &RenderOptions::default());
+        let window = result.lines;
+        let expected = render_markdown_range(lines.clone(), 3, 5, false, &RenderOptions::default());
+
+        assert_eq!(window.len(), 3);
+        assert_eq!(window[0].html, "<code class=\"code-block-line\"># not a heading</code>");
+        assert!(window[1].is_code_block_boundary);
+        assert!(window[2].html.contains("heading h1"));
+        assert_eq!(
+            window[1..].iter().map(|r| r.html.clone()).collect::<Vec<_>>(),
+            expected[1..].iter().map(|r| r.html.clone()).collect::<Vec<_>>()
+        );
+        assert_eq!(result.context_after, BlockContext::default());
+    }
+
+    #[test]
+    fn test_window_keeps_longer_fence_open() {
+        let window_lines: Vec<String> = ["```", "still code", "````", "$$"].iter().map(|l| l.to_string()).collect();
+        let context = BlockContext { open_fence: Some(4), in_math: false };
+
+        let window = render_markdown_window(window_lines, 100, 103, context, false, &RenderOptions::default()).lines;
+
+        // The 3-backtick line can't close a 4-backtick fence, and the $$ line is past the end
+        assert_eq!(window.len(), 3);
+        assert!(!window[0].is_code_block_boundary);
+        assert!(window[1].html.contains("code-block-line"));
+        assert!(window[2].is_code_block_boundary);
+    }
+
+    #[test]
+    fn test_windows_chain_their_context() {
+        let lines: Vec<String> = ["Intro", "```", "# code", "```", "# after"].iter().map(|l| l.to_string()).collect();
+        let options = RenderOptions::default();
+
+        let first = render_markdown_window(lines[..3].to_vec(), 0, lines.len(), BlockContext::default(), false, &options);
+        let second = render_markdown_window(lines[3..].to_vec(), 3, lines.len(), first.context_after, false, &options);
+        let expected = render_markdown_range(lines.clone(), 0, 4, false, &options);
+
+        assert_eq!(first.context_after, BlockContext { open_fence: Some(3), in_math: false });
+        assert_eq!(
+            first.lines.iter().chain(&second.lines).map(|r| r.html.clone()).collect::<Vec<_>>(),
+            expected.iter().map(|r| r.html.clone()).collect::<Vec<_>>()
+        );
+    }
+    #[test]
+    fn test_batch_requests_can_share_the_document() {
+        let lines: Vec<String> = ["Intro", "", "", "```", "# code", "```"].iter().map(|l| l.to_string()).collect();
+        let requests = |share: bool| -> Vec<RenderRequest> {
+            (0..lines.len())
+                .map(|line_index| RenderRequest {
+                    line: lines[line_index].clone(),
+                    line_index,
+                    all_lines: if share && line_index > 0 { Vec::new() } else { lines.clone() },
+                    collapse_blank_lines: true,
+                    ..Default::default()
+                })
+                .collect()
+        };
+        let html = |results: Vec<LineRenderResult>| results.into_iter().map(|r| r.html).collect::<Vec<_>>();
+
+        let full = html(render_markdown_lines(requests(false), &Mutex::new(RenderCache::new(100))));
+        let shared = html(render_markdown_lines(requests(true), &Mutex::new(RenderCache::new(100))));
+        assert_eq!(shared, full);
+        assert_eq!(shared[2], "");
+        assert!(!shared[4].contains("heading h1"));
+
+        // A request from another document still gets that document's context
+        let mut mixed = requests(true);
+        mixed[4].all_lines = vec!["# code".to_string()];
+        mixed[4].line_index = 0;
+        let mixed = html(render_markdown_lines(mixed, &Mutex::new(RenderCache::new(100))));
+        assert!(mixed[4].contains("heading h1"));
+    }
+
+    #[test]
+    fn test_repeated_batch_hits_cache() {
+        let lines: Vec<String> = ["# Title", "```", "# code", "```"].iter().map(|l| l.to_string()).collect();
+        let requests: Vec<RenderRequest> = (0..lines.len())
+            .map(|line_index| RenderRequest {
+                line: lines[line_index].clone(),
+                line_index,
+                all_lines: lines.clone(),
+                is_editing: false,
+                ..Default::default()
+            })
+            .collect();
+        let cache = Mutex::new(RenderCache::new(100));
+
+        let first = render_markdown_lines(requests.clone(), &cache);
+        let hits_after_first = cache.lock().unwrap().hits();
+        let second = render_markdown_lines(requests.clone(), &cache);
+        let hits_after_second = cache.lock().unwrap().hits();
+        let uncached = render_markdown_lines(requests, &Mutex::new(RenderCache::new(100)));
+
+        assert_eq!(hits_after_first, 0);
+        assert_eq!(hits_after_second, 4);
+        let html = |results: &[LineRenderResult]| results.iter().map(|r| r.html.clone()).collect::<Vec<_>>();
+        assert_eq!(html(&first), html(&second));
+        assert_eq!(html(&second), html(&uncached));
+    }
+
+    fn paragraph(line: &str) -> RenderRequest {
+        RenderRequest {
+            line: line.to_string(),
+            line_index: 0,
+            all_lines: vec![line.to_string()],
+            is_editing: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_highlight_plain_text_after_entities() {
+        let lines: Vec<String> = ["```", "if a < b && c {", "```"].iter().map(|l| l.to_string()).collect();
+        let request = RenderRequest { line: lines[1].clone(), line_index: 1, all_lines: lines, ..paragraph("") };
+        let result = render_markdown_line_with_highlights(request, vec![(7, 13)]);
+        let plain = render_markdown_line_with_highlights(paragraph("1 < 2 is true"), vec![(4, 13)]);
+
+        assert_eq!(
+            result.html,
+            "<code class=\"code-block-line\">if a &lt; <span class=\"search-highlight\">b &amp;&amp; c</span> {</code>"
+        );
+        assert_eq!(plain.html, "1 &lt; <span class=\"search-highlight\">2 is true</span>");
+    }
+
+    #[test]
+    fn test_highlight_spanning_bold() {
+        let line = "Some **bold** text";
+        let inside = render_markdown_line_with_highlights(paragraph(line), vec![(7, 11)]);
+        let across = render_markdown_line_with_highlights(paragraph(line), vec![(2, 6), (5, 9)]);
+
+        assert_eq!(inside.html, "Some <strong><span class=\"search-highlight\">bold</span></strong> text");
+        assert_eq!(
+            across.html,
+            "So<span class=\"search-highlight\">me </span><strong><span class=\"search-highlight\">bo</span>ld</strong> text"
+        );
+    }
+
+    #[test]
+    fn test_collapse_blank_lines() {
+        let lines: Vec<String> = ["Intro", "", "", "", "Next"].iter().map(|l| l.to_string()).collect();
+        let requests = |is_editing: bool| -> Vec<RenderRequest> {
+            (0..lines.len())
+                .map(|line_index| RenderRequest {
+                    line: lines[line_index].clone(),
+                    line_index,
+                    all_lines: lines.clone(),
+                    is_editing,
+                    collapse_blank_lines: true,
+                    ..paragraph("")
+                })
+                .collect()
+        };
+        let cache = Mutex::new(RenderCache::new(100));
+        let html = |results: Vec<LineRenderResult>| results.into_iter().map(|r| r.html).collect::<Vec<_>>();
+
+        let viewing = html(render_markdown_lines(requests(false), &cache));
+        let editing = html(render_markdown_lines(requests(true), &cache));
+
+        assert_eq!(viewing, ["Intro", "<br>", "", "", "Next"]);
+        assert_eq!(editing, ["Intro", "<br>", "<br>", "<br>", "Next"]);
+    }
+
+    #[test]
+    fn test_collapse_blank_lines_in_document() {
+        let content = "Intro\n\n\n\nNext";
+        let options = RenderOptions { collapse_blank_lines: true, ..RenderOptions::default() };
+        let lines: Vec<String> = content.lines().map(String::from).collect();
+
+        let document = render_document(content, &options);
+        let range = render_markdown_range(lines, 0, 4, false, &options);
+
+        assert_eq!(document.matches("<br>").count(), 1);
+        assert_eq!(range.iter().filter(|r| r.html == "<br>").count(), 1);
+    }
+
+    #[test]
+    fn test_raw_html_in_paragraphs() {
+        let line = "Some <b>bold</b> and *more*<script>alert(1)</script>";
+        let escaped = render_markdown_line(paragraph(line));
+        let passed = render_markdown_line(RenderRequest { allow_raw_html: true, ..paragraph(line) });
+
+        assert_eq!(
+            escaped.html,
+            "Some &lt;b&gt;bold&lt;/b&gt; and <em>more</em>&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+        assert_eq!(passed.html, "Some <b>bold</b> and <em>more</em>");
+    }
 }
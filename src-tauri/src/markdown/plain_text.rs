@@ -0,0 +1,93 @@
+/**
+ * Plain text extraction
+ *
+ * Reduces a markdown document to its prose for previews, word counts and
+ * copying as plain text, by removing markers instead of rendering to HTML.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::block_detection::DocumentBlockMap;
+use super::inline_rendering::strip_inline_markdown;
+use super::{BLOCKQUOTE_RE, HEADER_RE, HR_RE, LIST_RE};
+
+static TASK_BOX_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[[ xX]\]\s+").unwrap());
+
+/// Plain text of a single line outside any code or math block
+fn strip_line(line: &str) -> Option<String> {
+    if HR_RE.is_match(line) {
+        return None;
+    }
+
+    let text = if let Some(cap) = HEADER_RE.captures(line) {
+        cap.get(2).unwrap().as_str()
+    } else if let Some(cap) = LIST_RE.captures(line) {
+        let item = cap.get(4).unwrap().as_str();
+        TASK_BOX_RE.find(item).map_or(item, |checkbox| &item[checkbox.end()..])
+    } else if let Some(cap) = BLOCKQUOTE_RE.captures(line) {
+        cap.get(1).unwrap().as_str()
+    } else {
+        line.trim()
+    };
+
+    Some(strip_inline_markdown(text))
+}
+
+/// Text of a document with all markdown removed
+///
+/// Headings become plain lines, list and quote markers are dropped, and
+/// links keep their text but not their URL. Fences and rules are removed;
+/// code and math block contents are kept verbatim if `keep_code_blocks`
+/// is set and dropped otherwise.
+pub fn strip_markdown(content: &str, keep_code_blocks: bool) -> String {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let state = map.state(i);
+            if state.code_start || state.code_end || state.math_start || state.math_end {
+                None
+            } else if state.in_code || state.in_math {
+                keep_code_blocks.then(|| line.clone())
+            } else {
+                strip_line(line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading_becomes_plain_line() {
+        assert_eq!(strip_markdown("## Getting *Started*\n\nSome text", true), "Getting Started\n\nSome text");
+    }
+
+    #[test]
+    fn test_link_keeps_text_not_url() {
+        assert_eq!(
+            strip_markdown("- Read [the docs](https://example.com) and [[notes/todo|my list]]", true),
+            "Read the docs and my list"
+        );
+    }
+
+    #[test]
+    fn test_bold_italic_run() {
+        assert_eq!(strip_markdown("> A ***very*** **bold** _claim_", true), "A very bold claim");
+    }
+
+    #[test]
+    fn test_code_blocks_kept_or_dropped() {
+        let content = "Intro\n```rust\nlet *x* = 1;\n```\n---\nOutro";
+
+        assert_eq!(strip_markdown(content, true), "Intro\nlet *x* = 1;\nOutro");
+        assert_eq!(strip_markdown(content, false), "Intro\nOutro");
+    }
+}
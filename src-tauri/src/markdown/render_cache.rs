@@ -0,0 +1,155 @@
+/**
+ * Rendered line cache
+ *
+ * Remembers the HTML of recently rendered lines so unchanged lines aren't
+ * re-rendered on every keystroke. Entries are keyed by everything the output
+ * depends on, including the line's block context, so a hit stays correct
+ * after the line moves.
+ */
+
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+
+use super::block_detection::LineBlockState;
+use super::{is_collapsed_blank, LineRenderResult, RenderRequest};
+
+// Enough for several large documents while keeping memory in the low megabytes
+const RENDER_CACHE_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RenderKey {
+    line: String,
+    block_state: LineBlockState,
+    is_editing: bool,
+    heading_offset: i8,
+    server_side_math: bool,
+    hr_style: String,
+    allow_raw_html: bool,
+    code_language: String,
+    // Depends on the line before, which the rest of the key doesn't cover
+    collapsed_blank: bool,
+    heading_id: Option<String>,
+    bullet_glyphs: Vec<String>,
+    loose_list: bool,
+    tab_size: u8,
+    list_depth: Option<usize>,
+}
+
+impl RenderKey {
+    pub fn new(request: &RenderRequest, block_state: LineBlockState) -> Self {
+        Self {
+            line: request.line.clone(),
+            block_state,
+            is_editing: request.is_editing,
+            heading_offset: request.heading_offset,
+            server_side_math: request.server_side_math,
+            hr_style: request.hr_style.clone(),
+            allow_raw_html: request.allow_raw_html,
+            code_language: request.code_language.clone(),
+            collapsed_blank: is_collapsed_blank(request, block_state),
+            heading_id: request.heading_id.clone(),
+            bullet_glyphs: request.bullet_glyphs.clone(),
+            loose_list: request.loose_list,
+            tab_size: request.tab_size,
+            list_depth: request.list_depth,
+        }
+    }
+}
+
+/// Least-recently-used cache of rendered lines
+pub struct RenderCache {
+    entries: LruCache<RenderKey, LineRenderResult>,
+    hits: usize,
+}
+
+impl RenderCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: LruCache::new(NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN)),
+            hits: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &RenderKey) -> Option<LineRenderResult> {
+        let result = self.entries.get(key).cloned();
+        if result.is_some() {
+            self.hits += 1;
+        }
+        result
+    }
+
+    pub fn insert(&mut self, key: RenderKey, result: LineRenderResult) {
+        self.entries.put(key, result);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.hits = 0;
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of lookups answered from the cache since it was created or cleared
+    #[cfg(test)]
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+}
+
+// Render cache shared by batch renders, held in Tauri managed state
+pub type RenderCacheHandle = Arc<Mutex<RenderCache>>;
+
+pub fn create_render_cache() -> RenderCacheHandle {
+    Arc::new(Mutex::new(RenderCache::new(RENDER_CACHE_CAPACITY)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(line: &str) -> RenderKey {
+        RenderKey {
+            line: line.to_string(),
+            block_state: LineBlockState::default(),
+            is_editing: false,
+            heading_offset: 0,
+            server_side_math: false,
+            hr_style: String::new(),
+            allow_raw_html: false,
+            code_language: String::new(),
+            collapsed_blank: false,
+            heading_id: None,
+            bullet_glyphs: Vec::new(),
+            loose_list: false,
+            tab_size: 4,
+            list_depth: None,
+        }
+    }
+
+    fn result(html: &str) -> LineRenderResult {
+        LineRenderResult { html: html.to_string(), is_code_block_boundary: false }
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let mut cache = RenderCache::new(2);
+        cache.insert(key("a"), result("A"));
+        cache.insert(key("b"), result("B"));
+        assert!(cache.get(&key("a")).is_some());
+        cache.insert(key("c"), result("C"));
+
+        assert!(cache.get(&key("b")).is_none());
+        assert_eq!(cache.get(&key("a")).map(|r| r.html), Some("A".to_string()));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.hits(), 2);
+    }
+}
@@ -0,0 +1,16 @@
+/**
+ * HTML sanitizing
+ *
+ * Cleans HTML typed into notes before it is shown, keeping formatting
+ * tags and dropping scripts, frames and event handlers.
+ */
+
+/// Strip anything unsafe from an HTML fragment
+///
+/// Uses ammonia's default allowlist: formatting and structural tags and
+/// their plain attributes are kept, while `<script>`, `<style>` and
+/// `<iframe>` are removed along with `on*` handlers and `javascript:` URLs.
+/// Text outside tags comes back escaped.
+pub fn sanitize_html(html: &str) -> String {
+    ammonia::clean(html)
+}
@@ -0,0 +1,101 @@
+/**
+ * Document statistics
+ *
+ * Word and character counts for the status bar, taken from the plain text
+ * of a document so markdown markers and link URLs aren't counted.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use super::block_detection::DocumentBlockMap;
+use super::plain_text::strip_markdown;
+
+// Average silent reading speed used for the reading time estimate
+const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocStats {
+    pub words: usize,
+    /// Characters of the plain text, not counting line breaks
+    pub characters: usize,
+    pub lines: usize,
+    /// Estimated reading time, rounded up to whole minutes
+    pub reading_minutes: usize,
+}
+
+fn stats_of(plain: &str, lines: usize) -> DocStats {
+    let words = plain.split_whitespace().count();
+    DocStats {
+        words,
+        characters: plain.chars().filter(|&c| c != '\n').count(),
+        lines,
+        reading_minutes: words.div_ceil(WORDS_PER_MINUTE),
+    }
+}
+
+/// Statistics for a whole document, counting code block contents as words
+pub fn document_stats(content: &str) -> DocStats {
+    stats_of(&strip_markdown(content, true), content.lines().count())
+}
+
+/// Statistics for a selected part of a document
+///
+/// A selection that starts or ends partway through a code block has a
+/// fence with no partner, which would otherwise turn everything after it
+/// into code. That fence is dropped and the fragment counted as prose;
+/// complete blocks inside the selection are handled as in a document.
+pub fn selection_stats(text: &str) -> DocStats {
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let line_count = lines.len();
+    let map = DocumentBlockMap::build(&lines);
+
+    let unclosed_fence = (0..lines.len())
+        .rev()
+        .find(|&i| map.state(i).code_start)
+        .filter(|&start| !(start + 1..lines.len()).any(|i| map.state(i).code_end));
+    if let Some(fence) = unclosed_fence {
+        lines.remove(fence);
+    }
+
+    stats_of(&strip_markdown(&lines.join("\n"), true), line_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_stats_skip_markers() {
+        let stats = document_stats("# A title\n\nSee [the docs](https://example.com/very/long/url) **now**.\n");
+
+        assert_eq!(stats, DocStats { words: 6, characters: 24, lines: 3, reading_minutes: 1 });
+    }
+
+    #[test]
+    fn test_multi_line_selection() {
+        let stats = selection_stats("- first *item*\n- second item\n\nA closing line");
+
+        assert_eq!(stats.words, 7);
+        assert_eq!(stats.lines, 4);
+    }
+
+    #[test]
+    fn test_single_word_selection() {
+        assert_eq!(
+            selection_stats("**word**"),
+            DocStats { words: 1, characters: 4, lines: 1, reading_minutes: 1 }
+        );
+        assert_eq!(selection_stats("").words, 0);
+    }
+
+    #[test]
+    fn test_selection_starting_mid_code_block() {
+        // The closing fence of a block the selection started inside
+        let stats = selection_stats("let x = 1;\n```\nBack to *prose* here");
+
+        assert_eq!(stats.words, 8);
+        assert_eq!(stats.characters, 28);
+        assert_eq!(stats.lines, 3);
+    }
+}
@@ -0,0 +1,193 @@
+/**
+ * GFM table formatting
+ *
+ * Pads the cells of a pipe table so its columns line up in the source.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::block_detection::DocumentBlockMap;
+
+// A delimiter row cell such as `---`, `:--`, `--:` or `:-:`
+static DELIMITER_CELL_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^:?-+:?$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    fn from_delimiter(cell: &str) -> Self {
+        match (cell.starts_with(':'), cell.ends_with(':')) {
+            (true, true) => Alignment::Center,
+            (true, false) => Alignment::Left,
+            (false, true) => Alignment::Right,
+            (false, false) => Alignment::None,
+        }
+    }
+
+    fn delimiter(self, width: usize) -> String {
+        match self {
+            Alignment::None => "-".repeat(width),
+            Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+            Alignment::Center => format!(":{}:", "-".repeat(width - 2)),
+            Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+        }
+    }
+
+    fn pad(self, cell: &str, width: usize) -> String {
+        let space = width - cell.chars().count();
+        match self {
+            Alignment::Right => format!("{}{}", " ".repeat(space), cell),
+            Alignment::Center => format!("{}{}{}", " ".repeat(space / 2), cell, " ".repeat(space - space / 2)),
+            Alignment::None | Alignment::Left => format!("{}{}", cell, " ".repeat(space)),
+        }
+    }
+}
+
+/// Split a table row into trimmed cells, ignoring escaped `\|` pipes
+pub(crate) fn split_row(line: &str) -> Vec<String> {
+    let trimmed = line.trim();
+    let inner = trimmed.strip_prefix('|').unwrap_or(trimmed);
+    let inner = match inner.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => inner,
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut escaped = false;
+    for c in inner.chars() {
+        if c == '|' && !escaped {
+            cells.push(cell.trim().to_string());
+            cell.clear();
+        } else {
+            cell.push(c);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+pub(crate) fn is_delimiter_row(cells: &[String]) -> bool {
+    cells.iter().all(|cell| DELIMITER_CELL_RE.is_match(cell))
+}
+
+/// Align the columns of the table containing `line` (0-based)
+///
+/// Every cell is padded to its column's widest raw text, honoring the
+/// `:--`, `:-:` and `--:` alignment hints of the delimiter row. Rows with
+/// missing cells are filled out with empty ones. The document is returned
+/// unchanged if `line` is not part of a table.
+pub fn format_table(content: &str, line: usize) -> String {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+
+    let is_row = |i: usize| {
+        let state = map.state(i);
+        !state.in_code && !state.in_math && lines[i].contains('|')
+    };
+
+    if line >= lines.len() || !is_row(line) {
+        return content.to_string();
+    }
+
+    let mut start = line;
+    while start > 0 && is_row(start - 1) {
+        start -= 1;
+    }
+    let mut end = line + 1;
+    while end < lines.len() && is_row(end) {
+        end += 1;
+    }
+
+    let rows: Vec<Vec<String>> = lines[start..end].iter().map(|l| split_row(l)).collect();
+    if rows.len() < 2 || !is_delimiter_row(&rows[1]) {
+        return content.to_string();
+    }
+
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let alignments: Vec<Alignment> = (0..columns)
+        .map(|c| rows[1].get(c).map_or(Alignment::None, |cell| Alignment::from_delimiter(cell)))
+        .collect();
+    let widths: Vec<usize> = (0..columns)
+        .map(|c| {
+            rows.iter()
+                .enumerate()
+                .filter(|(r, _)| *r != 1)
+                .filter_map(|(_, row)| row.get(c))
+                .map(|cell| cell.chars().count())
+                .fold(3, usize::max)
+        })
+        .collect();
+
+    let formatted = rows.iter().enumerate().map(|(r, row)| {
+        let cells: Vec<String> = (0..columns)
+            .map(|c| {
+                if r == 1 {
+                    alignments[c].delimiter(widths[c])
+                } else {
+                    alignments[c].pad(row.get(c).map_or("", String::as_str), widths[c])
+                }
+            })
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    });
+
+    let mut output: Vec<String> = lines[..start].to_vec();
+    output.extend(formatted);
+    output.extend_from_slice(&lines[end..]);
+
+    let mut result = output.join("\n");
+    if content.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ragged_table_is_aligned() {
+        let content = "Intro\n\n|Name|Value|\n|-|-|\n|a|1|\n| longer name | 100 |\n|short\n\nOutro\n";
+
+        assert_eq!(
+            format_table(content, 4),
+            "Intro\n\n\
+             | Name        | Value |\n\
+             | ----------- | ----- |\n\
+             | a           | 1     |\n\
+             | longer name | 100   |\n\
+             | short       |       |\n\
+             \nOutro\n"
+        );
+    }
+
+    #[test]
+    fn test_alignment_markers_are_preserved() {
+        let content = "| Left | Center | Right |\n|:--|:-:|--:|\n| a | b | c |\n| `x\\|y` | ** | 12345 |";
+
+        assert_eq!(
+            format_table(content, 0),
+            "| Left   | Center | Right |\n\
+             | :----- | :----: | ----: |\n\
+             | a      |   b    |     c |\n\
+             | `x\\|y` |   **   | 12345 |"
+        );
+    }
+
+    #[test]
+    fn test_non_table_line_is_unchanged() {
+        let content = "```\n| a | b |\n|---|---|\n```\nplain";
+
+        assert_eq!(format_table(content, 1), content);
+        assert_eq!(format_table(content, 4), content);
+    }
+}
@@ -0,0 +1,121 @@
+/**
+ * Inline tag extraction
+ *
+ * Finds `#tag` style tags in a document, skipping headings, code and links.
+ */
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::block_detection::DocumentBlockMap;
+
+// A '#' at line start or after whitespace, directly followed by the tag
+static TAG_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?:^|\s)#([\w\-/]+)").unwrap());
+// Spans whose contents can't contain tags: inline code, link targets and bare URLs
+static IGNORED_SPAN_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"`[^`]*`|\]\([^)]*\)|[a-zA-Z][a-zA-Z0-9+.-]*://\S+").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// The inline tags of a document, with the 0-based line each one is on
+fn document_tags(content: &str) -> Vec<(usize, String)> {
+    let lines: Vec<String> = content.lines().map(String::from).collect();
+    let map = DocumentBlockMap::build(&lines);
+    let mut tags = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        if map.state(i).in_code {
+            continue;
+        }
+
+        let cleaned = IGNORED_SPAN_RE.replace_all(line, " ");
+        for cap in TAG_RE.captures_iter(&cleaned) {
+            tags.push((i, cap[1].to_string()));
+        }
+    }
+
+    tags
+}
+
+/// Count the inline tags in a document
+///
+/// Tags may contain letters, digits, `-`, `_` and `/` (for hierarchies like
+/// `#project/alpha`). Results are sorted by count (highest first), then name.
+pub fn extract_tags(content: &str) -> Vec<TagCount> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (_, tag) in document_tags(content) {
+        *counts.entry(tag).or_insert(0) += 1;
+    }
+
+    let mut tags: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, count)| TagCount { tag, count })
+        .collect();
+    tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+
+    tags
+}
+
+/// The 1-based lines on which a tag is used, ignoring case
+///
+/// A leading `#` on `tag` is optional. Tags nested below it match too, so
+/// `project` finds `#project/alpha`. Each line is listed once.
+pub fn tag_lines(content: &str, tag: &str) -> Vec<usize> {
+    let wanted = tag.trim().trim_start_matches('#').to_lowercase();
+    if wanted.is_empty() {
+        return Vec::new();
+    }
+
+    let mut lines: Vec<usize> = document_tags(content)
+        .into_iter()
+        .filter(|(_, found)| {
+            let found = found.to_lowercase();
+            found == wanted || found.strip_prefix(&wanted).is_some_and(|rest| rest.starts_with('/'))
+        })
+        .map(|(i, _)| i + 1)
+        .collect();
+    lines.dedup();
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading_is_not_a_tag() {
+        let tags = extract_tags("# Title\n## Subtitle\nSome #todo here and #todo again #project/alpha");
+
+        assert_eq!(
+            tags,
+            vec![
+                TagCount { tag: "todo".to_string(), count: 2 },
+                TagCount { tag: "project/alpha".to_string(), count: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tags_in_code_and_links_are_ignored() {
+        let content = "#real\n```\n#incode\n```\nUse `#inline` or [link](https://example.com/#anchor) https://x.io/ #after";
+        let tags: Vec<String> = extract_tags(content).into_iter().map(|t| t.tag).collect();
+
+        assert_eq!(tags, vec!["after".to_string(), "real".to_string()]);
+    }
+
+    #[test]
+    fn test_tag_lines_matches_nested_tags() {
+        let content = "# project\n#Project here\n`#project` #project/alpha #project\n#projects\n```\n#project\n```";
+
+        assert_eq!(tag_lines(content, "#project"), vec![2, 3]);
+        assert_eq!(tag_lines(content, "project/ALPHA"), vec![3]);
+        assert!(tag_lines(content, "alpha").is_empty());
+    }
+}
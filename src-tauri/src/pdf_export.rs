@@ -0,0 +1,454 @@
+/**
+ * PDF export
+ *
+ * Lays out the HTML export's rendered lines on A4 pages with the built-in
+ * PDF fonts, colored with the theme's variables. Math is written as its
+ * LaTeX source, since KaTeX only runs in a browser.
+ */
+
+use crate::color::parse_hex;
+use crate::config::ThemeConfig;
+use crate::markdown::{render_document, RenderOptions};
+use printpdf::{
+    BuiltinFont, Color, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Rect, Rgb,
+};
+use scraper::{ElementRef, Html, Node, Selector};
+use std::fs;
+use std::path::Path;
+
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 20.0;
+const PT_TO_MM: f32 = 0.352_778;
+const BODY_SIZE: f32 = 11.0;
+const LINE_SPACING: f32 = 1.4;
+// Left offset of quote text and of each list nesting level
+const INDENT: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    code: bool,
+}
+
+/// Text sharing one font and color
+#[derive(Debug)]
+struct Run {
+    text: String,
+    style: Style,
+    /// Theme variable for the color, or the block's color if None
+    color: Option<&'static str>,
+}
+
+#[derive(Debug)]
+enum Block {
+    Heading(usize, Vec<Run>),
+    Paragraph(Vec<Run>),
+    ListItem { level: usize, marker: String, runs: Vec<Run> },
+    Quote(Vec<Run>),
+    Code(String),
+    Math(String),
+    Rule,
+    Blank,
+}
+
+fn has_class(element: &ElementRef, class: &str) -> bool {
+    element.value().classes().any(|c| c == class)
+}
+
+/// Split an element's text into runs, following strong/em/code/link nesting
+fn collect_runs(element: ElementRef, style: Style, color: Option<&'static str>, runs: &mut Vec<Run>) {
+    for child in element.children() {
+        if let Some(child) = ElementRef::wrap(child) {
+            if has_class(&child, "list-marker") {
+                continue;
+            }
+            let (style, color) = match child.value().name() {
+                "strong" => (Style { bold: true, ..style }, color.or(Some("strong-color"))),
+                "em" => (Style { italic: true, ..style }, color.or(Some("em-color"))),
+                "code" => (Style { code: true, ..style }, Some("code-color")),
+                "a" => (style, Some("link-color")),
+                "img" => {
+                    let alt = child.value().attr("alt").filter(|alt| !alt.is_empty()).unwrap_or("image");
+                    runs.push(Run { text: format!("[{}]", alt), style: Style { italic: true, ..style }, color });
+                    continue;
+                }
+                _ => (style, color),
+            };
+            collect_runs(child, style, color, runs);
+        } else if let Node::Text(text) = child.value() {
+            runs.push(Run { text: text.to_string(), style, color });
+        }
+    }
+}
+
+fn runs_of(element: ElementRef) -> Vec<Run> {
+    let mut runs = Vec::new();
+    collect_runs(element, Style::default(), None, &mut runs);
+    runs
+}
+
+/// Classify one rendered `.editor-line`, or None for lines that draw nothing (fences)
+fn parse_line(line: ElementRef) -> Option<Block> {
+    let Some(first) = line.children().find_map(ElementRef::wrap) else {
+        return Some(Block::Paragraph(runs_of(line)));
+    };
+    let text = || first.text().collect::<String>();
+
+    if has_class(&first, "heading") {
+        let level = (1..=6).find(|n| has_class(&first, &format!("h{}", n))).unwrap_or(1);
+        Some(Block::Heading(level, runs_of(first)))
+    } else if has_class(&first, "list-item") {
+        // The padding follows the indent's width, so nesting comes from the depth
+        let level = first
+            .value()
+            .attr("data-depth")
+            .and_then(|depth| depth.parse::<usize>().ok())
+            .unwrap_or(0);
+        let marker = first
+            .children()
+            .filter_map(ElementRef::wrap)
+            .find(|child| has_class(child, "list-marker"))
+            .map(|marker| marker.text().collect())
+            .unwrap_or_default();
+        Some(Block::ListItem { level, marker, runs: runs_of(first) })
+    } else if has_class(&first, "blockquote") {
+        Some(Block::Quote(runs_of(first)))
+    } else if has_class(&first, "code-block-line") || has_class(&first, "mermaid") {
+        Some(Block::Code(text()))
+    } else if has_class(&first, "math-block") {
+        Some(Block::Math(first.value().attr("data-latex").map_or_else(text, String::from)))
+    } else if has_class(&first, "hr") {
+        Some(Block::Rule)
+    } else if ["code-block-start", "code-block-end", "math-block-start", "math-block-end"]
+        .iter()
+        .any(|class| has_class(&first, class))
+    {
+        None
+    } else if first.value().name() == "br" && line.text().all(|t| t.trim().is_empty()) {
+        Some(Block::Blank)
+    } else {
+        Some(Block::Paragraph(runs_of(line)))
+    }
+}
+
+/// Render a document and break it into blocks to lay out
+fn document_blocks(content: &str) -> Vec<Block> {
+    let fragment = Html::parse_fragment(&render_document(content, &RenderOptions::default()));
+    let lines = Selector::parse("div.editor-line").unwrap();
+    fragment.select(&lines).filter_map(parse_line).collect()
+}
+
+struct Fonts {
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+    italic: IndirectFontRef,
+    bold_italic: IndirectFontRef,
+    code: IndirectFontRef,
+}
+
+impl Fonts {
+    fn get(&self, style: Style) -> &IndirectFontRef {
+        match (style.code, style.bold, style.italic) {
+            (true, _, _) => &self.code,
+            (false, true, true) => &self.bold_italic,
+            (false, true, false) => &self.bold,
+            (false, false, true) => &self.italic,
+            (false, false, false) => &self.regular,
+        }
+    }
+}
+
+/// Approximate width of text in mm; the built-in fonts don't expose metrics
+fn text_width(text: &str, style: Style, size: f32) -> f32 {
+    let em = match (style.code, style.bold) {
+        (true, _) => 0.6,
+        (false, true) => 0.56,
+        (false, false) => 0.5,
+    };
+    text.chars().count() as f32 * size * em * PT_TO_MM
+}
+
+struct PdfWriter<'a> {
+    doc: PdfDocumentReference,
+    layer: PdfLayerReference,
+    fonts: Fonts,
+    theme: &'a ThemeConfig,
+    /// Distance from the bottom of the page to the top of the next line, in mm
+    y: f32,
+    at_page_top: bool,
+}
+
+impl<'a> PdfWriter<'a> {
+    fn new(title: &str, theme: &'a ThemeConfig) -> Result<Self, String> {
+        let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Content");
+        let font = |font| doc.add_builtin_font(font).map_err(|e| format!("Failed to load PDF font: {}", e));
+        let fonts = Fonts {
+            regular: font(BuiltinFont::Helvetica)?,
+            bold: font(BuiltinFont::HelveticaBold)?,
+            italic: font(BuiltinFont::HelveticaOblique)?,
+            bold_italic: font(BuiltinFont::HelveticaBoldOblique)?,
+            code: font(BuiltinFont::Courier)?,
+        };
+        let layer = doc.get_page(page).get_layer(layer);
+
+        let writer = Self { doc, layer, fonts, theme, y: PAGE_HEIGHT - MARGIN, at_page_top: true };
+        writer.paint_background();
+        Ok(writer)
+    }
+
+    /// A theme color, falling back to the text color and then black
+    fn color(&self, variable: &str) -> Color {
+        let rgb = [variable, "text-primary"]
+            .iter()
+            .filter_map(|name| self.theme.variables.get(*name))
+            .find_map(|value| parse_hex(value).ok());
+        match rgb {
+            Some(rgb) => Color::Rgb(Rgb::new(rgb.r as f32 / 255.0, rgb.g as f32 / 255.0, rgb.b as f32 / 255.0, None)),
+            None => Color::Rgb(Rgb::new(0.0, 0.0, 0.0, None)),
+        }
+    }
+
+    fn fill_rect(&self, variable: &str, left: f32, bottom: f32, right: f32, top: f32) {
+        self.layer.set_fill_color(self.color(variable));
+        self.layer.add_rect(Rect::new(Mm(left), Mm(bottom), Mm(right), Mm(top)));
+    }
+
+    fn paint_background(&self) {
+        if self.theme.variables.contains_key("bg-primary") {
+            self.fill_rect("bg-primary", 0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT);
+        }
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Content");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+        self.at_page_top = true;
+        self.paint_background();
+    }
+
+    /// Start a new page unless `height` mm still fits on this one
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < MARGIN && !self.at_page_top {
+            self.new_page();
+        }
+        self.at_page_top = false;
+    }
+
+    fn write_piece(&self, text: &str, style: Style, color: &str, size: f32, x: f32, baseline: f32) {
+        self.layer.set_fill_color(self.color(color));
+        self.layer.use_text(text, size, Mm(x), Mm(baseline), self.fonts.get(style));
+    }
+
+    /// Write runs word-wrapped between `left` and the right margin
+    ///
+    /// A list `marker` is drawn on the first line, one indent to the left.
+    fn write_runs(&mut self, runs: &[Run], size: f32, left: f32, block_color: &'static str, marker: Option<&str>) {
+        let line_height = size * LINE_SPACING * PT_TO_MM;
+        let max_width = PAGE_WIDTH - MARGIN - left;
+        let mut line: Vec<(String, Style, &'static str)> = Vec::new();
+        let mut width = 0.0;
+        let mut marker = marker;
+
+        let mut flush = |writer: &mut Self, line: &mut Vec<(String, Style, &'static str)>| {
+            writer.reserve(line_height);
+            let baseline = writer.y - size * PT_TO_MM;
+            if let Some(marker) = marker.take() {
+                writer.write_piece(marker, Style::default(), "accent-color", size, left - INDENT, baseline);
+            }
+            let mut x = left;
+            for (text, style, color) in line.drain(..) {
+                writer.write_piece(&text, style, color, size, x, baseline);
+                x += text_width(&text, style, size);
+            }
+            writer.y -= line_height;
+        };
+
+        for run in runs {
+            let color = run.color.unwrap_or(block_color);
+            for word in run.text.split_inclusive(' ') {
+                let word_width = text_width(word, run.style, size);
+                if width + word_width > max_width && !line.is_empty() {
+                    flush(self, &mut line);
+                    width = 0.0;
+                }
+                let word = if line.is_empty() { word.trim_start() } else { word };
+                width += text_width(word, run.style, size);
+                line.push((word.to_string(), run.style, color));
+            }
+        }
+        if !line.is_empty() || runs.is_empty() {
+            flush(self, &mut line);
+        }
+    }
+
+    fn write_block(&mut self, block: &Block, page_break_at_headings: bool) {
+        let body_line = BODY_SIZE * LINE_SPACING * PT_TO_MM;
+
+        match block {
+            Block::Heading(level, runs) => {
+                if *level == 1 && page_break_at_headings && !self.at_page_top {
+                    self.new_page();
+                }
+                let size = [24.0, 20.0, 16.0, 14.0, 12.0, 11.0][level - 1];
+                let runs: Vec<Run> = runs
+                    .iter()
+                    .map(|run| Run { text: run.text.clone(), style: Style { bold: true, ..run.style }, color: run.color })
+                    .collect();
+                let color = ["h1-color", "h2-color", "h3-color", "h4-color", "h5-color", "h6-color"][level - 1];
+                self.y -= if self.at_page_top { 0.0 } else { 2.0 };
+                self.write_runs(&runs, size, MARGIN, color, None);
+                self.y -= 1.5;
+            }
+            Block::Paragraph(runs) => self.write_runs(runs, BODY_SIZE, MARGIN, "text-primary", None),
+            Block::ListItem { level, marker, runs } => {
+                let left = MARGIN + (*level + 1) as f32 * INDENT;
+                self.write_runs(runs, BODY_SIZE, left, "text-primary", Some(marker));
+            }
+            Block::Quote(runs) => {
+                let top = self.y;
+                self.write_runs(runs, BODY_SIZE, MARGIN + INDENT, "blockquote-color", None);
+                let top = if self.y > top { PAGE_HEIGHT - MARGIN } else { top };
+                self.fill_rect("blockquote-border", MARGIN, self.y, MARGIN + 1.0, top);
+            }
+            Block::Code(text) => {
+                for line in text.lines() {
+                    self.reserve(body_line);
+                    self.fill_rect("code-bg", MARGIN, self.y - body_line, PAGE_WIDTH - MARGIN, self.y);
+                    let style = Style { code: true, ..Style::default() };
+                    self.write_piece(line, style, "code-color", BODY_SIZE - 1.0, MARGIN + 2.0, self.y - BODY_SIZE * PT_TO_MM);
+                    self.y -= body_line;
+                }
+            }
+            Block::Math(latex) => {
+                let style = Style { italic: true, ..Style::default() };
+                let width = text_width(latex, style, BODY_SIZE);
+                self.reserve(body_line);
+                let x = MARGIN.max((PAGE_WIDTH - width) / 2.0);
+                self.write_piece(latex, style, "h3-color", BODY_SIZE, x, self.y - BODY_SIZE * PT_TO_MM);
+                self.y -= body_line;
+            }
+            Block::Rule => {
+                self.reserve(body_line);
+                let middle = self.y - body_line / 2.0;
+                self.fill_rect("hr-color", MARGIN, middle - 0.15, PAGE_WIDTH - MARGIN, middle + 0.15);
+                self.y -= body_line;
+            }
+            Block::Blank => {
+                if !self.at_page_top {
+                    self.y -= body_line / 2.0;
+                }
+            }
+        }
+        self.y -= 1.0;
+    }
+
+    fn finish(self) -> Result<Vec<u8>, String> {
+        self.doc.save_to_bytes().map_err(|e| format!("Failed to build PDF: {}", e))
+    }
+}
+
+/// Lay out a document as PDF bytes
+fn render_pdf(content: &str, theme: &ThemeConfig, title: &str, page_break_at_headings: bool) -> Result<Vec<u8>, String> {
+    let mut writer = PdfWriter::new(title, theme)?;
+    for block in document_blocks(content) {
+        writer.write_block(&block, page_break_at_headings);
+    }
+    writer.finish()
+}
+
+/// Export a document to a PDF file styled with the theme's colors
+///
+/// With `page_break_at_headings`, each top-level heading after the first
+/// starts a new page. Only Latin text is supported by the built-in fonts.
+#[tauri::command]
+pub fn export_document_pdf(
+    content: String,
+    theme: ThemeConfig,
+    dest_path: String,
+    page_break_at_headings: Option<bool>,
+) -> Result<(), String> {
+    let title = Path::new(&dest_path)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Document")
+        .to_string();
+
+    let pdf = render_pdf(&content, &theme, &title, page_break_at_headings.unwrap_or(false))?;
+    fs::write(&dest_path, pdf).map_err(|e| format!("Failed to write PDF file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_default_light_theme;
+    use printpdf::lopdf;
+
+    const DOCUMENT: &str = "# Report\n\nSome **bold** and *italic* text.\n\n- one\n- two\n\n```\ncode\n```\n\n# Appendix\n\n> quoted\n";
+
+    fn page_count(pdf: &[u8]) -> usize {
+        lopdf::Document::load_mem(pdf).unwrap().get_pages().len()
+    }
+
+    #[test]
+    fn test_export_writes_pdf() {
+        let path = std::env::temp_dir().join(format!("loom-export-{}.pdf", std::process::id()));
+        let result = export_document_pdf(
+            DOCUMENT.to_string(),
+            get_default_light_theme(),
+            path.to_string_lossy().to_string(),
+            None,
+        );
+        let pdf = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(result.is_ok());
+        assert!(pdf.starts_with(b"%PDF"));
+        assert_eq!(page_count(&pdf), 1);
+    }
+
+    #[test]
+    fn test_page_break_at_top_level_headings() {
+        let pdf = render_pdf(DOCUMENT, &get_default_light_theme(), "Report", true).unwrap();
+
+        assert_eq!(page_count(&pdf), 2);
+    }
+
+    #[test]
+    fn test_document_blocks() {
+        let blocks = document_blocks(DOCUMENT);
+        let kinds: Vec<&str> = blocks
+            .iter()
+            .map(|block| match block {
+                Block::Heading(..) => "heading",
+                Block::Paragraph(_) => "paragraph",
+                Block::ListItem { .. } => "item",
+                Block::Quote(_) => "quote",
+                Block::Code(_) => "code",
+                Block::Math(_) => "math",
+                Block::Rule => "rule",
+                Block::Blank => "blank",
+            })
+            .collect();
+
+        assert_eq!(
+            kinds,
+            ["heading", "blank", "paragraph", "blank", "item", "item", "blank", "code", "blank", "heading", "blank", "quote"]
+        );
+    }
+
+    #[test]
+    fn test_list_levels_follow_nesting() {
+        let levels: Vec<usize> = document_blocks("- a\n  - b\n    - c\n- d\n    - e\n")
+            .iter()
+            .filter_map(|block| match block {
+                Block::ListItem { level, .. } => Some(*level),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(levels, [0, 1, 2, 0, 1]);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::config::get_loom_dir;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A plugin's `plugin.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+    /// Script to load, relative to the plugin's folder
+    pub entry: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn read_manifest(path: &Path) -> Result<PluginManifest, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("Failed to read manifest: {}", e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse manifest: {}", e))
+}
+
+/// Every readable manifest in `.loom/plugins/*/plugin.json`, with its path
+fn plugin_manifests(folder_path: Option<String>) -> Result<Vec<(PathBuf, PluginManifest)>, String> {
+    let plugins_dir = get_loom_dir(folder_path)?.join("plugins");
+    if !plugins_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&plugins_dir)
+        .map_err(|e| format!("Failed to read plugins directory: {}", e))?;
+
+    let mut manifests: Vec<(PathBuf, PluginManifest)> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("plugin.json"))
+        .filter(|path| path.is_file())
+        .filter_map(|path| match read_manifest(&path) {
+            Ok(manifest) => Some((path, manifest)),
+            Err(e) => {
+                eprintln!("Skipping plugin at {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect();
+    manifests.sort_by_key(|(_, manifest)| manifest.name.to_lowercase());
+
+    Ok(manifests)
+}
+
+/// List the installed plugins, skipping any with a malformed manifest
+pub fn list_plugins(folder_path: Option<String>) -> Result<Vec<PluginManifest>, String> {
+    Ok(plugin_manifests(folder_path)?
+        .into_iter()
+        .map(|(_, manifest)| manifest)
+        .collect())
+}
+
+/// Turn a plugin on or off by writing `enabled` to its manifest
+///
+/// Other fields in the manifest are kept as written.
+pub fn set_plugin_enabled(folder_path: Option<String>, name: &str, enabled: bool) -> Result<(), String> {
+    let (path, _) = plugin_manifests(folder_path)?
+        .into_iter()
+        .find(|(_, manifest)| manifest.name == name)
+        .ok_or_else(|| format!("Plugin '{}' not found", name))?;
+
+    let json = fs::read_to_string(&path).map_err(|e| format!("Failed to read manifest: {}", e))?;
+    let mut manifest: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse manifest: {}", e))?;
+    manifest["enabled"] = serde_json::Value::Bool(enabled);
+
+    let json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write manifest: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_plugin(dir: &Path, folder: &str, manifest: &str) {
+        let plugin_dir = dir.join(".loom").join("plugins").join(folder);
+        fs::create_dir_all(&plugin_dir).unwrap();
+        fs::write(plugin_dir.join("plugin.json"), manifest).unwrap();
+    }
+
+    #[test]
+    fn test_list_and_toggle_plugins() {
+        let dir = std::env::temp_dir().join(format!("loom-plugins-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        write_plugin(&dir, "word-goal", r#"{ "name": "Word Goal", "version": "1.0.0", "entry": "main.js" }"#);
+        write_plugin(
+            &dir,
+            "outline",
+            r#"{ "name": "Outline", "version": "0.2.1", "entry": "index.js", "enabled": false, "author": "me" }"#,
+        );
+        write_plugin(&dir, "broken", r#"{ "name": "Broken" }"#);
+        let folder = Some(dir.to_string_lossy().to_string());
+
+        let listed = list_plugins(folder.clone()).unwrap();
+        set_plugin_enabled(folder.clone(), "Outline", true).unwrap();
+        let missing = set_plugin_enabled(folder.clone(), "Nope", true);
+        let toggled = list_plugins(folder).unwrap();
+        let outline_json = fs::read_to_string(dir.join(".loom/plugins/outline/plugin.json")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<(&str, bool)> = listed.iter().map(|p| (p.name.as_str(), p.enabled)).collect();
+        assert_eq!(names, [("Outline", false), ("Word Goal", true)]);
+        assert_eq!(listed[1].entry, "main.js");
+        assert!(toggled[0].enabled);
+        assert!(outline_json.contains("\"author\": \"me\""));
+        assert_eq!(missing, Err("Plugin 'Nope' not found".to_string()));
+    }
+}
@@ -1,7 +1,14 @@
+use crate::file_watcher::WatchEventSink;
+use crate::markdown::tag_lines;
+use crate::text_file::read_normalized;
+use ignore::WalkBuilder;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::{Path};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, State};
 use walkdir::WalkDir;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -10,9 +17,12 @@ pub struct SearchOptions {
     pub case_sensitive: bool,
     pub whole_word: bool,
     pub use_regex: bool,
+    /// Skip files and folders excluded by `.gitignore` files
+    #[serde(default)]
+    pub respect_gitignore: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchMatch {
     pub line: usize,
@@ -22,13 +32,67 @@ pub struct SearchMatch {
     pub line_text: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct FileSearchResult {
     pub file_path: String,
     pub matches: Vec<SearchMatch>,
 }
 
+/// Payload of the `search-result` event, tagged with the search it belongs to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultEvent {
+    pub search_id: u64,
+    #[serde(flatten)]
+    pub result: FileSearchResult,
+}
+
+/// Payload of the `search-complete` event sent after a streaming search
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchComplete {
+    pub search_id: u64,
+    pub file_count: usize,
+    pub match_count: usize,
+    pub cancelled: bool,
+    pub error: Option<String>,
+}
+
+// Cancellation flags of the running directory searches, by search id
+pub type SearchCancelHandle = Arc<Mutex<HashMap<u64, Arc<AtomicBool>>>>;
+
+pub fn create_search_cancel_state() -> SearchCancelHandle {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+/// Register a search and return its cancellation flag
+///
+/// Starting a search under an id that is still running cancels the old one.
+fn start_search(cancel_state: &SearchCancelHandle, search_id: u64) -> Arc<AtomicBool> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    if let Some(old) = cancel_state.lock().unwrap().insert(search_id, Arc::clone(&cancel)) {
+        old.store(true, Ordering::Relaxed);
+    }
+    cancel
+}
+
+/// Forget a finished search, unless its id has been reused since
+fn finish_search(cancel_state: &SearchCancelHandle, search_id: u64, cancel: &Arc<AtomicBool>) {
+    let mut running = cancel_state.lock().unwrap();
+    if running.get(&search_id).is_some_and(|current| Arc::ptr_eq(current, cancel)) {
+        running.remove(&search_id);
+    }
+}
+
+/// A file using a tag and the 1-based lines it is used on
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTagHits {
+    pub file_path: String,
+    pub lines: Vec<usize>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReplaceResult {
@@ -36,26 +100,26 @@ pub struct ReplaceResult {
     pub replaced_count: usize,
 }
 
-/// Search for a query in text content
-#[tauri::command]
-pub fn search_in_content(
-    query: String,
-    content: String,
-    options: SearchOptions,
-) -> Result<Vec<SearchMatch>, String> {
-    if query.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let mut matches = Vec::new();
+/// One match of a replace, with its line before and after
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplacePreview {
+    pub line: usize,
+    pub column: usize,
+    pub line_text: String,
+    /// The line with only this match replaced
+    pub replaced_line: String,
+}
 
+/// Compile the regex a search runs, applying the whole word and case flags
+fn build_search_regex(query: &str, options: &SearchOptions) -> Result<Regex, String> {
     // Build the search pattern
     let pattern = if options.use_regex {
-        query.clone()
+        query.to_string()
     } else if options.whole_word {
-        format!(r"\b{}\b", regex::escape(&query))
+        format!(r"\b{}\b", regex::escape(query))
     } else {
-        regex::escape(&query)
+        regex::escape(query)
     };
 
     // Create regex with appropriate flags
@@ -65,15 +129,72 @@ pub fn search_in_content(
         format!("(?i){}", pattern)
     };
 
-    let re = Regex::new(&regex_pattern).map_err(|e| e.to_string())?;
+    Regex::new(&regex_pattern).map_err(regex_error_message)
+}
+
+/// A one-line description of why a pattern doesn't compile
+///
+/// Syntax errors from the regex crate span several lines, quoting the
+/// pattern with a caret under the problem; only the reason is kept.
+fn regex_error_message(error: regex::Error) -> String {
+    match error {
+        regex::Error::Syntax(message) => {
+            let reason = message
+                .lines()
+                .find_map(|line| line.strip_prefix("error: "))
+                .unwrap_or(message.trim());
+            format!("Invalid regular expression: {}", reason)
+        }
+        regex::Error::CompiledTooBig(_) => "Regular expression is too large".to_string(),
+        e => format!("Invalid regular expression: {}", e),
+    }
+}
+
+/// Matches of a search regex in a line, skipping empty ones
+///
+/// Patterns like `a*` or `\b` also match the empty string between
+/// characters, which would give matches with nothing to highlight.
+fn non_empty_matches<'a>(re: &'a Regex, line: &'a str) -> impl Iterator<Item = regex::Match<'a>> {
+    re.find_iter(line).filter(|mat| !mat.is_empty())
+}
+
+/// Check that a regex typed into the search box compiles
+///
+/// Uses the same flags as a case-insensitive regex search, so a pattern
+/// that passes here won't fail once searched.
+#[tauri::command]
+pub fn validate_search_regex(pattern: String) -> Result<(), String> {
+    let options = SearchOptions {
+        case_sensitive: false,
+        whole_word: false,
+        use_regex: true,
+        respect_gitignore: false,
+    };
+    build_search_regex(&pattern, &options).map(|_| ())
+}
+
+/// Search for a query in text content
+#[tauri::command]
+pub fn search_in_content(
+    query: String,
+    content: String,
+    options: SearchOptions,
+) -> Result<Vec<SearchMatch>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut matches = Vec::new();
+
+    let re = build_search_regex(&query, &options)?;
 
-    // Search line by line
+    // Search line by line, reporting columns and lengths in characters rather than bytes
     for (line_num, line) in content.lines().enumerate() {
-        for mat in re.find_iter(line) {
+        for mat in non_empty_matches(&re, line) {
             matches.push(SearchMatch {
                 line: line_num + 1,
-                column: mat.start() + 1,
-                length: mat.end() - mat.start(),
+                column: line[..mat.start()].chars().count() + 1,
+                length: mat.as_str().chars().count(),
                 text: mat.as_str().to_string(),
                 line_text: line.to_string(),
             });
@@ -98,23 +219,7 @@ pub fn replace_in_content(
         });
     }
 
-    // Build the search pattern
-    let pattern = if options.use_regex {
-        query.clone()
-    } else if options.whole_word {
-        format!(r"\b{}\b", regex::escape(&query))
-    } else {
-        regex::escape(&query)
-    };
-
-    // Create regex with appropriate flags
-    let regex_pattern = if options.case_sensitive {
-        pattern
-    } else {
-        format!("(?i){}", pattern)
-    };
-
-    let re = Regex::new(&regex_pattern).map_err(|e| e.to_string())?;
+    let re = build_search_regex(&query, &options)?;
 
     // Count matches before replacement
     let count = re.find_iter(&content).count();
@@ -128,55 +233,112 @@ pub fn replace_in_content(
     })
 }
 
-/// Search across all files in a directory
+/// Preview a replace without changing anything
+///
+/// Gives one entry per match, line by line like `search_in_content`, with
+/// capture groups in the replacement expanded.
 #[tauri::command]
-pub fn search_in_directory(
+pub fn preview_replace(
     query: String,
-    dir_path: String,
+    replacement: String,
+    content: String,
     options: SearchOptions,
-) -> Result<Vec<FileSearchResult>, String> {
+) -> Result<Vec<ReplacePreview>, String> {
     if query.is_empty() {
         return Ok(Vec::new());
     }
 
-    let path = Path::new(&dir_path);
+    let re = build_search_regex(&query, &options)?;
+    let mut previews = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        for caps in re.captures_iter(line) {
+            let mat = caps.get(0).unwrap();
+            let mut replaced_line = line[..mat.start()].to_string();
+            caps.expand(&replacement, &mut replaced_line);
+            replaced_line.push_str(&line[mat.end()..]);
+
+            previews.push(ReplacePreview {
+                line: line_num + 1,
+                column: line[..mat.start()].chars().count() + 1,
+                line_text: line.to_string(),
+                replaced_line,
+            });
+        }
+    }
+
+    Ok(previews)
+}
+
+/// Whether a path has a markdown extension (`.md` or `.markdown`, any case)
+fn is_markdown_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .is_some_and(|ext| ext == "md" || ext == "markdown")
+}
+
+/// Every path below `path`, pruning what `.gitignore` files exclude if asked
+fn walk_files(path: &Path, respect_gitignore: bool) -> Box<dyn Iterator<Item = PathBuf>> {
+    if respect_gitignore {
+        // Hidden files are still walked, like the plain walk; only ignore rules prune
+        let walker = WalkBuilder::new(path)
+            .hidden(false)
+            .git_global(false)
+            .require_git(false)
+            .build();
+        Box::new(walker.filter_map(|e| e.ok()).map(|entry| entry.into_path()))
+    } else {
+        let walker = WalkDir::new(path).follow_links(false).into_iter();
+        Box::new(walker.filter_map(|e| e.ok()).map(|entry| entry.into_path()))
+    }
+}
+
+/// Search every markdown file under a directory, passing each file with
+/// matches to `on_result` as soon as it has been searched
+///
+/// `cancel` is checked before each file; once it is set the walk stops and
+/// whatever was found so far stands.
+fn walk_directory_search(
+    query: &str,
+    dir_path: &str,
+    options: &SearchOptions,
+    cancel: &AtomicBool,
+    mut on_result: impl FnMut(FileSearchResult),
+) -> Result<(), String> {
+    if query.is_empty() {
+        return Ok(());
+    }
+
+    let path = Path::new(dir_path);
     if !path.exists() || !path.is_dir() {
         return Err("Directory does not exist".to_string());
     }
 
-    let mut results = Vec::new();
-
     // Walk through directory
-    for entry in WalkDir::new(path)
-        .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let entry_path = entry.path();
-
-        // Only search in .md files
-        if !entry_path.is_file() {
-            continue;
+    for entry_path in walk_files(path, options.respect_gitignore) {
+        if cancel.load(Ordering::Relaxed) {
+            break;
         }
 
-        if let Some(ext) = entry_path.extension() {
-            if ext != "md" {
-                continue;
-            }
-        } else {
+        let entry_path = entry_path.as_path();
+
+        // Only search in markdown files
+        if !entry_path.is_file() || !is_markdown_file(entry_path) {
             continue;
         }
 
         // Read file content
-        let content = match fs::read_to_string(entry_path) {
-            Ok(c) => c,
+        // Line endings are normalized so columns never count a trailing \r
+        let content = match read_normalized(entry_path) {
+            Ok((c, _)) => c,
             Err(_) => continue, // Skip files we can't read
         };
 
         // Search in content
-        match search_in_content(query.clone(), content, options.clone()) {
+        match search_in_content(query.to_string(), content, options.clone()) {
             Ok(matches) if !matches.is_empty() => {
-                results.push(FileSearchResult {
+                on_result(FileSearchResult {
                     file_path: entry_path.to_string_lossy().to_string(),
                     matches,
                 });
@@ -185,12 +347,183 @@ pub fn search_in_directory(
         }
     }
 
-    Ok(results)
+    Ok(())
+}
+
+/// Count matches across the markdown files in a directory, as
+/// (match count, file count)
+///
+/// Counts the same matches as `search_in_directory` without collecting
+/// them. Only files with at least one match are counted.
+#[tauri::command]
+pub fn count_matches_in_directory(
+    query: String,
+    dir_path: String,
+    options: SearchOptions,
+) -> Result<(usize, usize), String> {
+    if query.is_empty() {
+        return Ok((0, 0));
+    }
+
+    let path = Path::new(&dir_path);
+    if !path.exists() || !path.is_dir() {
+        return Err("Directory does not exist".to_string());
+    }
+
+    let re = build_search_regex(&query, &options)?;
+    let (mut match_count, mut file_count) = (0, 0);
+
+    for entry_path in walk_files(path, options.respect_gitignore) {
+        if !entry_path.is_file() || !is_markdown_file(&entry_path) {
+            continue;
+        }
+        let Ok((content, _)) = read_normalized(&entry_path) else {
+            continue;
+        };
+
+        let matches: usize = content.lines().map(|line| non_empty_matches(&re, line).count()).sum();
+        if matches > 0 {
+            match_count += matches;
+            file_count += 1;
+        }
+    }
+
+    Ok((match_count, file_count))
+}
+
+/// Search across all files in a directory
+///
+/// Runs on a blocking worker thread, so `cancel_search` with the same
+/// `search_id` can stop it; the results found so far are returned.
+#[tauri::command]
+pub async fn search_in_directory(
+    query: String,
+    dir_path: String,
+    options: SearchOptions,
+    search_id: Option<u64>,
+    cancel_state: State<'_, SearchCancelHandle>,
+) -> Result<Vec<FileSearchResult>, String> {
+    let search_id = search_id.unwrap_or_default();
+    let cancel_state = Arc::clone(&cancel_state);
+    let cancel = start_search(&cancel_state, search_id);
+
+    let worker_cancel = Arc::clone(&cancel);
+    let searched = tauri::async_runtime::spawn_blocking(move || {
+        let mut results = Vec::new();
+        walk_directory_search(&query, &dir_path, &options, &worker_cancel, |result| results.push(result))
+            .map(|_| results)
+    })
+    .await;
+    finish_search(&cancel_state, search_id, &cancel);
+
+    searched.map_err(|e| format!("Search failed: {}", e))?
+}
+
+/// Search a directory, sending a `search-result` event for each file with
+/// matches and a final `search-complete` event
+fn stream_directory_search<S: WatchEventSink>(
+    search_id: u64,
+    query: &str,
+    dir_path: &str,
+    options: &SearchOptions,
+    cancel: &AtomicBool,
+    sink: &S,
+) {
+    let mut complete = SearchComplete {
+        search_id,
+        file_count: 0,
+        match_count: 0,
+        cancelled: false,
+        error: None,
+    };
+
+    let walked = walk_directory_search(query, dir_path, options, cancel, |result| {
+        complete.file_count += 1;
+        complete.match_count += result.matches.len();
+        sink.send_event("search-result", SearchResultEvent { search_id, result });
+    });
+    complete.cancelled = cancel.load(Ordering::Relaxed);
+    complete.error = walked.err();
+
+    sink.send_event("search-complete", complete);
+}
+
+/// Search across all files in a directory, streaming results as events
+///
+/// Returns straight away; the search runs on a background thread so the
+/// frontend can show results as they arrive. Every event carries
+/// `search_id`, so results of an older search can be told apart.
+#[tauri::command]
+pub fn search_in_directory_streaming(
+    query: String,
+    dir_path: String,
+    options: SearchOptions,
+    search_id: u64,
+    app_handle: AppHandle,
+    cancel_state: State<SearchCancelHandle>,
+) -> Result<(), String> {
+    let cancel_state = Arc::clone(&cancel_state);
+    let cancel = start_search(&cancel_state, search_id);
+
+    std::thread::spawn(move || {
+        stream_directory_search(search_id, &query, &dir_path, &options, &cancel, &app_handle);
+        finish_search(&cancel_state, search_id, &cancel);
+    });
+    Ok(())
+}
+
+/// Stop a directory search after the file it is on
+///
+/// Without a `search_id` every running search is stopped.
+#[tauri::command]
+pub fn cancel_search(search_id: Option<u64>, cancel_state: State<SearchCancelHandle>) {
+    cancel_searches(&cancel_state, search_id);
+}
+
+fn cancel_searches(cancel_state: &SearchCancelHandle, search_id: Option<u64>) {
+    let running = cancel_state.lock().unwrap();
+    match search_id {
+        Some(id) => {
+            if let Some(cancel) = running.get(&id) {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+        None => running.values().for_each(|cancel| cancel.store(true, Ordering::Relaxed)),
+    }
+}
+
+/// Find the markdown files that use a `#tag`
+///
+/// Tags are detected the same way as `extract_tags`, matched ignoring case,
+/// and tags nested below `tag` count too. Hidden folders such as `.loom`
+/// are skipped.
+#[tauri::command]
+pub fn find_files_by_tag(dir_path: String, tag: String) -> Vec<FileTagHits> {
+    WalkDir::new(&dir_path)
+        .follow_links(false)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|entry| entry.file_type().is_file() && is_markdown_file(entry.path()))
+        .filter_map(|entry| {
+            let (content, _) = read_normalized(entry.path()).ok()?;
+            let lines = tag_lines(&content, &tag);
+            if lines.is_empty() {
+                return None;
+            }
+            Some(FileTagHits {
+                file_path: entry.path().to_string_lossy().to_string(),
+                lines,
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
 
     #[test]
     fn test_search_case_sensitive() {
@@ -199,6 +532,7 @@ mod tests {
             case_sensitive: true,
             whole_word: false,
             use_regex: false,
+            respect_gitignore: false,
         };
 
         let matches = search_in_content("Hello".to_string(), content, options).unwrap();
@@ -213,6 +547,7 @@ mod tests {
             case_sensitive: false,
             whole_word: false,
             use_regex: false,
+            respect_gitignore: false,
         };
 
         let matches = search_in_content("hello".to_string(), content, options).unwrap();
@@ -226,12 +561,100 @@ mod tests {
             case_sensitive: false,
             whole_word: true,
             use_regex: false,
+            respect_gitignore: false,
         };
 
         let matches = search_in_content("hello".to_string(), content, options).unwrap();
         assert_eq!(matches.len(), 1);
     }
 
+    #[test]
+    fn test_search_column_counts_characters() {
+        let content = "café naïve 日本 word".to_string();
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            respect_gitignore: false,
+        };
+
+        let matches = search_in_content("word".to_string(), content.clone(), options.clone()).unwrap();
+        assert_eq!(matches[0].column, 15);
+        assert_eq!(matches[0].length, 4);
+
+        let matches = search_in_content("日本".to_string(), content, options).unwrap();
+        assert_eq!(matches[0].column, 12);
+        assert_eq!(matches[0].length, 2);
+    }
+
+    #[test]
+    fn test_preview_literal_replace() {
+        let content = "cat and cat\nno match\nCat".to_string();
+        let options = SearchOptions {
+            case_sensitive: true,
+            whole_word: false,
+            use_regex: false,
+            respect_gitignore: false,
+        };
+
+        let previews = preview_replace("cat".to_string(), "dog".to_string(), content, options).unwrap();
+
+        let changes: Vec<(usize, usize, &str)> =
+            previews.iter().map(|p| (p.line, p.column, p.replaced_line.as_str())).collect();
+        assert_eq!(changes, [(1, 1, "dog and cat"), (1, 9, "cat and dog")]);
+        assert!(previews.iter().all(|p| p.line_text == "cat and cat"));
+    }
+
+    #[test]
+    fn test_preview_regex_groups() {
+        let content = "Date: 2024-03-15".to_string();
+        let options = SearchOptions {
+            case_sensitive: true,
+            whole_word: false,
+            use_regex: true,
+            respect_gitignore: false,
+        };
+
+        let previews =
+            preview_replace(r"(\d{4})-(\d{2})-(\d{2})".to_string(), "$3/$2/$1".to_string(), content, options).unwrap();
+
+        assert_eq!(
+            previews,
+            [ReplacePreview {
+                line: 1,
+                column: 7,
+                line_text: "Date: 2024-03-15".to_string(),
+                replaced_line: "Date: 15/03/2024".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_search_skips_empty_regex_matches() {
+        let options = SearchOptions {
+            case_sensitive: true,
+            whole_word: false,
+            use_regex: true,
+            respect_gitignore: false,
+        };
+
+        let stars = search_in_content("a*".to_string(), "baaab\nnone".to_string(), options.clone()).unwrap();
+        let boundaries = search_in_content(r"\b".to_string(), "two words".to_string(), options).unwrap();
+
+        assert_eq!(stars.len(), 1);
+        assert_eq!((stars[0].line, stars[0].column, stars[0].length), (1, 2, 3));
+        assert!(boundaries.is_empty());
+    }
+
+    #[test]
+    fn test_validate_search_regex() {
+        assert_eq!(validate_search_regex(r"^#+\s+\w+".to_string()), Ok(()));
+        assert_eq!(
+            validate_search_regex("(unclosed".to_string()),
+            Err("Invalid regular expression: unclosed group".to_string())
+        );
+    }
+
     #[test]
     fn test_replace() {
         let content = "Hello World\nHello Universe".to_string();
@@ -239,6 +662,7 @@ mod tests {
             case_sensitive: false,
             whole_word: false,
             use_regex: false,
+            respect_gitignore: false,
         };
 
         let result = replace_in_content(
@@ -251,4 +675,193 @@ mod tests {
         assert_eq!(result.replaced_count, 2);
         assert_eq!(result.new_content, "Hi World\nHi Universe");
     }
+
+    #[test]
+    fn test_count_matches_in_directory() {
+        let dir = std::env::temp_dir().join(format!("loom-search-count-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.md"), "needle needle\nhay\nneedle").unwrap();
+        fs::write(dir.join("b.md"), "Needle in the hay").unwrap();
+        fs::write(dir.join("c.md"), "only hay").unwrap();
+
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            respect_gitignore: false,
+        };
+        let counts = count_matches_in_directory("needle".to_string(), dir.to_string_lossy().to_string(), options);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(counts, Ok((4, 2)));
+    }
+
+    #[test]
+    fn test_search_directory_extension_case() {
+        let dir = std::env::temp_dir().join(format!("loom-search-ext-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("NOTES.MD"), "needle").unwrap();
+        fs::write(dir.join("guide.Markdown"), "needle").unwrap();
+        fs::write(dir.join("other.txt"), "needle").unwrap();
+
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            respect_gitignore: false,
+        };
+        let mut results = Vec::new();
+        walk_directory_search("needle", &dir.to_string_lossy(), &options, &AtomicBool::new(false), |r| results.push(r))
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut files: Vec<String> = results
+            .iter()
+            .map(|r| Path::new(&r.file_path).file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["NOTES.MD", "guide.Markdown"]);
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingSink(std::sync::Arc<std::sync::Mutex<Vec<(String, serde_json::Value)>>>);
+
+    impl WatchEventSink for RecordingSink {
+        fn send_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+            self.0.lock().unwrap().push((event.to_string(), serde_json::to_value(payload).unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_streaming_search_emits_per_file() {
+        let dir = std::env::temp_dir().join(format!("loom-search-stream-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.md"), "needle\nneedle").unwrap();
+        fs::write(dir.join("sub").join("b.md"), "one needle").unwrap();
+        fs::write(dir.join("c.md"), "nothing here").unwrap();
+
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            respect_gitignore: false,
+        };
+        let sink = RecordingSink::default();
+        stream_directory_search(7, "needle", &dir.to_string_lossy(), &options, &AtomicBool::new(false), &sink);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let events = sink.0.lock().unwrap();
+        let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["search-result", "search-result", "search-complete"]);
+        assert!(events.iter().all(|(_, payload)| payload["searchId"] == 7));
+        assert!(events[0].1["filePath"].is_string());
+        assert_eq!(
+            events[2].1,
+            serde_json::json!({ "searchId": 7, "fileCount": 2, "matchCount": 3, "cancelled": false, "error": null })
+        );
+    }
+
+    #[test]
+    fn test_cancel_only_stops_the_given_search() {
+        let state = create_search_cancel_state();
+        let first = start_search(&state, 1);
+        let second = start_search(&state, 2);
+
+        cancel_searches(&state, Some(1));
+        assert!(first.load(Ordering::Relaxed));
+        assert!(!second.load(Ordering::Relaxed));
+
+        // Reusing an id cancels the older search, and finishing that older
+        // search leaves the new one registered
+        let replacement = start_search(&state, 2);
+        assert!(second.load(Ordering::Relaxed));
+        finish_search(&state, 2, &second);
+        cancel_searches(&state, None);
+        assert!(replacement.load(Ordering::Relaxed));
+
+        finish_search(&state, 1, &first);
+        finish_search(&state, 2, &replacement);
+        assert!(state.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_stops_directory_walk() {
+        let dir = std::env::temp_dir().join(format!("loom-search-cancel-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.md", "b.md", "c.md"] {
+            fs::write(dir.join(name), "needle").unwrap();
+        }
+
+        let options = SearchOptions {
+            case_sensitive: false,
+            whole_word: false,
+            use_regex: false,
+            respect_gitignore: false,
+        };
+        let cancel = AtomicBool::new(false);
+        let mut results = Vec::new();
+        walk_directory_search("needle", &dir.to_string_lossy(), &options, &cancel, |result| {
+            results.push(result);
+            cancel.store(true, Ordering::Relaxed);
+        })
+        .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_find_files_by_tag() {
+        let dir = std::env::temp_dir().join(format!("loom-search-tags-{}", std::process::id()));
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        fs::create_dir_all(dir.join(".loom")).unwrap();
+        fs::write(dir.join("a.md"), "# Plan\nWork on #Project today").unwrap();
+        fs::write(dir.join("notes").join("b.md"), "Intro\n\n#project/alpha and #todo").unwrap();
+        fs::write(dir.join("notes").join("c.md"), "```\n#project\n```\n#projects").unwrap();
+        fs::write(dir.join(".loom").join("d.md"), "#project").unwrap();
+
+        let root = dir.to_string_lossy().to_string();
+        let summarize = |hits: Vec<FileTagHits>| {
+            hits.into_iter()
+                .map(|h| (Path::new(&h.file_path).file_name().unwrap().to_string_lossy().to_string(), h.lines))
+                .collect::<Vec<_>>()
+        };
+        let exact = summarize(find_files_by_tag(root.clone(), "#todo".to_string()));
+        let prefix = summarize(find_files_by_tag(root, "project".to_string()));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(exact, [("b.md".to_string(), vec![3])]);
+        assert_eq!(prefix, [("a.md".to_string(), vec![2]), ("b.md".to_string(), vec![3])]);
+    }
+
+    #[test]
+    fn test_search_directory_respects_gitignore() {
+        let dir = std::env::temp_dir().join(format!("loom-search-gitignore-{}", std::process::id()));
+        fs::create_dir_all(dir.join("build")).unwrap();
+        fs::write(dir.join(".gitignore"), "build/\n").unwrap();
+        fs::write(dir.join("notes.md"), "needle").unwrap();
+        fs::write(dir.join("build").join("generated.md"), "needle").unwrap();
+
+        let search = |respect_gitignore| {
+            let options = SearchOptions {
+                case_sensitive: false,
+                whole_word: false,
+                use_regex: false,
+                respect_gitignore,
+            };
+            let mut files = Vec::new();
+            walk_directory_search("needle", &dir.to_string_lossy(), &options, &AtomicBool::new(false), |r| {
+                files.push(Path::new(&r.file_path).file_name().unwrap().to_string_lossy().to_string())
+            })
+            .unwrap();
+            files.sort();
+            files
+        };
+        let everything = search(false);
+        let respected = search(true);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(everything, ["generated.md", "notes.md"]);
+        assert_eq!(respected, ["notes.md"]);
+    }
 }
@@ -0,0 +1,121 @@
+use crate::config::get_loom_dir;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Open tabs and layout restored when a folder is reopened
+///
+/// Kept in `.loom/session.json` rather than config.json, so a corrupt
+/// session never stops the config from loading.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionState {
+    /// Paths of the open files, in tab order
+    pub open_files: Vec<String>,
+    pub active_file: Option<String>,
+    /// Scroll offset in pixels for each open file
+    pub scroll_positions: HashMap<String, f64>,
+    pub sidebar_width: Option<u32>,
+}
+
+/// Save the session for a folder
+pub fn save_session(folder_path: Option<String>, session: &SessionState) -> Result<(), String> {
+    let loom_dir = get_loom_dir(folder_path)?;
+    fs::create_dir_all(&loom_dir)
+        .map_err(|e| format!("Failed to create .loom directory: {}", e))?;
+
+    let json = serde_json::to_string_pretty(session)
+        .map_err(|e| format!("Failed to serialize session: {}", e))?;
+    fs::write(loom_dir.join("session.json"), json)
+        .map_err(|e| format!("Failed to write session file: {}", e))
+}
+
+/// Load the session for a folder, dropping files that no longer exist
+///
+/// Relative paths are resolved against the folder, if there is one, and
+/// otherwise used as they are. A missing or corrupt
+/// session file gives an empty session.
+pub fn load_session(folder_path: Option<String>) -> Result<SessionState, String> {
+    // Without a folder, saved paths are absolute
+    let folder = folder_path.clone().unwrap_or_default();
+    let session_path = get_loom_dir(folder_path)?.join("session.json");
+    if !session_path.exists() {
+        return Ok(SessionState::default());
+    }
+
+    let parsed = fs::read_to_string(&session_path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str::<SessionState>(&json).map_err(|e| e.to_string()));
+    let mut session = match parsed {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Ignoring unreadable session file {}: {}", session_path.display(), e);
+            return Ok(SessionState::default());
+        }
+    };
+
+    let exists = |path: &str| Path::new(&folder).join(path).is_file();
+    session.open_files.retain(|path| exists(path));
+    session.active_file = session.active_file.filter(|path| exists(path));
+    session.scroll_positions.retain(|path, _| exists(path));
+
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_folder(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("loom-session-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = temp_folder("round-trip");
+        let folder = Some(dir.to_string_lossy().to_string());
+        fs::write(dir.join("a.md"), "").unwrap();
+        let b = dir.join("b.md").to_string_lossy().to_string();
+        fs::write(&b, "").unwrap();
+        let session = SessionState {
+            open_files: vec!["a.md".to_string(), b.clone()],
+            active_file: Some(b.clone()),
+            scroll_positions: HashMap::from([(b, 120.5)]),
+            sidebar_width: Some(280),
+        };
+
+        save_session(folder.clone(), &session).unwrap();
+        let loaded = load_session(folder);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded, Ok(session));
+    }
+
+    #[test]
+    fn test_missing_files_are_pruned() {
+        let dir = temp_folder("prune");
+        let folder = Some(dir.to_string_lossy().to_string());
+        fs::write(dir.join("kept.md"), "").unwrap();
+        let session = SessionState {
+            open_files: vec!["kept.md".to_string(), "gone.md".to_string()],
+            active_file: Some("gone.md".to_string()),
+            scroll_positions: HashMap::from([("kept.md".to_string(), 10.0), ("gone.md".to_string(), 20.0)]),
+            sidebar_width: None,
+        };
+
+        save_session(folder.clone(), &session).unwrap();
+        let loaded = load_session(folder.clone()).unwrap();
+        fs::write(dir.join(".loom").join("session.json"), "{ not json").unwrap();
+        let corrupt = load_session(folder);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.open_files, ["kept.md"]);
+        assert_eq!(loaded.active_file, None);
+        assert_eq!(loaded.scroll_positions, HashMap::from([("kept.md".to_string(), 10.0)]));
+        assert_eq!(corrupt, Ok(SessionState::default()));
+    }
+}
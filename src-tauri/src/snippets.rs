@@ -0,0 +1,83 @@
+use crate::config::get_loom_dir;
+use crate::templates::fill_placeholders;
+use chrono::{Local, NaiveDateTime};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+/// Load the snippets in `.loom/snippets.json`, keyed by trigger
+///
+/// A missing file means no snippets yet.
+pub fn get_snippets(folder_path: Option<String>) -> Result<BTreeMap<String, String>, String> {
+    let path = get_loom_dir(folder_path)?.join("snippets.json");
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read snippets file: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse snippets file: {}", e))
+}
+
+/// Add a snippet, or replace the body of an existing trigger
+pub fn save_snippet(folder_path: Option<String>, trigger: &str, body: String) -> Result<(), String> {
+    let trigger = trigger.trim();
+    if trigger.is_empty() {
+        return Err("Snippet trigger cannot be empty".to_string());
+    }
+
+    let loom_dir = get_loom_dir(folder_path.clone())?;
+    let mut snippets = get_snippets(folder_path)?;
+    snippets.insert(trigger.to_string(), body);
+
+    fs::create_dir_all(&loom_dir)
+        .map_err(|e| format!("Failed to create .loom directory: {}", e))?;
+    let json = serde_json::to_string_pretty(&snippets)
+        .map_err(|e| format!("Failed to serialize snippets: {}", e))?;
+    fs::write(loom_dir.join("snippets.json"), json)
+        .map_err(|e| format!("Failed to write snippets file: {}", e))
+}
+
+fn expand_snippet_at(folder_path: Option<String>, trigger: &str, now: NaiveDateTime) -> Result<Option<String>, String> {
+    let snippets = get_snippets(folder_path)?;
+    Ok(snippets
+        .get(trigger.trim())
+        .map(|body| fill_placeholders(body, &HashMap::new(), now)))
+}
+
+/// The body of a snippet with its `{{date}}`/`{{time}}` placeholders filled in,
+/// or `None` if no snippet has that trigger
+pub fn expand_snippet(folder_path: Option<String>, trigger: &str) -> Result<Option<String>, String> {
+    expand_snippet_at(folder_path, trigger, Local::now().naive_local())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_list_and_expand_snippets() {
+        let dir = std::env::temp_dir().join(format!("loom-snippets-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let folder = Some(dir.to_string_lossy().to_string());
+        let now = chrono::NaiveDate::from_ymd_opt(2025, 3, 9).unwrap().and_hms_opt(8, 30, 0).unwrap();
+
+        let empty = get_snippets(folder.clone());
+        save_snippet(folder.clone(), "/sig", "--\nJane\nSent {{date}}".to_string()).unwrap();
+        save_snippet(folder.clone(), "/todo", "- [ ] ".to_string()).unwrap();
+        save_snippet(folder.clone(), "/todo", "- [ ] {{task}}".to_string()).unwrap();
+        let rejected = save_snippet(folder.clone(), "  ", "body".to_string());
+        let snippets = get_snippets(folder.clone());
+        let signature = expand_snippet_at(folder.clone(), "/sig", now);
+        let todo = expand_snippet_at(folder.clone(), "/todo", now);
+        let unknown = expand_snippet_at(folder, "/nope", now);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(empty, Ok(BTreeMap::new()));
+        assert_eq!(rejected, Err("Snippet trigger cannot be empty".to_string()));
+        assert_eq!(snippets.unwrap().keys().collect::<Vec<_>>(), ["/sig", "/todo"]);
+        assert_eq!(signature, Ok(Some("--\nJane\nSent 2025-03-09".to_string())));
+        assert_eq!(todo, Ok(Some("- [ ] {{task}}".to_string())));
+        assert_eq!(unknown, Ok(None));
+    }
+}
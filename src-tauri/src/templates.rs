@@ -0,0 +1,137 @@
+use crate::config::get_loom_dir;
+use crate::file_ops::{create_file, validate_file_name};
+use chrono::{Local, NaiveDateTime};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+// {{name}} placeholders, allowing spaces inside the braces
+static PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\s*([\w.-]+)\s*\}\}").unwrap());
+
+/// Path of a template file in `.loom/templates/`
+fn template_path(folder_path: Option<String>, template_name: &str) -> Result<PathBuf, String> {
+    validate_file_name(template_name)?;
+    let loom_dir = get_loom_dir(folder_path)?;
+    Ok(loom_dir.join("templates").join(format!("{}.md", template_name)))
+}
+
+/// List the templates in `.loom/templates/`, by name without the `.md` extension
+pub fn list_templates(folder_path: Option<String>) -> Result<Vec<String>, String> {
+    let templates_dir = get_loom_dir(folder_path)?.join("templates");
+    if !templates_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&templates_dir)
+        .map_err(|e| format!("Failed to read templates directory: {}", e))?;
+
+    let mut templates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("md"))
+        .filter_map(|path| path.file_stem().and_then(|s| s.to_str()).map(String::from))
+        .collect();
+    templates.sort_by_key(|name| name.to_lowercase());
+
+    Ok(templates)
+}
+
+/// Read the contents of a template
+pub fn load_template(folder_path: Option<String>, template_name: &str) -> Result<String, String> {
+    let path = template_path(folder_path, template_name)?;
+    if !path.exists() {
+        return Err(format!("Template '{}' not found", template_name));
+    }
+
+    fs::read_to_string(&path).map_err(|e| format!("Failed to read template: {}", e))
+}
+
+/// Create a new file at `path` filled with a template
+pub fn apply_template(folder_path: Option<String>, template_name: &str, path: String) -> Result<(), String> {
+    let content = load_template(folder_path, template_name)?;
+    create_file(path, Some(content))
+}
+
+/// Fill in `{{key}}` placeholders from `variables`
+///
+/// `{{date}}` (YYYY-MM-DD) and `{{time}}` (HH:MM) come from `now` unless
+/// `variables` sets them. Placeholders with no value are left as written.
+pub fn fill_placeholders(text: &str, variables: &HashMap<String, String>, now: NaiveDateTime) -> String {
+    PLACEHOLDER_RE
+        .replace_all(text, |caps: &regex::Captures| {
+            let key = &caps[1];
+            match (variables.get(key), key) {
+                (Some(value), _) => value.clone(),
+                (None, "date") => now.format("%Y-%m-%d").to_string(),
+                (None, "time") => now.format("%H:%M").to_string(),
+                (None, _) => caps[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Load a template and fill in its placeholders for the current local time
+pub fn render_template(
+    folder_path: Option<String>,
+    template_name: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String, String> {
+    let template = load_template(folder_path, template_name)?;
+    Ok(fill_placeholders(&template, variables, Local::now().naive_local()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_and_apply_template() {
+        let dir = std::env::temp_dir().join(format!("loom-templates-{}", std::process::id()));
+        let templates_dir = dir.join(".loom").join("templates");
+        fs::create_dir_all(&templates_dir).unwrap();
+        fs::write(templates_dir.join("Meeting.md"), "# Meeting\n\n## Notes\n").unwrap();
+        fs::write(templates_dir.join("daily.md"), "---\ntags: [daily]\n---\n").unwrap();
+        fs::write(templates_dir.join("readme.txt"), "not a template").unwrap();
+        let folder = Some(dir.to_string_lossy().to_string());
+        let note = dir.join("today.md").to_string_lossy().to_string();
+
+        let templates = list_templates(folder.clone());
+        let applied = apply_template(folder.clone(), "daily", note.clone());
+        let content = fs::read_to_string(&note).unwrap();
+        let again = apply_template(folder.clone(), "Meeting", note.clone());
+        let missing = apply_template(folder.clone(), "weekly", dir.join("week.md").to_string_lossy().to_string());
+        let escaped = load_template(folder, "../config");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(templates, Ok(vec!["daily".to_string(), "Meeting".to_string()]));
+        assert!(applied.is_ok());
+        assert_eq!(content, "---\ntags: [daily]\n---\n");
+        assert_eq!(again, Err("File already exists".to_string()));
+        assert_eq!(missing, Err("Template 'weekly' not found".to_string()));
+        assert!(escaped.is_err());
+    }
+
+    fn fixed_now() -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap().and_hms_opt(12, 5, 0).unwrap()
+    }
+
+    #[test]
+    fn test_fill_builtin_date_and_time() {
+        let filled = fill_placeholders("# {{date}}\nStarted at {{ time }}", &HashMap::new(), fixed_now());
+
+        assert_eq!(filled, "# 2025-01-01\nStarted at 12:05");
+    }
+
+    #[test]
+    fn test_fill_custom_variables() {
+        let variables = HashMap::from([
+            ("title".to_string(), "Standup".to_string()),
+            ("date".to_string(), "tomorrow".to_string()),
+        ]);
+        let filled = fill_placeholders("# {{title}} ({{date}})\n{{attendees}}", &variables, fixed_now());
+
+        assert_eq!(filled, "# Standup (tomorrow)\n{{attendees}}");
+    }
+}
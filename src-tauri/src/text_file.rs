@@ -0,0 +1,299 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// A text file decoded to a string, with what's needed to save it back the same way
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedFile {
+    pub content: String,
+    /// WHATWG encoding name, e.g. "UTF-8", "UTF-16LE" or "windows-1252"
+    pub encoding: String,
+    /// Whether the file started with a byte order mark
+    pub bom: bool,
+    /// Set when the bytes didn't decode cleanly and invalid ones were replaced
+    pub warning: Option<String>,
+}
+
+/// The line ending a file was written with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// The ending used by most lines of `content`, LF if there are no line breaks
+    pub fn detect(content: &str) -> Self {
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        if crlf > lf {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// Convert `\n`-separated content to this line ending
+    pub fn apply(self, content: &str) -> String {
+        match self {
+            LineEnding::Lf => content.to_string(),
+            LineEnding::Crlf => normalize_line_endings(content).replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// How a text file is saved: its encoding, byte order mark and line ending
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextFormat {
+    pub encoding: String,
+    pub bom: bool,
+    pub line_ending: Option<LineEnding>,
+}
+
+impl TextFormat {
+    /// The format of the file at `path`, or plain UTF-8 if it can't be read
+    pub fn of_file(path: &Path) -> Self {
+        match read_text_file(path) {
+            Ok(decoded) => Self {
+                line_ending: Some(LineEnding::detect(&decoded.content)),
+                encoding: decoded.encoding,
+                bom: decoded.bom,
+            },
+            Err(_) => Self { encoding: UTF_8.name().to_string(), bom: false, line_ending: None },
+        }
+    }
+}
+
+/// Convert CRLF line endings to `\n`
+pub fn normalize_line_endings(content: &str) -> String {
+    content.replace("\r\n", "\n")
+}
+
+/// Decode file bytes, detecting the encoding
+///
+/// A byte order mark decides the encoding and is stripped. Without one,
+/// valid UTF-8 is taken as is and anything else goes to the detector;
+/// bytes it can't decode fall back to lossy UTF-8 with a warning.
+pub fn decode_bytes(bytes: &[u8]) -> DecodedFile {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (content, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return DecodedFile {
+            content: content.into_owned(),
+            encoding: encoding.name().to_string(),
+            bom: true,
+            warning: had_errors.then(|| format!("Some bytes were not valid {} and were replaced", encoding.name())),
+        };
+    }
+
+    if let Ok(content) = std::str::from_utf8(bytes) {
+        return DecodedFile {
+            content: content.to_string(),
+            encoding: UTF_8.name().to_string(),
+            bom: false,
+            warning: None,
+        };
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, false);
+    let (content, had_errors) = encoding.decode_without_bom_handling(bytes);
+
+    if had_errors {
+        return DecodedFile {
+            content: String::from_utf8_lossy(bytes).into_owned(),
+            encoding: UTF_8.name().to_string(),
+            bom: false,
+            warning: Some("Could not detect the file's encoding; invalid UTF-8 bytes were replaced".to_string()),
+        };
+    }
+
+    DecodedFile {
+        content: content.into_owned(),
+        encoding: encoding.name().to_string(),
+        bom: false,
+        warning: None,
+    }
+}
+
+/// Encode content in a named encoding, with a byte order mark if `bom` is set
+pub fn encode_content(content: &str, encoding: &str, bom: bool) -> Result<Vec<u8>, String> {
+    let encoding = Encoding::for_label(encoding.as_bytes())
+        .ok_or_else(|| format!("Unknown encoding: {}", encoding))?;
+
+    let mut bytes = Vec::new();
+    if bom {
+        match encoding {
+            e if e == UTF_8 => bytes.extend_from_slice(b"\xEF\xBB\xBF"),
+            e if e == UTF_16LE => bytes.extend_from_slice(b"\xFF\xFE"),
+            e if e == UTF_16BE => bytes.extend_from_slice(b"\xFE\xFF"),
+            _ => {}
+        }
+    }
+
+    // encoding_rs only decodes UTF-16, so encode it by hand
+    if encoding == UTF_16LE {
+        bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+    } else if encoding == UTF_16BE {
+        bytes.extend(content.encode_utf16().flat_map(u16::to_be_bytes));
+    } else {
+        let (encoded, _, had_unmappable) = encoding.encode(content);
+        if had_unmappable {
+            return Err(format!("Some characters can't be saved as {}", encoding.name()));
+        }
+        bytes.extend_from_slice(&encoded);
+    }
+
+    Ok(bytes)
+}
+
+/// Read a text file in whatever encoding it uses
+pub fn read_text_file(path: &Path) -> Result<DecodedFile, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let decoded = decode_bytes(&bytes);
+    if let Some(warning) = &decoded.warning {
+        eprintln!("{}: {}", path.display(), warning);
+    }
+    Ok(decoded)
+}
+
+/// Read a text file with its line endings converted to `\n`, and the ending it had
+pub fn read_normalized(path: &Path) -> Result<(String, LineEnding), String> {
+    let content = read_text_file(path)?.content;
+    let line_ending = LineEnding::detect(&content);
+    Ok((normalize_line_endings(&content), line_ending))
+}
+
+/// Write a file by writing a hidden temporary file next to it and renaming
+/// it into place, so an interrupted write never leaves the file half written
+///
+/// A symlink is followed so the file it points to is replaced rather than
+/// the link, and the temporary file gets the original file's permissions.
+fn write_file_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let name = target.file_name().ok_or("Invalid file path")?;
+    let temp_path = target.with_file_name(format!(".{}.{}.tmp", name.to_string_lossy(), std::process::id()));
+    let permissions = fs::metadata(&target).ok().map(|metadata| metadata.permissions());
+
+    let write = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        if let Some(permissions) = &permissions {
+            fs::set_permissions(&temp_path, permissions.clone())?;
+        }
+        fs::rename(&temp_path, &target)
+    };
+
+    write().map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to write file: {}", e)
+    })
+}
+
+/// Write a text file in the encoding it was read with
+///
+/// With a `line_ending`, `\n`-separated content is converted back to it.
+pub fn write_text_file(
+    path: &Path,
+    content: &str,
+    encoding: &str,
+    bom: bool,
+    line_ending: Option<LineEnding>,
+) -> Result<(), String> {
+    let content = match line_ending {
+        Some(line_ending) => line_ending.apply(content),
+        None => content.to_string(),
+    };
+    let bytes = encode_content(&content, encoding, bom)?;
+    write_file_atomic(path, &bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_bom_is_stripped() {
+        let decoded = decode_bytes(b"\xEF\xBB\xBF# Title\n");
+
+        assert_eq!(decoded.content, "# Title\n");
+        assert_eq!(decoded.encoding, "UTF-8");
+        assert!(decoded.bom);
+        assert_eq!(encode_content(&decoded.content, &decoded.encoding, decoded.bom).unwrap(), b"\xEF\xBB\xBF# Title\n");
+    }
+
+    #[test]
+    fn test_utf16le_round_trip() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend("Caf\u{e9} \u{2014} notes".encode_utf16().flat_map(u16::to_le_bytes));
+        let decoded = decode_bytes(&bytes);
+
+        assert_eq!(decoded.content, "Caf\u{e9} \u{2014} notes");
+        assert_eq!(decoded.encoding, "UTF-16LE");
+        assert!(decoded.bom);
+        assert_eq!(encode_content(&decoded.content, &decoded.encoding, decoded.bom).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_latin1_is_detected() {
+        let decoded = decode_bytes(b"Le caf\xE9 est tr\xE8s bon, d\xE9j\xE0 vu.");
+
+        assert_eq!(decoded.content, "Le caf\u{e9} est tr\u{e8}s bon, d\u{e9}j\u{e0} vu.");
+        assert_eq!(decoded.encoding, "windows-1252");
+        assert!(!decoded.bom);
+        assert!(decoded.warning.is_none());
+    }
+
+    #[test]
+    fn test_crlf_normalized_and_restored() {
+        let path = std::env::temp_dir().join(format!("loom-crlf-{}.md", std::process::id()));
+        fs::write(&path, "# Title\r\n\r\nText\r\n").unwrap();
+
+        let (content, line_ending) = read_normalized(&path).unwrap();
+        write_text_file(&path, &format!("{}More\n", content), "UTF-8", false, Some(line_ending)).unwrap();
+        let saved = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(content, "# Title\n\nText\n");
+        assert_eq!(line_ending, LineEnding::Crlf);
+        assert_eq!(saved, b"# Title\r\n\r\nText\r\nMore\r\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_keeps_symlink_and_permissions() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = std::env::temp_dir().join(format!("loom-symlink-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.md");
+        let link = dir.join("link.md");
+        fs::write(&target, "old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        write_text_file(&link, "new", "UTF-8", false, None).unwrap();
+        let link_kept = fs::symlink_metadata(&link).unwrap().file_type().is_symlink();
+        let content = fs::read_to_string(&target).unwrap();
+        let mode = fs::metadata(&target).unwrap().permissions().mode() & 0o777;
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(link_kept);
+        assert_eq!(content, "new");
+        assert_eq!(mode, 0o600);
+    }
+
+    #[test]
+    fn test_detect_line_ending() {
+        assert_eq!(LineEnding::detect("a\nb\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("a\r\nb\r\nc\n"), LineEnding::Crlf);
+        assert_eq!(LineEnding::detect("single line"), LineEnding::Lf);
+    }
+}
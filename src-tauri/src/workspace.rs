@@ -0,0 +1,112 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+// The folder file commands are confined to, if one has been set
+pub type WorkspaceRootHandle = Arc<Mutex<Option<PathBuf>>>;
+
+pub fn create_workspace_root_state() -> WorkspaceRootHandle {
+    Arc::new(Mutex::new(None))
+}
+
+/// Resolve a path to an absolute one with symlinks and `..` resolved
+///
+/// The path doesn't have to exist: its deepest existing ancestor is
+/// canonicalized and the missing names are appended to it.
+fn resolve_path(path: &Path) -> Result<PathBuf, String> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    let base = loop {
+        if let Ok(base) = existing.canonicalize() {
+            break base;
+        }
+        // `..` after a missing folder can't be resolved against the file system
+        let name = existing.file_name()
+            .ok_or_else(|| format!("Cannot resolve path: {}", path.display()))?;
+        missing.push(name);
+        existing = match existing.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+    };
+
+    Ok(missing.into_iter().rev().fold(base, |resolved, name| resolved.join(name)))
+}
+
+/// Check that a path is inside the workspace root, if there is one
+pub fn check_in_root(root: Option<&Path>, path: &str) -> Result<(), String> {
+    let Some(root) = root else {
+        return Ok(());
+    };
+
+    let path = Path::new(path);
+    let resolved = resolve_path(path)?;
+    if resolved.starts_with(root) {
+        Ok(())
+    } else {
+        Err(format!("Path is outside the workspace: {}", path.display()))
+    }
+}
+
+/// Check a command's path argument against the managed workspace root
+pub fn ensure_in_workspace(state: &State<WorkspaceRootHandle>, path: &str) -> Result<(), String> {
+    let root = state.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+
+    check_in_root(root.as_deref(), path)
+}
+
+/// Confine file commands to a folder, or lift the restriction with `None`
+#[tauri::command]
+pub fn set_workspace_root(
+    root: Option<String>,
+    workspace_root: State<WorkspaceRootHandle>,
+) -> Result<(), String> {
+    let root = match root {
+        Some(root) => {
+            let path = Path::new(&root);
+            if !path.is_dir() {
+                return Err("Workspace root is not a directory".to_string());
+            }
+            Some(path.canonicalize().map_err(|e| format!("Failed to resolve workspace root: {}", e))?)
+        }
+        None => None,
+    };
+
+    let mut state = workspace_root.lock()
+        .map_err(|e| format!("Failed to acquire workspace lock: {}", e))?;
+    *state = root;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_paths_checked_against_root() {
+        let dir = std::env::temp_dir().join(format!("loom-workspace-{}", std::process::id()));
+        let root = dir.join("vault");
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        fs::write(root.join("notes").join("a.md"), "").unwrap();
+        let canonical_root = root.canonicalize().unwrap();
+        let check = |path: PathBuf| check_in_root(Some(&canonical_root), &path.to_string_lossy());
+
+        let existing = check(root.join("notes").join("a.md"));
+        let new_file = check(root.join("notes").join("new").join("b.md"));
+        let escaped = check(root.join("notes").join("..").join("..").join("outside"));
+        let missing_escape = check(root.join("missing").join("..").join("..").join("outside"));
+        let sibling = check(dir.join("outside").join("c.md"));
+        let unrestricted = check_in_root(None, &dir.join("outside").to_string_lossy());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(existing.is_ok());
+        assert!(new_file.is_ok());
+        assert!(escaped.is_err());
+        assert!(missing_escape.is_err());
+        assert!(sibling.is_err());
+        assert!(unrestricted.is_ok());
+    }
+}
@@ -69,19 +69,33 @@ export interface ThemeConfig {
   name: string;
   author?: string;
   version?: string;
+  extends?: string;
   variables: Record<string, string>;
 }
 
+/**
+ * Result of loading the current theme
+ */
+export interface ThemeLoadResult {
+  theme: ThemeConfig;
+  // Set when the configured theme couldn't be loaded and a default was used
+  warning?: string;
+}
+
 /**
  * Application configuration
  */
 export interface AppConfig {
+  schema_version?: number;
   current_theme: string;
   status_bar_visible?: boolean;
   confirm_file_delete?: boolean;
   confirm_folder_delete?: boolean;
+  use_trash?: boolean;
   keybinds?: Record<string, string>;
   custom_settings?: Record<string, unknown>;
+  // Setting overrides keyed by file path relative to the folder
+  per_file_settings?: Record<string, Record<string, unknown>>;
 }
 
 /**
@@ -15,6 +15,14 @@ import { getFirstTextNode } from "./editor-utils";
 import { isImagePath } from "../utils/path-utils";
 import { getCursorPosition } from "../utils/cursor-utils";
 
+/**
+ * Result of saving a pasted image on the Rust side
+ */
+interface SavedImage {
+  path: string;
+  relative_path?: string;
+}
+
 /**
  * Convert a blob to base64 string
  */
@@ -134,11 +142,12 @@ export async function handleImagePaste(blob: Blob) {
     }
 
     // Save image via Rust backend
-    const imagePath = await invoke<string>("save_image_from_clipboard", {
+    const savedImage = await invoke<SavedImage>("save_image_from_clipboard", {
       base64Data,
       saveDir: resolvedSaveDir,
       filenamePrefix: "pasted",
     });
+    const imagePath = savedImage.path;
 
     // Insert image at cursor
     await insertImageAtCursor(imagePath, "image");
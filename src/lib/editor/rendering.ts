@@ -9,6 +9,7 @@ import { invoke, convertFileSrc } from "@tauri-apps/api/core";
 import katex from "katex";
 import { RenderRequest, LineRenderResult } from "../core/types";
 import { editor } from "../core/dom";
+import { state } from "../core/state";
 
 /**
  * Escape HTML entities
@@ -159,6 +160,7 @@ export async function renderMarkdownLine(
 
   try {
     const result = await invoke<LineRenderResult>("render_markdown", {
+      folderPath: state.currentFolder,
       request,
     });
 
@@ -188,8 +190,13 @@ export async function renderMarkdownBatch(
   requests: RenderRequest[]
 ): Promise<LineRenderResult[]> {
   try {
+    // Later requests of the same document leave it out and share the first one's
+    const sent = requests.map((req, index) =>
+      index > 0 && req.all_lines === requests[0].all_lines ? { ...req, all_lines: [] } : req
+    );
     const results = await invoke<LineRenderResult[]>("render_markdown_batch", {
-      requests,
+      folderPath: state.currentFolder,
+      requests: sent,
     });
 
     // Post-process all results to convert image paths and add LaTeX rendering
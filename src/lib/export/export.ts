@@ -108,16 +108,18 @@ async function embedImagesAsBase64(html: string): Promise<string> {
 async function renderAllLinesToHTML(): Promise<string[]> {
   const allLines = getAllLines();
 
-  // Create requests for batch rendering
+  // Create requests for batch rendering; only the first carries the document,
+  // the rest share it
   const requests: RenderRequest[] = allLines.map((line, index) => ({
     line,
     line_index: index,
-    all_lines: allLines,
+    all_lines: index === 0 ? allLines : [],
     is_editing: false,
   }));
 
   try {
     const results = await invoke<LineRenderResult[]>("render_markdown_batch", {
+      folderPath: state.currentFolder,
       requests,
     });
 
@@ -325,6 +327,15 @@ export async function exportToHTML(): Promise<void> {
       user-select: none;
     }
 
+    hr.hr {
+      border: none;
+      border-top: 1px solid #e1e4e8;
+    }
+
+    hr.hr-dashed {
+      border-top-style: dashed;
+    }
+
     /* Code blocks */
     .code-block-start,
     .code-block-end {
@@ -700,6 +711,15 @@ export async function exportToPDF(): Promise<void> {
       user-select: none;
     }
 
+    hr.hr {
+      border: none;
+      border-top: 1px solid #e1e4e8;
+    }
+
+    hr.hr-dashed {
+      border-top-style: dashed;
+    }
+
     /* Code blocks */
     .code-block-start,
     .code-block-end {
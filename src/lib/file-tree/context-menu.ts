@@ -466,7 +466,7 @@ async function deleteItem(itemPath: string, isDir: boolean) {
     console.log("[STEP 5] About to call delete_folder for:", itemPath);
 
     try {
-      await invoke("delete_folder", { path: itemPath });
+      await invoke("delete_folder", { folderPath: state.currentFolder, path: itemPath });
       console.log("[STEP 6] Folder deleted successfully:", itemPath);
 
       // Refresh the file tree
@@ -500,7 +500,7 @@ async function deleteItem(itemPath: string, isDir: boolean) {
 
     try {
       console.log("[STEP 3] User CONFIRMED file deletion - proceeding");
-      await invoke("delete_file", { path: itemPath });
+      await invoke("delete_file", { folderPath: state.currentFolder, path: itemPath });
       console.log("[STEP 4] File deleted successfully:", itemPath);
 
       // If deleting the currently open file, clear the editor
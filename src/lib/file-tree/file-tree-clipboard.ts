@@ -159,9 +159,9 @@ export async function deleteSelectedItems() {
       const isDir = item?.getAttribute("data-is-dir") === "true";
 
       if (isDir) {
-        await invoke("delete_folder", { path });
+        await invoke("delete_folder", { folderPath: state.currentFolder, path });
       } else {
-        await invoke("delete_file", { path });
+        await invoke("delete_file", { folderPath: state.currentFolder, path });
       }
     }
 
@@ -26,6 +26,9 @@ let fileSystemEventUnlisten: UnlistenFn | null = null;
 interface FileSystemEvent {
   event_type: string;
   path: string;
+  // Set for renames where both halves were seen
+  old_path?: string;
+  new_path?: string;
 }
 
 /**
@@ -43,6 +46,10 @@ async function setupFileSystemListener() {
     "file-system-change",
     (event: Event<FileSystemEvent>) => {
       console.log("File system change detected:", event.payload);
+      // Content changes don't affect the tree structure
+      if (event.payload.event_type === "modify") {
+        return;
+      }
       // Refresh the file tree to reflect changes
       refreshFileTree();
     }
@@ -8,6 +8,9 @@ import type { FileSearchResult, SearchOptions } from './search-state';
 import { setMultiFileResults } from './search-state';
 import { state } from '../core/state';
 
+// Id of the latest folder search; results of older searches are dropped
+let latestSearchId = 0;
+
 /**
  * Search across all files in the current folder
  */
@@ -17,18 +20,25 @@ export async function searchInFolder(query: string, options: SearchOptions): Pro
     return [];
   }
 
+  const searchId = ++latestSearchId;
   try {
     const results = await invoke<FileSearchResult[]>('search_in_directory', {
       query,
       dirPath: state.currentFolder,
       options,
+      searchId,
     });
 
+    if (searchId !== latestSearchId) {
+      return results;
+    }
     setMultiFileResults(results);
     return results;
   } catch (error) {
     console.error('Multi-file search failed:', error);
-    setMultiFileResults([]);
+    if (searchId === latestSearchId) {
+      setMultiFileResults([]);
+    }
     return [];
   }
 }
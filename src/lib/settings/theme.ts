@@ -3,7 +3,7 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
-import { ThemeConfig, AppConfig } from "../core/types";
+import { ThemeConfig, ThemeLoadResult, AppConfig } from "../core/types";
 import { state } from "../core/state";
 
 /**
@@ -45,10 +45,10 @@ export async function initializeTheme(): Promise<void> {
     state.currentTheme = "dark";
     state.availableThemes = ["dark", "light"];
     // Apply default dark theme
-    const defaultTheme = await invoke<ThemeConfig>("get_current_theme", {
+    const defaultTheme = await invoke<ThemeLoadResult>("get_current_theme", {
       folderPath: null
     });
-    applyThemeVariables(defaultTheme);
+    applyThemeVariables(defaultTheme.theme);
   }
 }
 
@@ -172,9 +172,13 @@ export async function exportTheme(themeName: string, destPath: string): Promise<
  */
 export async function getCurrentTheme(): Promise<ThemeConfig> {
   try {
-    return await invoke<ThemeConfig>("get_current_theme", {
+    const result = await invoke<ThemeLoadResult>("get_current_theme", {
       folderPath: state.currentFolder
     });
+    if (result.warning) {
+      console.warn(result.warning);
+    }
+    return result.theme;
   } catch (error) {
     console.error("Failed to get current theme:", error);
     throw error;